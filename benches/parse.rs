@@ -0,0 +1,181 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::VecDeque,
+    hint::black_box,
+    marker::PhantomData,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use criterion::*;
+use futures::StreamExt;
+use quantx_core::protocol::{
+    stream::{BorrowedExchangeStream, ExchangeStream},
+    transformer::{BorrowedTransformer, Transformer},
+    websocket::{WebsocketJsonParser, WsError, WsMessage},
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use smol_str::SmolStr;
+use utils::BENCH_MSG_COUNT;
+
+#[allow(dead_code)]
+mod utils;
+
+const TRADE: &str = r#"{"e":"trade","E":1700000000001,"s":"BTCUSDT","t":12345,"p":"64123.10","q":"0.01500","T":1700000000000,"m":true}"#;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Trade {
+    symbol: SmolStr,
+    price: Decimal,
+    amount: Decimal,
+    time: u64,
+}
+
+#[derive(Debug)]
+struct BenchError;
+
+impl From<Box<dyn std::error::Error>> for BenchError {
+    fn from(_: Box<dyn std::error::Error>) -> Self {
+        Self
+    }
+}
+
+#[derive(Deserialize)]
+struct TradeOwned {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    amount: String,
+    #[serde(rename = "T")]
+    time: u64,
+}
+
+#[derive(Deserialize)]
+struct TradeBorrowed<'a> {
+    #[serde(rename = "s")]
+    symbol: SmolStr,
+    #[serde(rename = "p")]
+    price: &'a str,
+    #[serde(rename = "q")]
+    amount: &'a str,
+    #[serde(rename = "T")]
+    time: u64,
+}
+
+struct OwnedTrades;
+
+impl Transformer for OwnedTrades {
+    type Error = BenchError;
+    type Input = TradeOwned;
+    type Output = Trade;
+    type OutputIter = Option<Result<Trade, BenchError>>;
+
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        Some(Ok(Trade {
+            symbol: SmolStr::new(&input.symbol),
+            price: Decimal::from_str(&input.price).ok()?,
+            amount: Decimal::from_str(&input.amount).ok()?,
+            time: input.time,
+        }))
+    }
+}
+
+struct BorrowedTrades;
+
+impl BorrowedTransformer for BorrowedTrades {
+    type Error = BenchError;
+    type Input<'de> = TradeBorrowed<'de>;
+    type Output = Trade;
+    type OutputIter = Option<Result<Trade, BenchError>>;
+
+    fn transform(&mut self, input: Self::Input<'_>) -> Self::OutputIter {
+        Some(Ok(Trade {
+            symbol: input.symbol,
+            price: Decimal::from_str(input.price).ok()?,
+            amount: Decimal::from_str(input.amount).ok()?,
+            time: input.time,
+        }))
+    }
+}
+
+fn frames(count: usize) -> impl futures::Stream<Item = Result<WsMessage, WsError>> + Unpin {
+    futures::stream::iter(std::iter::repeat_n(WsMessage::text(TRADE), count).map(Ok))
+}
+
+fn drive_owned(count: usize) {
+    let stream = ExchangeStream::<WebsocketJsonParser, _, _> {
+        stream: frames(count),
+        transformer: OwnedTrades,
+        buffer: VecDeque::with_capacity(1),
+        protocol_marker: PhantomData,
+    };
+
+    futures::executor::block_on(stream.for_each(|trade| {
+        black_box(trade.unwrap());
+        std::future::ready(())
+    }));
+}
+
+fn drive_borrowed(count: usize) {
+    let stream = BorrowedExchangeStream::<WebsocketJsonParser, _, _>::new(frames(count), BorrowedTrades);
+
+    futures::executor::block_on(stream.for_each(|trade| {
+        black_box(trade.unwrap());
+        std::future::ready(())
+    }));
+}
+
+fn report_allocations(name: &str, drive: fn(usize)) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    drive(BENCH_MSG_COUNT);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!("{name}: {:.3} allocations/message", allocations as f64 / BENCH_MSG_COUNT as f64);
+}
+
+fn parse_trade(c: &mut Criterion) {
+    let mut g = c.benchmark_group("parse::trade");
+
+    g.throughput(Throughput::Elements(BENCH_MSG_COUNT as u64));
+    g.sample_size(10).warm_up_time(Duration::from_secs(1));
+    g.measurement_time(Duration::from_secs(10));
+
+    report_allocations("owned", drive_owned);
+    report_allocations("borrowed", drive_borrowed);
+
+    g.bench_function("owned", |b| b.iter(|| drive_owned(BENCH_MSG_COUNT)));
+    g.bench_function("borrowed", |b| b.iter(|| drive_borrowed(BENCH_MSG_COUNT)));
+
+    g.finish();
+}
+
+criterion_group!(parse_bench, parse_trade);
+criterion_main!(parse_bench);
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use derive_more::Constructor;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Constructor, Serialize, Deserialize)]
+pub struct Level {
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrderBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    pub fn from_levels<Bids, Asks>(bids: Bids, asks: Asks) -> Self
+    where
+        Bids: IntoIterator<Item = Level>,
+        Asks: IntoIterator<Item = Level>,
+    {
+        let mut book = Self::default();
+        bids.into_iter().for_each(|level| book.apply(Side::Bid, level));
+        asks.into_iter().for_each(|level| book.apply(Side::Ask, level));
+
+        book
+    }
+
+    // A zero amount removes the price level.
+    pub fn apply(&mut self, side: Side, level: Level) {
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+
+        if level.amount.is_zero() {
+            levels.remove(&level.price);
+        } else {
+            levels.insert(level.price, level.amount);
+        }
+    }
+
+    pub fn bids(&self) -> impl Iterator<Item = Level> + '_ {
+        self.bids.iter().rev().map(|(price, amount)| Level::new(*price, *amount))
+    }
+
+    pub fn asks(&self) -> impl Iterator<Item = Level> + '_ {
+        self.asks.iter().map(|(price, amount)| Level::new(*price, *amount))
+    }
+
+    pub fn best_bid(&self) -> Option<Level> {
+        self.bids().next()
+    }
+
+    pub fn best_ask(&self) -> Option<Level> {
+        self.asks().next()
+    }
+
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.best_bid()?.price + self.best_ask()?.price) / Decimal::TWO)
+    }
+
+    pub fn is_crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some(bid), Some(ask)) if bid.price >= ask.price)
+    }
+}
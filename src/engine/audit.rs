@@ -1 +1,284 @@
-pub trait AuditManager {}
+use std::{
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    io::{LineWriter, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use derive_more::Constructor;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tracing::error;
+
+use crate::{
+    engine::{
+        BlockReason,
+        EngineEvent,
+        kill_switch::KillSwitchTrip,
+        order::{ExecutionRequest, OrderRequest},
+        state::EngineState,
+    },
+    transport::channel::SyncTx,
+};
+
+const AUDIT_TREE: &str = "audit";
+
+// Compact view of the state, taken before each input and diffed against the state after it.
+pub trait AuditState {
+    type Snapshot;
+    type Diff: Debug + Clone;
+
+    fn snapshot(&self) -> Self::Snapshot;
+
+    fn diff(&self, before: &Self::Snapshot) -> Self::Diff;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditInput<Event> {
+    Start,
+    Event(Event),
+    Stop,
+}
+
+// Everything needed to reconstruct why the engine sent, or refused, a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord<Event, Diff, Refusal> {
+    pub seq: usize,
+    pub time: DateTime<Utc>,
+    pub input: AuditInput<Event>,
+    pub diff: Diff,
+    pub sent: Vec<ExecutionRequest>,
+    pub refused: Vec<(OrderRequest, Refusal)>,
+    pub blocked: Vec<(OrderRequest, BlockReason)>,
+    pub tripped: Option<KillSwitchTrip>,
+}
+
+pub type EngineAuditRecord<State, Refusal> =
+    AuditRecord<EngineEvent<<State as EngineState>::Market, <State as EngineState>::Account>, <State as AuditState>::Diff, Refusal>;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("audit I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to serialise audit record: {0}")]
+    Serialise(#[from] serde_json::Error),
+
+    #[error("audit KV store error: {0}")]
+    Kv(#[from] sled::Error),
+}
+
+// Write failures are logged rather than stopping the engine.
+pub trait AuditManager<Record> {
+    fn record(&mut self, record: Record);
+
+    // The engine only snapshots and diffs state for enabled managers.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoAudit;
+
+impl<Record> AuditManager<Record> for NoAudit {
+    fn record(&mut self, _: Record) {}
+
+    fn is_enabled(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Constructor)]
+pub struct AuditTx<Tx> {
+    tx: Tx,
+}
+
+impl<Tx, Record> AuditManager<Record> for AuditTx<Tx>
+where
+    Tx: SyncTx<Record>,
+{
+    fn record(&mut self, record: Record) {
+        if let Err(error) = self.tx.send(record) {
+            error!(?error, "Failed to send AuditRecord, audit receiver dropped.");
+        }
+    }
+}
+
+// JSON lines appended to a file, flushed per record so the journal survives the process crashing.
+// Surviving an OS crash or power loss needs `with_sync_data`, at the cost of an fsync per record.
+#[derive(Debug)]
+pub struct AuditFile {
+    writer: LineWriter<File>,
+    is_sync_data: bool,
+}
+
+impl AuditFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            writer: LineWriter::new(file),
+            is_sync_data: false,
+        })
+    }
+
+    pub fn with_sync_data(self, is_sync_data: bool) -> Self {
+        Self { is_sync_data, ..self }
+    }
+
+    fn write<Record>(&mut self, record: &Record) -> Result<(), AuditError>
+    where
+        Record: Serialize,
+    {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        if self.is_sync_data {
+            self.writer.get_ref().sync_data()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<Record> AuditManager<Record> for AuditFile
+where
+    Record: Serialize,
+{
+    fn record(&mut self, record: Record) {
+        if let Err(error) = self.write(&record) {
+            error!(?error, "Failed to write AuditRecord to file.");
+        }
+    }
+}
+
+// JSON records keyed by big-endian run id then seq, so they iterate in order. Seq restarts with
+// every engine, the run id keeps a reopened store from overwriting earlier runs.
+#[derive(Debug, Clone)]
+pub struct AuditKv {
+    tree: sled::Tree,
+    run: u64,
+}
+
+impl AuditKv {
+    pub fn new(tree: sled::Tree, run: u64) -> Self {
+        Self { tree, run }
+    }
+
+    // Each open starts a new run, with an id greater than any earlier run's.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let db = sled::open(path)?;
+        let run = db.generate_id()?;
+
+        Ok(Self::new(db.open_tree(AUDIT_TREE)?, run))
+    }
+
+    pub fn run(&self) -> u64 {
+        self.run
+    }
+
+    pub fn get<Record>(&self, run: u64, seq: usize) -> Result<Option<Record>, AuditError>
+    where
+        Record: DeserializeOwned,
+    {
+        self.tree
+            .get(Self::key(run, seq))?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(AuditError::from)
+    }
+
+    fn key(run: u64, seq: usize) -> [u8; 16] {
+        let mut key = [0; 16];
+        key[..8].copy_from_slice(&run.to_be_bytes());
+        key[8..].copy_from_slice(&(seq as u64).to_be_bytes());
+        key
+    }
+
+    fn insert<Record>(&self, seq: usize, record: &Record) -> Result<(), AuditError>
+    where
+        Record: Serialize,
+    {
+        self.tree.insert(Self::key(self.run, seq), serde_json::to_vec(record)?)?;
+
+        Ok(())
+    }
+}
+
+impl<Event, Diff, Refusal> AuditManager<AuditRecord<Event, Diff, Refusal>> for AuditKv
+where
+    AuditRecord<Event, Diff, Refusal>: Serialize,
+{
+    fn record(&mut self, record: AuditRecord<Event, Diff, Refusal>) {
+        if let Err(error) = self.insert(record.seq, &record) {
+            error!(?error, run = self.run, seq = record.seq, "Failed to insert AuditRecord into KV store.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Record = AuditRecord<u32, i64, String>;
+
+    fn record(seq: usize, input: u32) -> Record {
+        AuditRecord {
+            seq,
+            time: DateTime::from_timestamp(1_700_000_000 + seq as i64, 0).unwrap(),
+            input: AuditInput::Event(input),
+            diff: -1,
+            sent: Vec::new(),
+            refused: Vec::new(),
+            blocked: Vec::new(),
+            tripped: None,
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("audit-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn test_audit_file_appends_json_lines() {
+        let path = temp_path("file");
+        let _ = std::fs::remove_file(&path);
+
+        let mut audit = AuditFile::open(&path).unwrap().with_sync_data(true);
+        audit.record(record(1, 10));
+        audit.record(record(2, 20));
+        drop(audit);
+
+        // Reopening appends rather than truncating
+        AuditFile::open(&path).unwrap().record(record(1, 30));
+
+        let records = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Record>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records, vec![record(1, 10), record(2, 20), record(1, 30)]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_audit_kv_keeps_every_run() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let tree = db.open_tree(AUDIT_TREE).unwrap();
+
+        let mut first = AuditKv::new(tree.clone(), db.generate_id().unwrap());
+        first.record(record(1, 10));
+        first.record(record(2, 20));
+
+        let mut second = AuditKv::new(tree, db.generate_id().unwrap());
+        second.record(record(1, 30));
+
+        // Seq restarts with the run without overwriting the earlier one
+        assert_eq!(second.get::<Record>(first.run(), 1).unwrap(), Some(record(1, 10)));
+        assert_eq!(second.get::<Record>(first.run(), 2).unwrap(), Some(record(2, 20)));
+        assert_eq!(second.get::<Record>(second.run(), 1).unwrap(), Some(record(1, 30)));
+        assert_eq!(second.get::<Record>(second.run(), 2).unwrap(), None);
+    }
+}
@@ -1,10 +1,22 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+use tracing::debug;
 
 pub trait EngineClock {
     fn time(&self) -> DateTime<Utc>;
+
+    // Called with the exchange time of every engine event that carries one.
+    fn process_time_exchange(&mut self, _time_exchange: DateTime<Utc>) {}
+
+    // Local time minus exchange time of the last event, ie/ feed latency plus any clock offset.
+    fn skew(&self) -> Option<TimeDelta> {
+        None
+    }
 }
 
-#[derive(Debug)]
+// Engine time follows the exchange timestamps of processed events, so a replay is deterministic
+// regardless of how fast it runs. Clones share the same time. There is no skew, as replayed exchange
+// times are unrelated to the wall clock.
+#[derive(Debug, Clone)]
 pub struct SimulatedClock {
     inner: std::sync::Arc<parking_lot::RwLock<SimulatedClockInner>>,
 }
@@ -15,5 +27,72 @@ struct SimulatedClockInner {
     time_live_last_event: DateTime<Utc>,
 }
 
-#[derive(Debug)]
-pub struct LiveClock;
+impl SimulatedClock {
+    pub fn new(time_exchange_init: DateTime<Utc>) -> Self {
+        Self {
+            inner: std::sync::Arc::new(parking_lot::RwLock::new(SimulatedClockInner {
+                time_exchange_last_event: time_exchange_init,
+                time_live_last_event: Utc::now(),
+            })),
+        }
+    }
+
+    pub fn time_exchange_last_event(&self) -> DateTime<Utc> {
+        self.inner.read().time_exchange_last_event
+    }
+
+    pub fn time_live_last_event(&self) -> DateTime<Utc> {
+        self.inner.read().time_live_last_event
+    }
+}
+
+impl EngineClock for SimulatedClock {
+    fn time(&self) -> DateTime<Utc> {
+        self.inner.read().time_exchange_last_event
+    }
+
+    fn process_time_exchange(&mut self, time_exchange: DateTime<Utc>) {
+        let mut inner = self.inner.write();
+
+        // Events merged from several feeds arrive slightly out of order, time never moves backwards
+        if time_exchange < inner.time_exchange_last_event {
+            debug!(%time_exchange, time_exchange_last_event = %inner.time_exchange_last_event, "Ignoring out of order exchange time.");
+            return;
+        }
+
+        inner.time_exchange_last_event = time_exchange;
+        inner.time_live_last_event = Utc::now();
+    }
+}
+
+// Engine time is the local time, exchange times are only tracked for clock skew checks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LiveClock {
+    time_exchange_last_event: Option<DateTime<Utc>>,
+    time_live_last_event: Option<DateTime<Utc>>,
+}
+
+impl LiveClock {
+    pub fn time_exchange_last_event(&self) -> Option<DateTime<Utc>> {
+        self.time_exchange_last_event
+    }
+
+    pub fn time_live_last_event(&self) -> Option<DateTime<Utc>> {
+        self.time_live_last_event
+    }
+}
+
+impl EngineClock for LiveClock {
+    fn time(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn process_time_exchange(&mut self, time_exchange: DateTime<Utc>) {
+        self.time_exchange_last_event = Some(time_exchange);
+        self.time_live_last_event = Some(Utc::now());
+    }
+
+    fn skew(&self) -> Option<TimeDelta> {
+        Some(self.time_live_last_event? - self.time_exchange_last_event?)
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use crate::{
+    engine::{
+        order::{CancelRequest, OrderRequest},
+        state::InstrumentSpec,
+    },
+    route::InstrumentIndex,
+    transport::channel::{KanalSyncChannel, UnboundedRx},
+};
+
+// Operator intervention, processed ahead of any queued market or account events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Command {
+    // Strategy callbacks and timers are skipped, state keeps tracking events.
+    PauseStrategy,
+    ResumeStrategy,
+    CancelAllOrders { filter: InstrumentFilter },
+    // Flattens positions with market orders, which are still checked by the RiskManager but are not
+    // blocked by a tripped kill switch.
+    ClosePositions { filter: InstrumentFilter },
+    // Trips the kill switch for `KillReason::Manual`, reset re-arms its conditions.
+    TripKillSwitch,
+    ResetKillSwitch,
+    // No-op, lets time based checks run on a quiet feed, eg/ the kill switch market data timeout.
+    Heartbeat,
+    // A graceful shutdown cancels all open orders and runs `StrategyManager::on_stop`.
+    Shutdown { graceful: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum InstrumentFilter {
+    #[default]
+    All,
+    Instruments(Vec<InstrumentIndex>),
+    Product(SmolStr),
+}
+
+impl InstrumentFilter {
+    pub fn matches(&self, instrument: InstrumentIndex, spec: &InstrumentSpec) -> bool {
+        match self {
+            Self::All => true,
+            Self::Instruments(instruments) => instruments.contains(&instrument),
+            Self::Product(product) => spec.product == *product,
+        }
+    }
+}
+
+// Requests the engine sends on behalf of a command.
+pub trait CommandState {
+    fn cancel_requests(&self, filter: &InstrumentFilter) -> Vec<CancelRequest>;
+
+    // `seq` keeps the generated ClientOrderIds unique across commands. Quantity already being
+    // closed by working orders is netted out, so repeating the command does not close twice.
+    fn close_requests(&self, filter: &InstrumentFilter, seq: usize) -> Vec<OrderRequest>;
+}
+
+// Non-blocking source of commands, drained before every event.
+pub trait CommandRx {
+    fn try_recv(&mut self) -> Option<Command>;
+}
+
+impl CommandRx for VecDeque<Command> {
+    fn try_recv(&mut self) -> Option<Command> {
+        self.pop_front()
+    }
+}
+
+impl CommandRx for kanal::Receiver<Command> {
+    fn try_recv(&mut self) -> Option<Command> {
+        // A dropped command sender leaves the engine running on its event feed
+        kanal::Receiver::try_recv(self).ok().flatten()
+    }
+}
+
+impl CommandRx for UnboundedRx<KanalSyncChannel, Command> {
+    fn try_recv(&mut self) -> Option<Command> {
+        CommandRx::try_recv(&mut self.rx)
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book::Level,
+    engine::{
+        AccountUpdate,
+        EventTime,
+        order::{ClientOrderId, Offset, OrderSide},
+    },
+    route::{AssetIndex, InstrumentIndex},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketEvent {
+    pub instrument: InstrumentIndex,
+    pub time_exchange: DateTime<Utc>,
+    pub kind: MarketEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarketEventKind {
+    Trade(PublicTrade),
+    Quote(Quote),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicTrade {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub side: OrderSide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    pub bid: Option<Level>,
+    pub ask: Option<Level>,
+}
+
+impl Quote {
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.bid?.price + self.ask?.price) / Decimal::TWO)
+    }
+}
+
+impl EventTime for MarketEvent {
+    fn time_exchange(&self) -> Option<DateTime<Utc>> {
+        Some(self.time_exchange)
+    }
+}
+
+// Execution reports and balance snapshots from the exchange account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountEvent {
+    pub time_exchange: DateTime<Utc>,
+    pub kind: AccountEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountEventKind {
+    OrderUpdate(OrderUpdate),
+    Fill(Fill),
+    Balance { asset: AssetIndex, balance: Balance },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderUpdate {
+    pub instrument: InstrumentIndex,
+    pub cid: ClientOrderId,
+    pub kind: OrderUpdateKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderUpdateKind {
+    Accepted,
+    Rejected { reason: String },
+    Cancelled,
+    CancelRejected { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fill {
+    pub instrument: InstrumentIndex,
+    pub cid: ClientOrderId,
+    pub side: OrderSide,
+    pub offset: Offset,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub fee: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Balance {
+    pub total: Decimal,
+    pub free: Decimal,
+}
+
+impl EventTime for AccountEvent {
+    fn time_exchange(&self) -> Option<DateTime<Utc>> {
+        Some(self.time_exchange)
+    }
+}
+
+impl AccountUpdate for AccountEvent {
+    fn order_update(&self) -> Option<&OrderUpdate> {
+        match &self.kind {
+            AccountEventKind::OrderUpdate(update) => Some(update),
+            _ => None,
+        }
+    }
+
+    fn fill(&self) -> Option<&Fill> {
+        match &self.kind {
+            AccountEventKind::Fill(fill) => Some(fill),
+            _ => None,
+        }
+    }
+}
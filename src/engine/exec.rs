@@ -1 +1,24 @@
-pub trait ExecutionManager {}
+use derive_more::Constructor;
+use tracing::error;
+
+use crate::{engine::order::ExecutionRequest, transport::channel::SyncTx};
+
+pub trait ExecutionManager {
+    fn send(&mut self, request: ExecutionRequest);
+}
+
+#[derive(Debug, Clone, Constructor)]
+pub struct ExecutionTx<Tx> {
+    tx: Tx,
+}
+
+impl<Tx> ExecutionManager for ExecutionTx<Tx>
+where
+    Tx: SyncTx<ExecutionRequest>,
+{
+    fn send(&mut self, request: ExecutionRequest) {
+        if let Err(error) = self.tx.send(request) {
+            error!(?error, "Failed to send ExecutionRequest, execution layer receiver dropped.");
+        }
+    }
+}
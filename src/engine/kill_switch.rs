@@ -0,0 +1,354 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::channel::mpsc;
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+const REJECTS_WINDOW: TimeDelta = TimeDelta::minutes(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KillReason {
+    Manual,
+    Drawdown {
+        drawdown: Decimal,
+        limit: Decimal,
+    },
+    Rejects {
+        rejects: usize,
+        limit: usize,
+    },
+    // No market event for longer than the timeout.
+    MarketDataLost {
+        #[serde(with = "crate::engine::time_delta_ms")]
+        silence: TimeDelta,
+        #[serde(with = "crate::engine::time_delta_ms")]
+        timeout: TimeDelta,
+    },
+    ClockSkew {
+        #[serde(with = "crate::engine::time_delta_ms")]
+        skew: TimeDelta,
+        #[serde(with = "crate::engine::time_delta_ms")]
+        limit: TimeDelta,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KillSwitchTrip {
+    pub reason: KillReason,
+    pub time: DateTime<Utc>,
+}
+
+// Shared handle, clones trip the same switch, eg/ one held by the RiskManager and one by an
+// operator task. A tripped switch stays tripped until reset.
+#[derive(Debug, Clone, Default)]
+pub struct KillSwitch {
+    trip: Arc<Mutex<Option<KillSwitchTrip>>>,
+}
+
+impl KillSwitch {
+    // Returns false if the switch was already tripped, keeping the original reason.
+    pub fn trip(&self, reason: KillReason, time: DateTime<Utc>) -> bool {
+        let mut trip = self.trip.lock();
+        if trip.is_some() {
+            return false;
+        }
+
+        *trip = Some(KillSwitchTrip { reason, time });
+        true
+    }
+
+    pub fn reset(&self) {
+        *self.trip.lock() = None;
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.trip.lock().is_some()
+    }
+
+    pub fn tripped(&self) -> Option<KillSwitchTrip> {
+        self.trip.lock().clone()
+    }
+}
+
+// Conditions tripping the switch automatically, `None` disables the condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KillSwitchConfig {
+    // Fall of total pnl from its peak, in quote asset.
+    pub drawdown_max: Option<Decimal>,
+    pub rejects_per_minute_max: Option<usize>,
+    pub market_data_timeout: Option<TimeDelta>,
+    // Absolute `EngineClock::skew`, only measured by a `LiveClock`.
+    pub clock_skew_max: Option<TimeDelta>,
+}
+
+// Total pnl of the state, for the drawdown condition.
+pub trait PnlState {
+    fn pnl(&self) -> Decimal;
+}
+
+// Evaluates the conditions on every input the engine processes, which on a quiet live feed
+// requires `Command::Heartbeat`s for the market data timeout to be noticed.
+#[derive(Debug)]
+pub struct KillSwitchMonitor {
+    kill_switch: KillSwitch,
+    config: KillSwitchConfig,
+    pnl_peak: Option<Decimal>,
+    rejects: VecDeque<DateTime<Utc>>,
+    time_market_last: Option<DateTime<Utc>>,
+    // Trip the engine has already cancelled orders for
+    handled: Option<KillSwitchTrip>,
+    alerts: Vec<mpsc::UnboundedSender<KillSwitchTrip>>,
+}
+
+impl KillSwitchMonitor {
+    pub fn new(kill_switch: KillSwitch, config: KillSwitchConfig) -> Self {
+        Self {
+            kill_switch,
+            config,
+            pnl_peak: None,
+            rejects: VecDeque::new(),
+            time_market_last: None,
+            handled: None,
+            alerts: Vec::new(),
+        }
+    }
+
+    // Every trip the monitor observes, automatic or manual, is also sent to each alert receiver, eg/
+    // to page an operator. Dropped receivers are forgotten.
+    pub fn alerts(&mut self) -> mpsc::UnboundedReceiver<KillSwitchTrip> {
+        let (tx, rx) = mpsc::unbounded();
+        self.alerts.push(tx);
+        rx
+    }
+
+    pub fn kill_switch(&self) -> &KillSwitch {
+        &self.kill_switch
+    }
+
+    pub fn config(&self) -> &KillSwitchConfig {
+        &self.config
+    }
+
+    pub fn record_market(&mut self, time: DateTime<Utc>) {
+        self.time_market_last = Some(time);
+    }
+
+    pub fn record_reject(&mut self, time: DateTime<Utc>) {
+        self.rejects.push_back(time);
+    }
+
+    // Returns a trip, automatic or manual, the first time it is observed.
+    pub fn check(&mut self, time: DateTime<Utc>, pnl: Decimal, skew: Option<TimeDelta>) -> Option<KillSwitchTrip> {
+        // Conditions start over once a handled trip is reset, rather than tripping again at once
+        if self.handled.is_some() && !self.kill_switch.is_tripped() {
+            self.handled = None;
+            self.pnl_peak = None;
+            self.rejects.clear();
+            self.time_market_last = None;
+        }
+
+        if let Some(reason) = self.breach(time, pnl, skew) {
+            self.kill_switch.trip(reason, time);
+        }
+
+        let trip = self.kill_switch.tripped()?;
+        if self.handled.as_ref() == Some(&trip) {
+            return None;
+        }
+
+        self.handled = Some(trip.clone());
+        self.alerts.retain(|alert| alert.unbounded_send(trip.clone()).is_ok());
+        Some(trip)
+    }
+
+    fn breach(&mut self, time: DateTime<Utc>, pnl: Decimal, skew: Option<TimeDelta>) -> Option<KillReason> {
+        let pnl_peak = self.pnl_peak.map_or(pnl, |peak| peak.max(pnl));
+        self.pnl_peak = Some(pnl_peak);
+        if let Some(limit) = self.config.drawdown_max
+            && pnl_peak - pnl > limit
+        {
+            return Some(KillReason::Drawdown {
+                drawdown: pnl_peak - pnl,
+                limit,
+            });
+        }
+
+        while self.rejects.front().is_some_and(|reject| *reject <= time - REJECTS_WINDOW) {
+            self.rejects.pop_front();
+        }
+        if let Some(limit) = self.config.rejects_per_minute_max
+            && self.rejects.len() > limit
+        {
+            return Some(KillReason::Rejects {
+                rejects: self.rejects.len(),
+                limit,
+            });
+        }
+
+        // Silence is measured from the first check until market data has been seen
+        let time_market_last = *self.time_market_last.get_or_insert(time);
+        if let Some(timeout) = self.config.market_data_timeout
+            && time - time_market_last > timeout
+        {
+            return Some(KillReason::MarketDataLost {
+                silence: time - time_market_last,
+                timeout,
+            });
+        }
+
+        if let Some(limit) = self.config.clock_skew_max
+            && let Some(skew) = skew
+            && skew.abs() > limit
+        {
+            return Some(KillReason::ClockSkew { skew, limit });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn monitor(config: KillSwitchConfig) -> KillSwitchMonitor {
+        KillSwitchMonitor::new(KillSwitch::default(), config)
+    }
+
+    fn reason(trip: Option<KillSwitchTrip>) -> Option<KillReason> {
+        trip.map(|trip| trip.reason)
+    }
+
+    #[test]
+    fn test_drawdown_from_peak() {
+        let mut monitor = monitor(KillSwitchConfig {
+            drawdown_max: Some(dec!(100)),
+            ..Default::default()
+        });
+
+        assert_eq!(monitor.check(time(0), dec!(0), None), None);
+        assert_eq!(monitor.check(time(1), dec!(50), None), None);
+        assert_eq!(monitor.check(time(2), dec!(-50), None), None);
+        assert_eq!(
+            reason(monitor.check(time(3), dec!(-51), None)),
+            Some(KillReason::Drawdown {
+                drawdown: dec!(101),
+                limit: dec!(100)
+            })
+        );
+        assert!(monitor.kill_switch().is_tripped());
+    }
+
+    #[test]
+    fn test_rejects_per_minute() {
+        let config = KillSwitchConfig {
+            rejects_per_minute_max: Some(2),
+            ..Default::default()
+        };
+
+        let mut monitor = self::monitor(config);
+        [0, 30, 59].into_iter().for_each(|seconds| monitor.record_reject(time(seconds)));
+        assert_eq!(
+            reason(monitor.check(time(59), Decimal::ZERO, None)),
+            Some(KillReason::Rejects { rejects: 3, limit: 2 })
+        );
+
+        // A reject exactly one window old has left it
+        let mut monitor = self::monitor(config);
+        [0, 30, 60].into_iter().for_each(|seconds| monitor.record_reject(time(seconds)));
+        assert_eq!(monitor.check(time(60), Decimal::ZERO, None), None);
+    }
+
+    #[test]
+    fn test_market_data_timeout() {
+        let mut monitor = monitor(KillSwitchConfig {
+            market_data_timeout: Some(TimeDelta::seconds(5)),
+            ..Default::default()
+        });
+
+        // Silence counts from the first check, then from the last market event
+        assert_eq!(monitor.check(time(0), Decimal::ZERO, None), None);
+        assert_eq!(monitor.check(time(5), Decimal::ZERO, None), None);
+        monitor.record_market(time(4));
+        assert_eq!(monitor.check(time(9), Decimal::ZERO, None), None);
+        assert_eq!(
+            reason(monitor.check(time(10), Decimal::ZERO, None)),
+            Some(KillReason::MarketDataLost {
+                silence: TimeDelta::seconds(6),
+                timeout: TimeDelta::seconds(5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_clock_skew_either_way() {
+        let config = KillSwitchConfig {
+            clock_skew_max: Some(TimeDelta::seconds(1)),
+            ..Default::default()
+        };
+
+        let mut monitor = self::monitor(config);
+        assert_eq!(monitor.check(time(0), Decimal::ZERO, None), None);
+        assert_eq!(monitor.check(time(0), Decimal::ZERO, Some(TimeDelta::seconds(1))), None);
+        assert_eq!(
+            reason(monitor.check(time(0), Decimal::ZERO, Some(TimeDelta::seconds(-2)))),
+            Some(KillReason::ClockSkew {
+                skew: TimeDelta::seconds(-2),
+                limit: TimeDelta::seconds(1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_trip_observed_once_and_alerted() {
+        let mut monitor = monitor(KillSwitchConfig::default());
+        let mut alerts = monitor.alerts();
+        let dropped = monitor.alerts();
+        drop(dropped);
+
+        assert_eq!(monitor.check(time(0), Decimal::ZERO, None), None);
+        monitor.kill_switch().trip(KillReason::Manual, time(1));
+
+        let trip = KillSwitchTrip {
+            reason: KillReason::Manual,
+            time: time(1),
+        };
+        assert_eq!(monitor.check(time(2), Decimal::ZERO, None), Some(trip.clone()));
+        assert_eq!(monitor.check(time(3), Decimal::ZERO, None), None);
+
+        assert_eq!(alerts.try_next().unwrap(), Some(trip));
+        assert!(alerts.try_next().is_err());
+        assert_eq!(monitor.alerts.len(), 1);
+    }
+
+    #[test]
+    fn test_reset_restarts_conditions() {
+        let mut monitor = monitor(KillSwitchConfig {
+            drawdown_max: Some(dec!(100)),
+            rejects_per_minute_max: Some(1),
+            ..Default::default()
+        });
+
+        monitor.check(time(0), dec!(200), None);
+        assert!(monitor.check(time(1), dec!(0), None).is_some());
+        monitor.kill_switch().reset();
+
+        // The old peak and rejects are forgotten, so the same pnl no longer breaches
+        assert_eq!(monitor.check(time(2), dec!(0), None), None);
+        assert!(!monitor.kill_switch().is_tripped());
+
+        [3, 4].into_iter().for_each(|seconds| monitor.record_reject(time(seconds)));
+        assert_eq!(
+            reason(monitor.check(time(5), dec!(0), None)),
+            Some(KillReason::Rejects { rejects: 2, limit: 1 })
+        );
+    }
+}
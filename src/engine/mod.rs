@@ -1,20 +1,51 @@
-use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
 
-mod audit;
-mod clock;
-mod exec;
-mod risk;
-mod state;
-mod strategy;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{
+    Stream,
+    StreamExt,
+    stream::{self, PollNext},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::engine::{
+    audit::{AuditInput, AuditManager, AuditRecord, AuditState, EngineAuditRecord},
+    clock::EngineClock,
+    command::{Command, CommandRx, CommandState, InstrumentFilter},
+    event::{Fill, OrderUpdate, OrderUpdateKind},
+    exec::ExecutionManager,
+    kill_switch::{KillReason, KillSwitch, KillSwitchMonitor, KillSwitchTrip, PnlState},
+    order::{ExecutionRequest, OrderRequest},
+    risk::RiskManager,
+    state::EngineState,
+    strategy::{StrategyManager, Timer},
+};
+
+pub mod audit;
+pub mod clock;
+pub mod command;
+pub mod event;
+pub mod exec;
+pub mod kill_switch;
+pub mod order;
+pub mod risk;
+pub mod state;
+pub mod strategy;
 
 #[derive(Debug)]
-pub struct Engine<Clock, State, Execution, Risk, Strategy> {
+pub struct Engine<Clock, State, Execution, Risk, Strategy, Audit> {
     meta: EngineMeta,
     clock: Clock,
     state: State,
     exec: Execution,
     risk: Risk,
     strategy: Strategy,
+    audit: Audit,
+    strategy_paused: bool,
+    kill_switch: Option<KillSwitchMonitor>,
+    // Strategy timers with the time each is next due
+    timers: Vec<(Timer, DateTime<Utc>)>,
 }
 
 #[derive(Debug)]
@@ -24,4 +55,1036 @@ pub struct EngineMeta {
     time_init: DateTime<Utc>,
 }
 
+impl EngineMeta {
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+
+    pub fn time_init(&self) -> DateTime<Utc> {
+        self.time_init
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EngineEvent<Market, Account> {
+    Command(Command),
+    Market(Market),
+    Account(Account),
+}
+
+pub trait EventTime {
+    fn time_exchange(&self) -> Option<DateTime<Utc>>;
+}
+
+// Routes account events to the order update and fill callbacks of a strategy.
+pub trait AccountUpdate {
+    fn order_update(&self) -> Option<&OrderUpdate> {
+        None
+    }
+
+    fn fill(&self) -> Option<&Fill> {
+        None
+    }
+}
+
+impl<Market, Account> EventTime for EngineEvent<Market, Account>
+where
+    Market: EventTime,
+    Account: EventTime,
+{
+    fn time_exchange(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Command(_) => None,
+            Self::Market(market) => market.time_exchange(),
+            Self::Account(account) => account.time_exchange(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockReason {
+    KillSwitch,
+    // The state still tracks an open order with the same ClientOrderId, the venue would see a duplicate.
+    DuplicateClientOrderId,
+}
+
+// Everything the engine decided while processing a single event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineOutput<Refusal> {
+    pub seq: usize,
+    pub time: DateTime<Utc>,
+    pub sent: Vec<ExecutionRequest>,
+    pub refused: Vec<(OrderRequest, Refusal)>,
+    // Orders dropped by the engine itself, without reaching the RiskManager.
+    pub blocked: Vec<(OrderRequest, BlockReason)>,
+    pub tripped: Option<KillSwitchTrip>,
+}
+
+impl<Refusal> EngineOutput<Refusal> {
+    fn new(seq: usize, time: DateTime<Utc>) -> Self {
+        Self {
+            seq,
+            time,
+            sent: Vec::new(),
+            refused: Vec::new(),
+            blocked: Vec::new(),
+            tripped: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineShutdown {
+    Command { graceful: bool },
+    FeedEnded,
+}
+
+impl<Clock, State, Execution, Risk, Strategy, Audit> Engine<Clock, State, Execution, Risk, Strategy, Audit>
+where
+    Clock: EngineClock,
+    State: EngineState + AuditState + CommandState + PnlState,
+    State::Market: EventTime,
+    State::Account: EventTime + AccountUpdate,
+    Execution: ExecutionManager,
+    Risk: RiskManager<State>,
+    Strategy: StrategyManager<State>,
+    Audit: AuditManager<EngineAuditRecord<State, Risk::Refusal>>,
+{
+    pub fn new(clock: Clock, state: State, exec: Execution, risk: Risk, strategy: Strategy, audit: Audit) -> Self {
+        Self {
+            meta: EngineMeta {
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+                seq: 0,
+                time_init: clock.time(),
+            },
+            clock,
+            state,
+            exec,
+            risk,
+            strategy,
+            audit,
+            strategy_paused: false,
+            kill_switch: None,
+            timers: Vec::new(),
+        }
+    }
+
+    // While the monitor's `KillSwitch` is tripped every order is blocked before reaching the RiskManager,
+    // except those of `Command::ClosePositions`, cancels are still sent.
+    pub fn with_kill_switch(mut self, monitor: KillSwitchMonitor) -> Self {
+        self.kill_switch = Some(monitor);
+        self
+    }
+
+    pub fn meta(&self) -> &EngineMeta {
+        &self.meta
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn strategy(&self) -> &Strategy {
+        &self.strategy
+    }
+
+    pub fn is_strategy_paused(&self) -> bool {
+        self.strategy_paused
+    }
+
+    pub fn kill_switch(&self) -> Option<&KillSwitch> {
+        self.kill_switch.as_ref().map(KillSwitchMonitor::kill_switch)
+    }
+
+    // Arms the strategy timers and sends the requests of `StrategyManager::on_start`.
+    pub fn start(&mut self) -> EngineOutput<Risk::Refusal> {
+        self.meta.seq += 1;
+        let snapshot = self.snapshot();
+        let time = self.clock.time();
+
+        self.timers = self
+            .strategy
+            .timers()
+            .into_iter()
+            .filter(|timer| {
+                let is_valid = timer.interval > TimeDelta::zero();
+                if !is_valid {
+                    warn!(?timer, "Ignoring strategy Timer with non-positive interval.");
+                }
+                is_valid
+            })
+            .map(|timer| {
+                let due = time + timer.interval;
+                (timer, due)
+            })
+            .collect();
+
+        let requests = self.strategy.on_start(&self.state, time);
+        let mut output = EngineOutput::new(self.meta.seq, time);
+        self.dispatch(requests, false, &mut output);
+
+        self.record_audit(AuditInput::Start, snapshot, &output);
+        output
+    }
+
+    // Disarms the strategy timers and sends the requests of `StrategyManager::on_stop`.
+    pub fn stop(&mut self) -> EngineOutput<Risk::Refusal> {
+        self.meta.seq += 1;
+        let snapshot = self.snapshot();
+        let time = self.clock.time();
+        self.timers.clear();
+
+        let requests = self.strategy.on_stop(&self.state, time);
+        let mut output = EngineOutput::new(self.meta.seq, time);
+        self.dispatch(requests, false, &mut output);
+
+        self.record_audit(AuditInput::Stop, snapshot, &output);
+        output
+    }
+
+    // Dispatches in a fixed order: clock, state, strategy, kill switch, due timers, risk and execution,
+    // with every sent request recorded in state, and finally audit. Cancels of a kill switch trip are
+    // sent ahead of the strategy's requests.
+    pub fn process(&mut self, event: EngineEvent<State::Market, State::Account>) -> EngineOutput<Risk::Refusal> {
+        self.meta.seq += 1;
+        let snapshot = self.snapshot();
+
+        if let Some(time_exchange) = event.time_exchange() {
+            self.clock.process_time_exchange(time_exchange);
+        }
+        let time = self.clock.time();
+
+        let requests = match &event {
+            EngineEvent::Command(command) => self.process_command(command, time),
+            EngineEvent::Market(market) => {
+                self.state.process_market(market);
+                self.strategy_requests(|strategy, state| strategy.on_market(state, market))
+            },
+            EngineEvent::Account(account) => {
+                self.state.process_account(account);
+                self.strategy_requests(|strategy, state| {
+                    if let Some(update) = account.order_update() {
+                        strategy.on_order_update(state, update)
+                    } else if let Some(fill) = account.fill() {
+                        strategy.on_fill(state, fill)
+                    } else {
+                        strategy.on_account(state, account)
+                    }
+                })
+            },
+        };
+
+        // Flattening positions is what a tripped kill switch calls for, so the operator's closes are not blocked
+        let kill_switch_exempt = matches!(event, EngineEvent::Command(Command::ClosePositions { .. }));
+
+        let mut output = EngineOutput::new(self.meta.seq, time);
+        self.process_kill_switch(&event, &mut output);
+        self.dispatch(requests, kill_switch_exempt, &mut output);
+        self.process_timers(time, &mut output);
+
+        self.record_audit(AuditInput::Event(event), snapshot, &output);
+        output
+    }
+
+    // Skipped when nothing is audited, the snapshot and diff can cost more than the event itself.
+    fn snapshot(&self) -> Option<State::Snapshot> {
+        self.audit.is_enabled().then(|| self.state.snapshot())
+    }
+
+    fn record_audit(
+        &mut self,
+        input: AuditInput<EngineEvent<State::Market, State::Account>>,
+        snapshot: Option<State::Snapshot>,
+        output: &EngineOutput<Risk::Refusal>,
+    ) {
+        let Some(snapshot) = snapshot else {
+            return;
+        };
+
+        self.audit.record(AuditRecord {
+            seq: output.seq,
+            time: output.time,
+            input,
+            diff: self.state.diff(&snapshot),
+            sent: output.sent.clone(),
+            refused: output.refused.clone(),
+            blocked: output.blocked.clone(),
+            tripped: output.tripped.clone(),
+        });
+    }
+
+    fn process_kill_switch(&mut self, event: &EngineEvent<State::Market, State::Account>, output: &mut EngineOutput<Risk::Refusal>) {
+        let Some(monitor) = &mut self.kill_switch else {
+            return;
+        };
+
+        match event {
+            EngineEvent::Market(_) => monitor.record_market(output.time),
+            EngineEvent::Account(account)
+                if account
+                    .order_update()
+                    .is_some_and(|update| matches!(update.kind, OrderUpdateKind::Rejected { .. })) =>
+            {
+                monitor.record_reject(output.time)
+            },
+            _ => {},
+        }
+
+        let Some(trip) = monitor.check(output.time, self.state.pnl(), self.clock.skew()) else {
+            return;
+        };
+
+        error!(target: "kill_switch", seq = output.seq, reason = ?trip.reason, "Kill switch tripped, cancelling all open orders.");
+        let cancels = self.cancel_requests(&InstrumentFilter::All);
+        self.dispatch(cancels, false, output);
+        output.tripped = Some(trip);
+    }
+
+    fn strategy_requests<FnCallback>(&mut self, callback: FnCallback) -> Vec<ExecutionRequest>
+    where
+        FnCallback: FnOnce(&mut Strategy, &State) -> Vec<ExecutionRequest>,
+    {
+        if self.strategy_paused {
+            return Vec::new();
+        }
+
+        callback(&mut self.strategy, &self.state)
+    }
+
+    // Paused timers stay due, so they fire once on resume.
+    fn process_timers(&mut self, time: DateTime<Utc>, output: &mut EngineOutput<Risk::Refusal>) {
+        if self.strategy_paused {
+            return;
+        }
+
+        for index in 0..self.timers.len() {
+            let (timer, due) = &mut self.timers[index];
+            if *due > time {
+                continue;
+            }
+
+            // Intervals missed across a gap in the feed fire once
+            *due = time + timer.interval;
+            let id = timer.id.clone();
+
+            let requests = self.strategy.on_timer(&self.state, &id, time);
+            self.dispatch(requests, false, output);
+        }
+    }
+
+    fn dispatch(&mut self, requests: Vec<ExecutionRequest>, kill_switch_exempt: bool, output: &mut EngineOutput<Risk::Refusal>) {
+        for request in requests {
+            let request = match request {
+                ExecutionRequest::Open(order) if !kill_switch_exempt && self.kill_switch().is_some_and(KillSwitch::is_tripped) => {
+                    warn!(seq = output.seq, ?order, "Kill switch tripped, blocking OrderRequest.");
+                    output.blocked.push((order, BlockReason::KillSwitch));
+                    continue;
+                },
+                ExecutionRequest::Open(order) if self.state.is_order_open(&order) => {
+                    warn!(seq = output.seq, ?order, "Blocking OrderRequest reusing the ClientOrderId of an open order.");
+                    output.blocked.push((order, BlockReason::DuplicateClientOrderId));
+                    continue;
+                },
+                ExecutionRequest::Open(order) => match self.risk.check(&self.state, &order, output.time) {
+                    Ok(()) => ExecutionRequest::Open(order),
+                    Err(refusal) => {
+                        warn!(seq = output.seq, ?order, ?refusal, "RiskManager refused OrderRequest.");
+                        output.refused.push((order, refusal));
+                        continue;
+                    },
+                },
+                cancel => cancel,
+            };
+
+            self.state.process_request(&request, output.time);
+            self.exec.send(request.clone());
+            output.sent.push(request);
+        }
+    }
+
+    fn process_command(&mut self, command: &Command, time: DateTime<Utc>) -> Vec<ExecutionRequest> {
+        info!(seq = self.meta.seq, ?command, "Engine processing Command.");
+
+        match command {
+            Command::PauseStrategy => {
+                self.strategy_paused = true;
+                Vec::new()
+            },
+            Command::ResumeStrategy => {
+                self.strategy_paused = false;
+                Vec::new()
+            },
+            Command::CancelAllOrders { filter } => self.cancel_requests(filter),
+            Command::ClosePositions { filter } => self
+                .state
+                .close_requests(filter, self.meta.seq)
+                .into_iter()
+                .map(ExecutionRequest::Open)
+                .collect(),
+            Command::TripKillSwitch | Command::ResetKillSwitch => {
+                match (command, self.kill_switch()) {
+                    (_, None) => warn!(?command, "Ignoring kill switch Command, engine has no KillSwitchMonitor."),
+                    // Orders are cancelled as the monitor observes the trip
+                    (Command::TripKillSwitch, Some(kill_switch)) => {
+                        kill_switch.trip(KillReason::Manual, time);
+                    },
+                    (_, Some(kill_switch)) => kill_switch.reset(),
+                }
+                Vec::new()
+            },
+            Command::Heartbeat => Vec::new(),
+            Command::Shutdown { graceful: true } => self.cancel_requests(&InstrumentFilter::All),
+            Command::Shutdown { graceful: false } => Vec::new(),
+        }
+    }
+
+    fn cancel_requests(&self, filter: &InstrumentFilter) -> Vec<ExecutionRequest> {
+        self.state.cancel_requests(filter).into_iter().map(ExecutionRequest::Cancel).collect()
+    }
+
+    // Processes `events` between `start` and `stop`, until a `Command::Shutdown` or the end of the
+    // feed, eg/ a historical replay.
+    pub fn run<Events>(&mut self, events: Events) -> EngineShutdown
+    where
+        Events: IntoIterator<Item = EngineEvent<State::Market, State::Account>>,
+    {
+        self.run_with_commands(&mut VecDeque::new(), events)
+    }
+
+    // Pending `commands` are processed before each event, so they jump ahead of anything already
+    // queued in the feed.
+    pub fn run_with_commands<Commands, Events>(&mut self, commands: &mut Commands, events: Events) -> EngineShutdown
+    where
+        Commands: CommandRx,
+        Events: IntoIterator<Item = EngineEvent<State::Market, State::Account>>,
+    {
+        info!(version = %self.meta.version, seq = self.meta.seq, "Engine running.");
+        log_output("start", &self.start());
+
+        let shutdown = events
+            .into_iter()
+            .find_map(|event| self.process_commands(commands).or_else(|| self.process_and_check_shutdown(event)))
+            .or_else(|| self.process_commands(commands))
+            .unwrap_or(EngineShutdown::FeedEnded);
+
+        self.shutdown(shutdown)
+    }
+
+    pub async fn run_async<Events>(&mut self, events: Events) -> EngineShutdown
+    where
+        Events: Stream<Item = EngineEvent<State::Market, State::Account>>,
+    {
+        self.run_async_with_commands(stream::pending(), events).await
+    }
+
+    // Whenever both are ready, `commands` are polled ahead of `events`.
+    pub async fn run_async_with_commands<Commands, Events>(&mut self, commands: Commands, events: Events) -> EngineShutdown
+    where
+        Commands: Stream<Item = Command>,
+        Events: Stream<Item = EngineEvent<State::Market, State::Account>>,
+    {
+        info!(version = %self.meta.version, seq = self.meta.seq, "Engine running.");
+        log_output("start", &self.start());
+
+        // The end of the feed is marked by a `None`, as the command stream may never end
+        let commands = commands.map(|command| Some(EngineEvent::Command(command)));
+        let events = events.map(Some).chain(stream::once(async { None }));
+        let mut merged = std::pin::pin!(stream::select_with_strategy(commands, events, |_: &mut ()| PollNext::Left));
+
+        let mut shutdown = EngineShutdown::FeedEnded;
+        while let Some(Some(event)) = merged.next().await {
+            if let Some(command) = self.process_and_check_shutdown(event) {
+                shutdown = command;
+                break;
+            }
+        }
+
+        self.shutdown(shutdown)
+    }
+
+    fn process_commands<Commands>(&mut self, commands: &mut Commands) -> Option<EngineShutdown>
+    where
+        Commands: CommandRx,
+    {
+        while let Some(command) = commands.try_recv() {
+            if let Some(shutdown) = self.process_and_check_shutdown(EngineEvent::Command(command)) {
+                return Some(shutdown);
+            }
+        }
+
+        None
+    }
+
+    fn process_and_check_shutdown(&mut self, event: EngineEvent<State::Market, State::Account>) -> Option<EngineShutdown> {
+        let shutdown = match event {
+            EngineEvent::Command(Command::Shutdown { graceful }) => Some(EngineShutdown::Command { graceful }),
+            _ => None,
+        };
+
+        let output = self.process(event);
+        log_output("event", &output);
+
+        shutdown
+    }
+
+    // An immediate shutdown skips `StrategyManager::on_stop`, leaving open orders as they are.
+    fn shutdown(&mut self, shutdown: EngineShutdown) -> EngineShutdown {
+        match shutdown {
+            EngineShutdown::Command { graceful: true } => info!(seq = self.meta.seq, "Engine received graceful Shutdown command."),
+            EngineShutdown::Command { graceful: false } => {
+                info!(seq = self.meta.seq, "Engine received immediate Shutdown command.");
+                self.timers.clear();
+                return shutdown;
+            },
+            EngineShutdown::FeedEnded => info!(seq = self.meta.seq, "Engine event feed ended, shutting down."),
+        }
+
+        log_output("stop", &self.stop());
+        shutdown
+    }
+}
+
+fn log_output<Refusal>(stage: &'static str, output: &EngineOutput<Refusal>) {
+    debug!(
+        stage,
+        seq = output.seq,
+        sent = output.sent.len(),
+        refused = output.refused.len(),
+        blocked = output.blocked.len(),
+        "Engine processed event."
+    );
+}
+
+// chrono has no serde support for `TimeDelta`, it is journaled as milliseconds.
+mod time_delta_ms {
+    use chrono::TimeDelta;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(delta: &TimeDelta, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(delta.num_milliseconds())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<TimeDelta, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        i64::deserialize(deserializer).map(TimeDelta::milliseconds)
+    }
+}
+
 pub type BarterEngine<Clock, State, Execution, Risk, Strategy> = barter::engine::Engine<Clock, State, Execution, Risk, Strategy>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::{
+        engine::{
+            clock::SimulatedClock,
+            event::{AccountEvent, AccountEventKind, Fill, MarketEvent, MarketEventKind, PublicTrade},
+            order::{CancelRequest, ClientOrderId, Offset, OrderKind, OrderSide},
+            risk::{DefaultRiskManager, NoRefusal},
+            state::{DefaultEngineState, InstrumentSpec},
+            strategy::TimerId,
+        },
+        route::{AssetIndex, InstrumentIndex},
+    };
+
+    type Record = EngineAuditRecord<DefaultEngineState, NoRefusal>;
+    type Event = EngineEvent<MarketEvent, AccountEvent>;
+    type TestEngine = Engine<SimulatedClock, DefaultEngineState, Sent, DefaultRiskManager, DipBuyer, Journal>;
+
+    #[derive(Debug, Clone, Default)]
+    struct Journal(Arc<Mutex<Vec<Record>>>);
+
+    impl AuditManager<Record> for Journal {
+        fn record(&mut self, record: Record) {
+            self.0.lock().push(record);
+        }
+    }
+
+    // Records nothing, the engine should not even hand it records
+    #[derive(Debug, Clone, Default)]
+    struct Disabled(Journal);
+
+    impl AuditManager<Record> for Disabled {
+        fn record(&mut self, record: Record) {
+            self.0.record(record);
+        }
+
+        fn is_enabled(&self) -> bool {
+            false
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Sent(Arc<Mutex<Vec<ExecutionRequest>>>);
+
+    impl Sent {
+        // Cancels sent together come out of the order map in hash order
+        fn requests(&self) -> Vec<ExecutionRequest> {
+            let mut requests = self.0.lock().clone();
+            for batch in requests.chunk_by_mut(|a, b| matches!((a, b), (ExecutionRequest::Cancel(_), ExecutionRequest::Cancel(_)))) {
+                batch.sort_by_key(|request| match request {
+                    ExecutionRequest::Cancel(cancel) => cancel.cid.clone(),
+                    ExecutionRequest::Open(open) => open.cid.clone(),
+                });
+            }
+            requests
+        }
+    }
+
+    impl ExecutionManager for Sent {
+        fn send(&mut self, request: ExecutionRequest) {
+            self.0.lock().push(request);
+        }
+    }
+
+    // Buys every trade below 100, and cancels whatever is still open once a minute.
+    #[derive(Debug, Default)]
+    struct DipBuyer {
+        orders: usize,
+        timer_times: Vec<DateTime<Utc>>,
+    }
+
+    impl StrategyManager<DefaultEngineState> for DipBuyer {
+        fn timers(&self) -> Vec<Timer> {
+            vec![Timer::new(TimerId::new("sweep".into()), TimeDelta::minutes(1))]
+        }
+
+        fn on_market(&mut self, _: &DefaultEngineState, event: &MarketEvent) -> Vec<ExecutionRequest> {
+            match event.kind {
+                MarketEventKind::Trade(trade) if trade.price < dec!(100) => {
+                    self.orders += 1;
+                    vec![ExecutionRequest::Open(buy(self.orders, trade.price))]
+                },
+                _ => Vec::new(),
+            }
+        }
+
+        fn on_timer(&mut self, state: &DefaultEngineState, _: &TimerId, time: DateTime<Utc>) -> Vec<ExecutionRequest> {
+            self.timer_times.push(time);
+            state
+                .cancel_requests(&InstrumentFilter::All)
+                .into_iter()
+                .map(ExecutionRequest::Cancel)
+                .collect()
+        }
+    }
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn cid(order: usize) -> ClientOrderId {
+        ClientOrderId::new(format!("buy-{order}").into())
+    }
+
+    fn buy(order: usize, price: Decimal) -> OrderRequest {
+        OrderRequest {
+            instrument: InstrumentIndex::new(0),
+            cid: cid(order),
+            side: OrderSide::Buy,
+            offset: Offset::Open,
+            kind: OrderKind::Limit,
+            time_in_force: Default::default(),
+            price,
+            quantity: dec!(1),
+        }
+    }
+
+    fn cancel(order: usize) -> ExecutionRequest {
+        ExecutionRequest::Cancel(CancelRequest::new(InstrumentIndex::new(0), cid(order)))
+    }
+
+    fn trade(seconds: i64, price: Decimal) -> Event {
+        EngineEvent::Market(MarketEvent {
+            instrument: InstrumentIndex::new(0),
+            time_exchange: time(seconds),
+            kind: MarketEventKind::Trade(PublicTrade {
+                price,
+                quantity: dec!(0.5),
+                side: OrderSide::Sell,
+            }),
+        })
+    }
+
+    fn setup() -> (TestEngine, Sent, Journal) {
+        let state = DefaultEngineState::new([InstrumentSpec {
+            name: "BTC-USDT".into(),
+            product: "BTC".into(),
+            quote_asset: AssetIndex::new(0),
+            multiplier: Decimal::ONE,
+            tick_size: dec!(0.01),
+        }]);
+        let (sent, journal) = (Sent::default(), Journal::default());
+        let engine = Engine::new(
+            SimulatedClock::new(time(0)),
+            state,
+            sent.clone(),
+            DefaultRiskManager,
+            DipBuyer::default(),
+            journal.clone(),
+        );
+
+        (engine, sent, journal)
+    }
+
+    fn feed() -> Vec<Event> {
+        vec![
+            trade(10, dec!(99)),
+            trade(30, dec!(101)),
+            // Out of order, the clock stays at 30s
+            trade(20, dec!(98)),
+            // The timer due at 60s fires on the first event past it
+            trade(75, dec!(102)),
+            EngineEvent::Account(AccountEvent {
+                time_exchange: time(80),
+                kind: AccountEventKind::OrderUpdate(OrderUpdate {
+                    instrument: InstrumentIndex::new(0),
+                    cid: cid(1),
+                    kind: OrderUpdateKind::Cancelled,
+                }),
+            }),
+            // Intervals missed across the gap fire once
+            trade(200, dec!(103)),
+        ]
+    }
+
+    #[test]
+    fn test_replay_is_deterministic() {
+        let (mut engine, sent, journal) = setup();
+        assert_eq!(engine.run(feed()), EngineShutdown::FeedEnded);
+
+        assert_eq!(engine.clock().time(), time(200));
+        assert_eq!(engine.strategy().timer_times, vec![time(75), time(200)]);
+        assert_eq!(
+            sent.requests(),
+            vec![
+                ExecutionRequest::Open(buy(1, dec!(99))),
+                ExecutionRequest::Open(buy(2, dec!(98))),
+                cancel(1),
+                cancel(2),
+            ]
+        );
+
+        // Start, every event and stop, stamped with the exchange time rather than the wall clock
+        let records = journal.0.lock().clone();
+        let stamps = records.iter().map(|record| (record.seq, record.time)).collect::<Vec<_>>();
+        assert_eq!(
+            stamps,
+            vec![
+                (1, time(0)),
+                (2, time(10)),
+                (3, time(30)),
+                (4, time(30)),
+                (5, time(75)),
+                (6, time(80)),
+                (7, time(200)),
+                (8, time(200)),
+            ]
+        );
+
+        // Replaying the same feed reproduces every decision
+        let (mut replay, replay_sent, replay_journal) = setup();
+        replay.run(feed());
+        assert_eq!(*replay_sent.0.lock(), *sent.0.lock());
+        assert_eq!(*replay_journal.0.lock(), records);
+    }
+
+    #[test]
+    fn test_run_stops_at_shutdown_command() {
+        let (mut engine, sent, journal) = setup();
+        let mut feed = feed();
+        feed.insert(3, EngineEvent::Command(Command::Shutdown { graceful: true }));
+
+        assert_eq!(engine.run(feed), EngineShutdown::Command { graceful: true });
+
+        // Events queued behind the command are never processed, open orders are cancelled
+        assert_eq!(engine.clock().time(), time(30));
+        assert!(engine.strategy().timer_times.is_empty());
+        assert_eq!(sent.requests()[2..], [cancel(1), cancel(2)]);
+        assert_eq!(journal.0.lock().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_run_async_matches_run() {
+        let (mut engine, sent, journal) = setup();
+        assert_eq!(engine.run_async(stream::iter(feed())).await, EngineShutdown::FeedEnded);
+
+        let (mut replay, replay_sent, replay_journal) = setup();
+        replay.run(feed());
+        assert_eq!(*replay_sent.0.lock(), *sent.0.lock());
+        assert_eq!(*replay_journal.0.lock(), *journal.0.lock());
+    }
+
+    #[test]
+    fn test_disabled_audit_is_skipped() {
+        let (engine, sent, journal) = setup();
+        let disabled = Disabled::default();
+        let mut engine = Engine::new(
+            engine.clock,
+            engine.state,
+            sent.clone(),
+            DefaultRiskManager,
+            DipBuyer::default(),
+            disabled.clone(),
+        );
+
+        assert_eq!(engine.run(feed()), EngineShutdown::FeedEnded);
+        assert_eq!(sent.0.lock().len(), 4);
+        assert!(disabled.0.0.lock().is_empty());
+        assert!(journal.0.lock().is_empty());
+    }
+
+    #[test]
+    fn test_tripped_kill_switch_blocks_orders() {
+        let (engine, sent, journal) = setup();
+        let kill_switch = KillSwitch::default();
+        let mut engine = engine.with_kill_switch(KillSwitchMonitor::new(kill_switch.clone(), Default::default()));
+        engine.start();
+
+        assert_eq!(engine.process(trade(10, dec!(99))).sent, vec![ExecutionRequest::Open(buy(1, dec!(99)))]);
+
+        let output = engine.process(EngineEvent::Command(Command::TripKillSwitch));
+        assert_eq!(output.tripped.map(|trip| trip.reason), Some(KillReason::Manual));
+        assert_eq!(output.sent, vec![cancel(1)]);
+
+        // Never reaches the RiskManager or the execution layer, nor is it recorded in state
+        let output = engine.process(trade(20, dec!(98)));
+        assert!(output.sent.is_empty() && output.refused.is_empty());
+        assert_eq!(output.blocked, vec![(buy(2, dec!(98)), BlockReason::KillSwitch)]);
+        assert!(engine.state().order(InstrumentIndex::new(0), &cid(2)).is_none());
+        assert_eq!(journal.0.lock().last().unwrap().blocked, output.blocked);
+
+        kill_switch.reset();
+        let output = engine.process(trade(30, dec!(97)));
+        assert_eq!(output.sent, vec![ExecutionRequest::Open(buy(3, dec!(97)))]);
+        assert!(output.blocked.is_empty());
+        assert_eq!(sent.0.lock().len(), 3);
+    }
+
+    #[test]
+    fn test_open_client_order_id_is_blocked() {
+        let (mut engine, sent, _) = setup();
+        engine.start();
+
+        assert_eq!(engine.process(trade(10, dec!(99))).sent, vec![ExecutionRequest::Open(buy(1, dec!(99)))]);
+
+        // Reuses buy-1 while it is still open
+        engine.strategy.orders = 0;
+        let output = engine.process(trade(20, dec!(98)));
+        assert!(output.sent.is_empty() && output.refused.is_empty());
+        assert_eq!(output.blocked, vec![(buy(1, dec!(98)), BlockReason::DuplicateClientOrderId)]);
+        assert_eq!(engine.state().order(InstrumentIndex::new(0), &cid(1)).unwrap().request.price, dec!(99));
+
+        // Free to reuse once the venue confirms the cancel
+        engine.process(EngineEvent::Account(AccountEvent {
+            time_exchange: time(30),
+            kind: AccountEventKind::OrderUpdate(OrderUpdate {
+                instrument: InstrumentIndex::new(0),
+                cid: cid(1),
+                kind: OrderUpdateKind::Cancelled,
+            }),
+        }));
+        engine.strategy.orders = 0;
+        assert_eq!(engine.process(trade(40, dec!(97))).sent, vec![ExecutionRequest::Open(buy(1, dec!(97)))]);
+        assert_eq!(sent.0.lock().len(), 2);
+    }
+
+    #[test]
+    fn test_queued_commands_jump_ahead_of_events() {
+        let (mut engine, sent, journal) = setup();
+        let mut commands = VecDeque::from([Command::PauseStrategy]);
+
+        assert_eq!(engine.run_with_commands(&mut commands, feed()), EngineShutdown::FeedEnded);
+
+        // Paused before the first trade, so nothing is ever bought
+        assert!(sent.0.lock().is_empty());
+        assert_eq!(journal.0.lock()[1].input, AuditInput::Event(EngineEvent::Command(Command::PauseStrategy)));
+    }
+
+    #[tokio::test]
+    async fn test_immediate_shutdown_skips_on_stop() {
+        let (mut engine, sent, journal) = setup();
+        let commands = stream::iter([Command::Shutdown { graceful: false }]);
+
+        // Both are ready, the command wins
+        let shutdown = engine.run_async_with_commands(commands, stream::iter(feed())).await;
+        assert_eq!(shutdown, EngineShutdown::Command { graceful: false });
+        assert_eq!(engine.clock().time(), time(0));
+        assert!(sent.0.lock().is_empty());
+
+        let records = journal.0.lock();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.input != AuditInput::Stop));
+    }
+
+    #[test]
+    fn test_immediate_shutdown_leaves_orders_open() {
+        let (mut engine, sent, journal) = setup();
+        let mut feed = feed();
+        feed.insert(1, EngineEvent::Command(Command::Shutdown { graceful: false }));
+
+        assert_eq!(engine.run(feed), EngineShutdown::Command { graceful: false });
+        assert_eq!(*sent.0.lock(), vec![ExecutionRequest::Open(buy(1, dec!(99)))]);
+        assert_ne!(journal.0.lock().last().unwrap().input, AuditInput::Stop);
+    }
+
+    #[test]
+    fn test_paused_timers_fire_once_on_resume() {
+        let (mut engine, sent, _) = setup();
+        engine.start();
+        engine.process(trade(10, dec!(99)));
+
+        engine.process(EngineEvent::Command(Command::PauseStrategy));
+        assert!(engine.is_strategy_paused());
+
+        // State keeps tracking, the strategy sees nothing and the timer due at 60s stays due
+        assert!(engine.process(trade(20, dec!(98))).sent.is_empty());
+        assert!(engine.process(trade(200, dec!(97))).sent.is_empty());
+        assert!(engine.strategy().timer_times.is_empty());
+        assert_eq!(engine.state().order(InstrumentIndex::new(0), &cid(1)).unwrap().request.price, dec!(99));
+
+        let output = engine.process(EngineEvent::Command(Command::ResumeStrategy));
+        assert_eq!(output.sent, vec![cancel(1)]);
+        assert_eq!(engine.strategy().timer_times, vec![time(200)]);
+
+        // Next due a full interval after the resume
+        assert!(engine.process(trade(250, dec!(101))).sent.is_empty());
+        assert_eq!(engine.strategy().orders, 1);
+        assert_eq!(sent.0.lock().len(), 2);
+    }
+
+    // Long 1 with an open bid on each instrument, BTC-USDT and BTC-USD share a product.
+    fn portfolio() -> (TestEngine, Sent) {
+        let spec = |name: &str, product: &str| InstrumentSpec {
+            name: name.into(),
+            product: product.into(),
+            quote_asset: AssetIndex::new(0),
+            multiplier: Decimal::ONE,
+            tick_size: dec!(0.01),
+        };
+        let state = DefaultEngineState::new([spec("BTC-USDT", "BTC"), spec("ETH-USDT", "ETH"), spec("BTC-USD", "BTC")]);
+        let sent = Sent::default();
+        let mut engine = Engine::new(
+            SimulatedClock::new(time(0)),
+            state,
+            sent.clone(),
+            DefaultRiskManager,
+            DipBuyer::default(),
+            Journal::default(),
+        );
+        engine.start();
+
+        for index in 0..3 {
+            let instrument = InstrumentIndex::new(index);
+            engine.process(EngineEvent::Account(AccountEvent {
+                time_exchange: time(1),
+                kind: AccountEventKind::Fill(Fill {
+                    instrument,
+                    cid: ClientOrderId::new(format!("fill-{index}").into()),
+                    side: OrderSide::Buy,
+                    offset: Offset::Open,
+                    price: dec!(100),
+                    quantity: dec!(1),
+                    fee: Decimal::ZERO,
+                }),
+            }));
+            engine.process(EngineEvent::Market(MarketEvent {
+                instrument,
+                time_exchange: time(2),
+                kind: MarketEventKind::Trade(PublicTrade {
+                    price: dec!(100),
+                    quantity: dec!(1),
+                    side: OrderSide::Sell,
+                }),
+            }));
+            engine.state.process_request(
+                &ExecutionRequest::Open(OrderRequest {
+                    instrument,
+                    cid: ClientOrderId::new(format!("bid-{index}").into()),
+                    ..buy(0, dec!(90))
+                }),
+                time(2),
+            );
+        }
+        assert!(sent.0.lock().is_empty());
+
+        (engine, sent)
+    }
+
+    fn instruments(output: &EngineOutput<NoRefusal>) -> Vec<usize> {
+        let mut instruments: Vec<_> = output
+            .sent
+            .iter()
+            .map(|request| match request {
+                ExecutionRequest::Open(open) => open.instrument.index(),
+                ExecutionRequest::Cancel(cancel) => cancel.instrument.index(),
+            })
+            .collect();
+        instruments.sort();
+        instruments
+    }
+
+    #[test]
+    fn test_commands_apply_instrument_filter() {
+        let filters = [
+            (InstrumentFilter::All, vec![0, 1, 2]),
+            (InstrumentFilter::Instruments(vec![InstrumentIndex::new(1)]), vec![1]),
+            (InstrumentFilter::Product("BTC".into()), vec![0, 2]),
+        ];
+
+        for (filter, expected) in filters {
+            let (mut engine, _) = portfolio();
+            let output = engine.process(EngineEvent::Command(Command::CancelAllOrders { filter: filter.clone() }));
+            assert!(output.sent.iter().all(|request| matches!(request, ExecutionRequest::Cancel(_))));
+            assert_eq!(instruments(&output), expected, "{filter:?}");
+
+            let output = engine.process(EngineEvent::Command(Command::ClosePositions { filter: filter.clone() }));
+            assert!(output.sent.iter().all(|request| matches!(
+                request,
+                ExecutionRequest::Open(OrderRequest {
+                    side: OrderSide::Sell,
+                    offset: Offset::Close,
+                    kind: OrderKind::Market,
+                    ..
+                })
+            )));
+            assert_eq!(instruments(&output), expected, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn test_close_positions_bypass_tripped_kill_switch() {
+        let (engine, _) = portfolio();
+        let mut engine = engine.with_kill_switch(KillSwitchMonitor::new(KillSwitch::default(), Default::default()));
+
+        let output = engine.process(EngineEvent::Command(Command::TripKillSwitch));
+        assert_eq!(instruments(&output), vec![0, 1, 2]);
+
+        let output = engine.process(EngineEvent::Command(Command::ClosePositions { filter: InstrumentFilter::All }));
+        assert_eq!(instruments(&output), vec![0, 1, 2]);
+        assert!(output.blocked.is_empty());
+
+        // The strategy's orders are still blocked
+        let output = engine.process(trade(10, dec!(99)));
+        assert_eq!(output.blocked, vec![(buy(1, dec!(99)), BlockReason::KillSwitch)]);
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use derive_more::{Constructor, Display};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use crate::route::InstrumentIndex;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Display, Constructor, Serialize, Deserialize)]
+pub struct ClientOrderId(SmolStr);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+// Which position leg an order opens or closes. `Auto` nets against the opposite leg first, as on
+// venues without separate long and short positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Offset {
+    #[default]
+    Auto,
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderKind {
+    Limit,
+    Market,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimeInForce {
+    #[default]
+    GoodUntilCancelled,
+    ImmediateOrCancel,
+    FillOrKill,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderRequest {
+    pub instrument: InstrumentIndex,
+    pub cid: ClientOrderId,
+    pub side: OrderSide,
+    pub offset: Offset,
+    pub kind: OrderKind,
+    pub time_in_force: TimeInForce,
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Constructor, Serialize, Deserialize)]
+pub struct CancelRequest {
+    pub instrument: InstrumentIndex,
+    pub cid: ClientOrderId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExecutionRequest {
+    Open(OrderRequest),
+    Cancel(CancelRequest),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderStatus {
+    // Sent, not yet acknowledged by the exchange.
+    PendingNew,
+    Open,
+    PartiallyFilled,
+    PendingCancel,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Filled | Self::Cancelled | Self::Rejected)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order {
+    pub request: OrderRequest,
+    pub status: OrderStatus,
+    pub quantity_filled: Decimal,
+    pub time_update: DateTime<Utc>,
+}
+
+impl Order {
+    pub fn new(request: OrderRequest, time: DateTime<Utc>) -> Self {
+        Self {
+            request,
+            status: OrderStatus::PendingNew,
+            quantity_filled: Decimal::ZERO,
+            time_update: time,
+        }
+    }
+
+    pub fn quantity_remaining(&self) -> Decimal {
+        (self.request.quantity - self.quantity_filled).max(Decimal::ZERO)
+    }
+}
@@ -1,3 +0,0 @@
-pub trait RiskManager {}
-
-pub type BarterDefaultRiskManager<State> = barter::risk::DefaultRiskManager<State>;
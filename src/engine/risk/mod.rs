@@ -0,0 +1,34 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::order::OrderRequest;
+
+pub mod rule;
+
+pub use rule::{InstrumentLimits, OrderRateLimit, RiskRefusal, RuleRiskManager};
+
+// Only order requests are checked, cancels always reach the execution layer.
+pub trait RiskManager<State> {
+    type Refusal: Debug + Clone;
+
+    fn check(&mut self, state: &State, request: &OrderRequest, time: DateTime<Utc>) -> Result<(), Self::Refusal>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRiskManager;
+
+// Refusal of a manager that approves everything, unlike `Infallible` it can be journaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoRefusal {}
+
+impl<State> RiskManager<State> for DefaultRiskManager {
+    type Refusal = NoRefusal;
+
+    fn check(&mut self, _: &State, _: &OrderRequest, _: DateTime<Utc>) -> Result<(), Self::Refusal> {
+        Ok(())
+    }
+}
+
+pub type BarterDefaultRiskManager<State> = barter::risk::DefaultRiskManager<State>;
@@ -0,0 +1,509 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use fnv::FnvHashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+use thiserror::Error;
+
+use crate::{
+    engine::{
+        order::{OrderKind, OrderRequest, OrderSide},
+        risk::RiskManager,
+        state::{DefaultEngineState, InstrumentState},
+    },
+    route::InstrumentIndex,
+};
+
+// Limits of a single instrument, `None` disables the rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InstrumentLimits {
+    pub order_quantity_max: Option<Decimal>,
+    // In quote asset, ie/ price * quantity * multiplier.
+    pub order_notional_max: Option<Decimal>,
+    // Absolute net position, counting open orders on the same side as filled.
+    pub position_max: Option<Decimal>,
+    // Maximum relative distance of a limit price from the last trade, eg/ 0.05 for 5%.
+    pub price_band: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderRateLimit {
+    pub orders_max: usize,
+    pub window: TimeDelta,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+pub enum RiskRefusal {
+    #[error("unknown instrument {0:?}")]
+    UnknownInstrument(InstrumentIndex),
+
+    #[error("order quantity {quantity} exceeds limit {limit}")]
+    OrderQuantity { quantity: Decimal, limit: Decimal },
+
+    #[error("order notional {notional} exceeds limit {limit}")]
+    OrderNotional { notional: Decimal, limit: Decimal },
+
+    #[error("projected position {position} exceeds instrument limit {limit}")]
+    InstrumentPosition { position: Decimal, limit: Decimal },
+
+    #[error("projected position {position} exceeds limit {limit} of product {product}")]
+    ProductPosition { product: SmolStr, position: Decimal, limit: Decimal },
+
+    #[error("price {price} outside band {band} of last trade {trade_last}")]
+    PriceBand { price: Decimal, trade_last: Decimal, band: Decimal },
+
+    #[error("no market price to check price {price} against")]
+    PriceReferenceMissing { price: Decimal },
+
+    #[error("order rate exceeds {orders_max} orders per {window}")]
+    OrderRate {
+        orders_max: usize,
+        #[serde(with = "crate::engine::time_delta_ms")]
+        window: TimeDelta,
+    },
+}
+
+// Pre-trade checks against `DefaultEngineState`, rules are evaluated in a fixed order and the first
+// breached one is returned.
+#[derive(Debug, Clone, Default)]
+pub struct RuleRiskManager {
+    limits_default: InstrumentLimits,
+    limits: FnvHashMap<InstrumentIndex, InstrumentLimits>,
+    product_position_max: FnvHashMap<SmolStr, Decimal>,
+    order_rate: Option<OrderRateLimit>,
+    // Times of approved orders within the order rate window
+    approved: VecDeque<DateTime<Utc>>,
+}
+
+impl RuleRiskManager {
+    pub fn new(limits_default: InstrumentLimits) -> Self {
+        Self {
+            limits_default,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_instrument_limits(mut self, instrument: InstrumentIndex, limits: InstrumentLimits) -> Self {
+        self.limits.insert(instrument, limits);
+        self
+    }
+
+    pub fn with_product_position_max(mut self, product: impl Into<SmolStr>, position_max: Decimal) -> Self {
+        self.product_position_max.insert(product.into(), position_max);
+        self
+    }
+
+    pub fn with_order_rate_limit(mut self, order_rate: OrderRateLimit) -> Self {
+        self.order_rate = Some(order_rate);
+        self
+    }
+
+    pub fn limits(&self, instrument: InstrumentIndex) -> &InstrumentLimits {
+        self.limits.get(&instrument).unwrap_or(&self.limits_default)
+    }
+
+    fn check_order_rate(&mut self, time: DateTime<Utc>) -> Result<(), RiskRefusal> {
+        let Some(OrderRateLimit { orders_max, window }) = self.order_rate else {
+            return Ok(());
+        };
+
+        while self.approved.front().is_some_and(|approved| *approved <= time - window) {
+            self.approved.pop_front();
+        }
+
+        if self.approved.len() >= orders_max {
+            return Err(RiskRefusal::OrderRate { orders_max, window });
+        }
+
+        Ok(())
+    }
+}
+
+impl RiskManager<DefaultEngineState> for RuleRiskManager {
+    type Refusal = RiskRefusal;
+
+    fn check(&mut self, state: &DefaultEngineState, request: &OrderRequest, time: DateTime<Utc>) -> Result<(), Self::Refusal> {
+        let instrument = state.instrument(request.instrument).ok_or(RiskRefusal::UnknownInstrument(request.instrument))?;
+        let limits = *self.limits(request.instrument);
+
+        if let Some(limit) = limits.order_quantity_max
+            && request.quantity > limit
+        {
+            return Err(RiskRefusal::OrderQuantity {
+                quantity: request.quantity,
+                limit,
+            });
+        }
+
+        if let Some(limit) = limits.order_notional_max {
+            // Market orders carry no meaningful price, they are valued at the mark price
+            let price = match request.kind {
+                OrderKind::Limit => request.price,
+                OrderKind::Market => instrument.price_mark().ok_or(RiskRefusal::PriceReferenceMissing { price: request.price })?,
+            };
+
+            let notional = price * request.quantity * instrument.spec.multiplier;
+            if notional > limit {
+                return Err(RiskRefusal::OrderNotional { notional, limit });
+            }
+        }
+
+        if let Some(band) = limits.price_band
+            && request.kind == OrderKind::Limit
+        {
+            let trade_last = instrument.trade_last.ok_or(RiskRefusal::PriceReferenceMissing { price: request.price })?.price;
+
+            if (request.price - trade_last).abs() > trade_last * band {
+                return Err(RiskRefusal::PriceBand {
+                    price: request.price,
+                    trade_last,
+                    band,
+                });
+            }
+        }
+
+        if let Some(limit) = limits.position_max {
+            let (current, projected) = position_projected(instrument, request.side, request.quantity);
+            if is_breach(current, projected, limit) {
+                return Err(RiskRefusal::InstrumentPosition { position: projected, limit });
+            }
+        }
+
+        if let Some(limit) = self.product_position_max.get(&instrument.spec.product).copied() {
+            let (current, projected) = state
+                .instruments()
+                .filter(|(_, other)| other.spec.product == instrument.spec.product)
+                .map(|(index, other)| {
+                    let quantity = if index == request.instrument { request.quantity } else { Decimal::ZERO };
+                    position_projected(other, request.side, quantity)
+                })
+                .fold((Decimal::ZERO, Decimal::ZERO), |(current, projected), (other_current, other_projected)| {
+                    (current + other_current, projected + other_projected)
+                });
+
+            if is_breach(current, projected, limit) {
+                return Err(RiskRefusal::ProductPosition {
+                    product: instrument.spec.product.clone(),
+                    position: projected,
+                    limit,
+                });
+            }
+        }
+
+        self.check_order_rate(time)?;
+        if self.order_rate.is_some() {
+            self.approved.push_back(time);
+        }
+
+        Ok(())
+    }
+}
+
+// Current and worst case net position if every open order on `side` plus `quantity` were filled.
+fn position_projected(instrument: &InstrumentState, side: OrderSide, quantity: Decimal) -> (Decimal, Decimal) {
+    let current = instrument.position.quantity_net();
+
+    let pending = instrument
+        .orders
+        .values()
+        .filter(|order| order.request.side == side)
+        .map(|order| order.quantity_remaining())
+        .sum::<Decimal>()
+        + quantity;
+
+    let projected = match side {
+        OrderSide::Buy => current + pending,
+        OrderSide::Sell => current - pending,
+    };
+
+    (current, projected)
+}
+
+// Orders reducing an already breached position are still allowed.
+fn is_breach(current: Decimal, projected: Decimal, limit: Decimal) -> bool {
+    projected.abs() > limit && projected.abs() > current.abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::{
+        engine::{
+            event::{AccountEvent, AccountEventKind, Fill, MarketEvent, MarketEventKind, PublicTrade},
+            order::{ClientOrderId, ExecutionRequest, Offset, TimeInForce},
+            state::{EngineState, InstrumentSpec},
+        },
+        route::AssetIndex,
+    };
+
+    const RB2510: InstrumentIndex = InstrumentIndex::new(0);
+    const RB2601: InstrumentIndex = InstrumentIndex::new(1);
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn state() -> DefaultEngineState {
+        let spec = |name: &str| InstrumentSpec {
+            name: name.into(),
+            product: "rb".into(),
+            quote_asset: AssetIndex::new(0),
+            multiplier: dec!(10),
+            tick_size: dec!(1),
+        };
+
+        DefaultEngineState::new([spec("rb2510"), spec("rb2601")])
+    }
+
+    fn traded(mut state: DefaultEngineState, instrument: InstrumentIndex, price: Decimal) -> DefaultEngineState {
+        state.process_market(&MarketEvent {
+            instrument,
+            time_exchange: time(0),
+            kind: MarketEventKind::Trade(PublicTrade {
+                price,
+                quantity: Decimal::ONE,
+                side: OrderSide::Buy,
+            }),
+        });
+        state
+    }
+
+    fn filled(mut state: DefaultEngineState, instrument: InstrumentIndex, side: OrderSide, quantity: Decimal) -> DefaultEngineState {
+        state.process_account(&AccountEvent {
+            time_exchange: time(0),
+            kind: AccountEventKind::Fill(Fill {
+                instrument,
+                cid: ClientOrderId::new("external".into()),
+                side,
+                offset: Offset::Auto,
+                price: dec!(3500),
+                quantity,
+                fee: Decimal::ZERO,
+            }),
+        });
+        state
+    }
+
+    fn request(instrument: InstrumentIndex, side: OrderSide, kind: OrderKind, price: Decimal, quantity: Decimal) -> OrderRequest {
+        OrderRequest {
+            instrument,
+            cid: ClientOrderId::new(format!("{side:?}-{price}-{quantity}").into()),
+            side,
+            offset: Offset::Auto,
+            kind,
+            time_in_force: TimeInForce::GoodUntilCancelled,
+            price,
+            quantity,
+        }
+    }
+
+    fn buy(quantity: Decimal) -> OrderRequest {
+        request(RB2510, OrderSide::Buy, OrderKind::Limit, dec!(3500), quantity)
+    }
+
+    fn sell(quantity: Decimal) -> OrderRequest {
+        request(RB2510, OrderSide::Sell, OrderKind::Limit, dec!(3500), quantity)
+    }
+
+    fn limits(limits: InstrumentLimits) -> RuleRiskManager {
+        RuleRiskManager::new(limits)
+    }
+
+    #[test]
+    fn test_unknown_instrument() {
+        let order = request(InstrumentIndex::new(9), OrderSide::Buy, OrderKind::Limit, dec!(1), dec!(1));
+        assert_eq!(
+            RuleRiskManager::default().check(&state(), &order, time(0)),
+            Err(RiskRefusal::UnknownInstrument(InstrumentIndex::new(9)))
+        );
+    }
+
+    #[test]
+    fn test_order_quantity() {
+        let mut risk = limits(InstrumentLimits {
+            order_quantity_max: Some(dec!(5)),
+            ..Default::default()
+        });
+
+        assert_eq!(risk.check(&state(), &buy(dec!(5)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state(), &buy(dec!(6)), time(0)),
+            Err(RiskRefusal::OrderQuantity {
+                quantity: dec!(6),
+                limit: dec!(5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_order_notional() {
+        let mut risk = limits(InstrumentLimits {
+            order_notional_max: Some(dec!(70000)),
+            ..Default::default()
+        });
+        let state = traded(state(), RB2510, dec!(3600));
+
+        // 3500 * 2 * 10
+        assert_eq!(risk.check(&state, &buy(dec!(2)), time(0)), Ok(()));
+
+        // Market orders are valued at the mark, not their nominal price
+        let market = request(RB2510, OrderSide::Buy, OrderKind::Market, Decimal::ZERO, dec!(2));
+        assert_eq!(
+            risk.check(&state, &market, time(0)),
+            Err(RiskRefusal::OrderNotional {
+                notional: dec!(72000),
+                limit: dec!(70000)
+            })
+        );
+
+        // Without a mark a Market order cannot be valued at all
+        let market = request(RB2601, OrderSide::Buy, OrderKind::Market, Decimal::ZERO, dec!(1));
+        assert_eq!(
+            risk.check(&state, &market, time(0)),
+            Err(RiskRefusal::PriceReferenceMissing { price: Decimal::ZERO })
+        );
+    }
+
+    #[test]
+    fn test_price_band() {
+        let mut risk = limits(InstrumentLimits {
+            price_band: Some(dec!(0.05)),
+            ..Default::default()
+        });
+
+        let order = |price| request(RB2510, OrderSide::Buy, OrderKind::Limit, price, dec!(1));
+        assert_eq!(
+            risk.check(&state(), &order(dec!(3500)), time(0)),
+            Err(RiskRefusal::PriceReferenceMissing { price: dec!(3500) })
+        );
+
+        let state = traded(state(), RB2510, dec!(3500));
+        assert_eq!(risk.check(&state, &order(dec!(3675)), time(0)), Ok(()));
+        assert_eq!(risk.check(&state, &order(dec!(3325)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state, &order(dec!(3676)), time(0)),
+            Err(RiskRefusal::PriceBand {
+                price: dec!(3676),
+                trade_last: dec!(3500),
+                band: dec!(0.05)
+            })
+        );
+
+        // Market orders take whatever price there is
+        let market = request(RB2510, OrderSide::Buy, OrderKind::Market, dec!(9999), dec!(1));
+        assert_eq!(risk.check(&state, &market, time(0)), Ok(()));
+    }
+
+    #[test]
+    fn test_instrument_position_counts_open_orders() {
+        let mut risk = limits(InstrumentLimits {
+            position_max: Some(dec!(5)),
+            ..Default::default()
+        });
+        let mut state = filled(state(), RB2510, OrderSide::Buy, dec!(2));
+        state.process_request(&ExecutionRequest::Open(buy(dec!(2))), time(0));
+
+        assert_eq!(risk.check(&state, &buy(dec!(1)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state, &buy(dec!(2)), time(0)),
+            Err(RiskRefusal::InstrumentPosition {
+                position: dec!(6),
+                limit: dec!(5)
+            })
+        );
+
+        // Open buys don't offset sells
+        assert_eq!(risk.check(&state, &sell(dec!(7)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state, &sell(dec!(8)), time(0)),
+            Err(RiskRefusal::InstrumentPosition {
+                position: dec!(-6),
+                limit: dec!(5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_product_position_spans_instruments() {
+        let mut risk = RuleRiskManager::default().with_product_position_max("rb", dec!(5));
+        let state = filled(state(), RB2601, OrderSide::Buy, dec!(3));
+
+        assert_eq!(risk.check(&state, &buy(dec!(2)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state, &buy(dec!(3)), time(0)),
+            Err(RiskRefusal::ProductPosition {
+                product: "rb".into(),
+                position: dec!(6),
+                limit: dec!(5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_reducing_orders_pass_breached_limits() {
+        let mut risk = limits(InstrumentLimits {
+            position_max: Some(dec!(5)),
+            ..Default::default()
+        })
+        .with_product_position_max("rb", dec!(5));
+        let state = filled(state(), RB2510, OrderSide::Buy, dec!(10));
+
+        assert_eq!(risk.check(&state, &sell(dec!(3)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state, &buy(dec!(1)), time(0)),
+            Err(RiskRefusal::InstrumentPosition {
+                position: dec!(11),
+                limit: dec!(5)
+            })
+        );
+
+        // Flipping sides is allowed while it shrinks the position, but not past the current size
+        assert_eq!(risk.check(&state, &sell(dec!(16)), time(0)), Ok(()));
+        assert_eq!(
+            risk.check(&state, &sell(dec!(21)), time(0)),
+            Err(RiskRefusal::InstrumentPosition {
+                position: dec!(-11),
+                limit: dec!(5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_order_rate_sliding_window() {
+        let mut risk = RuleRiskManager::default().with_order_rate_limit(OrderRateLimit {
+            orders_max: 2,
+            window: TimeDelta::seconds(1),
+        });
+        let refusal = Err(RiskRefusal::OrderRate {
+            orders_max: 2,
+            window: TimeDelta::seconds(1),
+        });
+        let at = |millis| time(0) + TimeDelta::milliseconds(millis);
+
+        assert_eq!(risk.check(&state(), &buy(dec!(1)), at(0)), Ok(()));
+        assert_eq!(risk.check(&state(), &buy(dec!(1)), at(500)), Ok(()));
+        assert_eq!(risk.check(&state(), &buy(dec!(1)), at(999)), refusal);
+
+        // Refused orders are not counted, and an approval leaves the window exactly one window later
+        assert_eq!(risk.check(&state(), &buy(dec!(1)), at(1000)), Ok(()));
+        assert_eq!(risk.check(&state(), &buy(dec!(1)), at(1499)), refusal);
+        assert_eq!(risk.check(&state(), &buy(dec!(1)), at(1500)), Ok(()));
+    }
+
+    #[test]
+    fn test_instrument_limits_override_default() {
+        let mut risk = limits(InstrumentLimits {
+            order_quantity_max: Some(dec!(1)),
+            ..Default::default()
+        })
+        .with_instrument_limits(RB2601, InstrumentLimits::default());
+
+        let order = request(RB2601, OrderSide::Buy, OrderKind::Limit, dec!(3500), dec!(100));
+        assert_eq!(risk.check(&state(), &order, time(0)), Ok(()));
+        assert!(risk.check(&state(), &buy(dec!(2)), time(0)).is_err());
+    }
+}
@@ -0,0 +1,598 @@
+use chrono::{DateTime, Utc};
+use fnv::FnvHashMap;
+use rust_decimal::Decimal;
+use smol_str::SmolStr;
+use tracing::warn;
+
+use crate::{
+    engine::{
+        command::{CommandState, InstrumentFilter},
+        event::{AccountEvent, AccountEventKind, Balance, Fill, MarketEvent, MarketEventKind, OrderUpdate, OrderUpdateKind, PublicTrade, Quote},
+        kill_switch::PnlState,
+        order::{CancelRequest, ClientOrderId, ExecutionRequest, Offset, Order, OrderKind, OrderRequest, OrderSide, OrderStatus, TimeInForce},
+    },
+    route::{AssetIndex, InstrumentIndex},
+};
+
+pub mod position;
+pub mod summary;
+
+pub use position::{Position, PositionLeg};
+
+pub trait EngineState {
+    type Market;
+    type Account;
+
+    fn process_market(&mut self, event: &Self::Market);
+
+    fn process_account(&mut self, event: &Self::Account);
+
+    // Called with every request the engine sends to the execution layer.
+    fn process_request(&mut self, _request: &ExecutionRequest, _time: DateTime<Utc>) {}
+
+    // Whether an order with the request's ClientOrderId is still open, such requests are not sent.
+    fn is_order_open(&self, _request: &OrderRequest) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstrumentSpec {
+    pub name: SmolStr,
+    // Product the instrument is a contract of, eg/ "rb" for "rb2510", limits can apply across a product.
+    pub product: SmolStr,
+    pub quote_asset: AssetIndex,
+    pub multiplier: Decimal,
+    pub tick_size: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstrumentState {
+    pub spec: InstrumentSpec,
+    pub position: Position,
+    pub orders: FnvHashMap<ClientOrderId, Order>,
+    pub trade_last: Option<PublicTrade>,
+    pub quote: Option<Quote>,
+    pub time_exchange_last: Option<DateTime<Utc>>,
+}
+
+impl InstrumentState {
+    pub fn new(spec: InstrumentSpec) -> Self {
+        Self {
+            spec,
+            position: Position::default(),
+            orders: FnvHashMap::default(),
+            trade_last: None,
+            quote: None,
+            time_exchange_last: None,
+        }
+    }
+
+    // Last trade price, falling back to the quote mid before the first trade.
+    pub fn price_mark(&self) -> Option<Decimal> {
+        self.trade_last.map(|trade| trade.price).or_else(|| self.quote?.mid_price())
+    }
+
+    pub fn pnl_unrealised(&self) -> Decimal {
+        self.price_mark()
+            .map_or(Decimal::ZERO, |price_mark| self.position.pnl_unrealised(price_mark, self.spec.multiplier))
+    }
+}
+
+// Instruments are stored densely, `InstrumentIndex` is the position of the instrument's spec in
+// the list the state was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DefaultEngineState {
+    instruments: Vec<InstrumentState>,
+    balances: FnvHashMap<AssetIndex, Balance>,
+}
+
+impl DefaultEngineState {
+    pub fn new<Specs>(specs: Specs) -> Self
+    where
+        Specs: IntoIterator<Item = InstrumentSpec>,
+    {
+        Self {
+            instruments: specs.into_iter().map(InstrumentState::new).collect(),
+            balances: FnvHashMap::default(),
+        }
+    }
+
+    pub fn instrument(&self, instrument: InstrumentIndex) -> Option<&InstrumentState> {
+        self.instruments.get(instrument.index())
+    }
+
+    pub fn instruments(&self) -> impl Iterator<Item = (InstrumentIndex, &InstrumentState)> {
+        self.instruments.iter().enumerate().map(|(index, state)| (InstrumentIndex::new(index), state))
+    }
+
+    pub fn balance(&self, asset: AssetIndex) -> Option<&Balance> {
+        self.balances.get(&asset)
+    }
+
+    pub fn balances(&self) -> impl Iterator<Item = (&AssetIndex, &Balance)> {
+        self.balances.iter()
+    }
+
+    pub fn orders_open(&self) -> impl Iterator<Item = &Order> {
+        self.instruments.iter().flat_map(|state| state.orders.values())
+    }
+
+    pub fn order(&self, instrument: InstrumentIndex, cid: &ClientOrderId) -> Option<&Order> {
+        self.instrument(instrument)?.orders.get(cid)
+    }
+
+    pub fn pnl(&self) -> Decimal {
+        self.instruments
+            .iter()
+            .map(|state| state.position.pnl_realised() + state.pnl_unrealised())
+            .sum()
+    }
+
+    fn instrument_mut(&mut self, instrument: InstrumentIndex) -> Option<&mut InstrumentState> {
+        let state = self.instruments.get_mut(instrument.index());
+        if state.is_none() {
+            warn!(?instrument, "Ignoring update for unknown instrument.");
+        }
+
+        state
+    }
+
+    fn update_order(&mut self, instrument: InstrumentIndex, cid: &ClientOrderId, status: OrderStatus, time: DateTime<Utc>) {
+        let Some(state) = self.instrument_mut(instrument) else {
+            return;
+        };
+
+        let Some(order) = state.orders.get_mut(cid) else {
+            warn!(?instrument, ?cid, ?status, "Ignoring status update for unknown order.");
+            return;
+        };
+
+        order.status = status;
+        order.time_update = time;
+
+        if status.is_terminal() {
+            state.orders.remove(cid);
+        }
+    }
+
+    fn process_order_update(&mut self, update: &OrderUpdate, time: DateTime<Utc>) {
+        let OrderUpdate { instrument, cid, kind } = update;
+
+        match kind {
+            OrderUpdateKind::Accepted => {
+                // A cancel may already be in flight before the acknowledgement arrives
+                let is_pending_cancel = self.order(*instrument, cid).is_some_and(|order| order.status == OrderStatus::PendingCancel);
+                if !is_pending_cancel {
+                    self.update_order(*instrument, cid, OrderStatus::Open, time);
+                }
+            },
+            OrderUpdateKind::Rejected { reason } => {
+                warn!(?instrument, ?cid, %reason, "Order rejected by exchange.");
+                self.update_order(*instrument, cid, OrderStatus::Rejected, time);
+            },
+            OrderUpdateKind::Cancelled => {
+                self.update_order(*instrument, cid, OrderStatus::Cancelled, time);
+            },
+            OrderUpdateKind::CancelRejected { reason } => {
+                warn!(?instrument, ?cid, %reason, "Cancel rejected by exchange.");
+                let status = match self.order(*instrument, cid) {
+                    Some(order) if order.quantity_filled.is_zero() => OrderStatus::Open,
+                    _ => OrderStatus::PartiallyFilled,
+                };
+                self.update_order(*instrument, cid, status, time);
+            },
+        }
+    }
+
+    fn process_fill(&mut self, fill: &Fill, time: DateTime<Utc>) {
+        let Some(state) = self.instrument_mut(fill.instrument) else {
+            return;
+        };
+
+        let closed_excess = state
+            .position
+            .apply_fill(fill.side, fill.offset, fill.price, fill.quantity, state.spec.multiplier, fill.fee);
+        if !closed_excess.is_zero() {
+            warn!(?fill, %closed_excess, "Fill closed more than the position held.");
+        }
+
+        // Fills of orders placed outside the engine still move the position
+        let Some(order) = state.orders.get_mut(&fill.cid) else {
+            warn!(?fill, "Fill for unknown order.");
+            return;
+        };
+
+        order.quantity_filled += fill.quantity;
+        order.time_update = time;
+        if order.quantity_remaining().is_zero() {
+            state.orders.remove(&fill.cid);
+        } else if order.status != OrderStatus::PendingCancel {
+            order.status = OrderStatus::PartiallyFilled;
+        }
+    }
+}
+
+impl EngineState for DefaultEngineState {
+    type Market = MarketEvent;
+    type Account = AccountEvent;
+
+    fn process_market(&mut self, event: &Self::Market) {
+        let Some(state) = self.instrument_mut(event.instrument) else {
+            return;
+        };
+
+        state.time_exchange_last = Some(event.time_exchange);
+        match &event.kind {
+            MarketEventKind::Trade(trade) => state.trade_last = Some(*trade),
+            MarketEventKind::Quote(quote) => state.quote = Some(*quote),
+        }
+    }
+
+    fn process_account(&mut self, event: &Self::Account) {
+        let time = event.time_exchange;
+
+        match &event.kind {
+            AccountEventKind::OrderUpdate(update) => self.process_order_update(update, time),
+            AccountEventKind::Fill(fill) => self.process_fill(fill, time),
+            AccountEventKind::Balance { asset, balance } => {
+                self.balances.insert(*asset, *balance);
+            },
+        }
+    }
+
+    fn is_order_open(&self, request: &OrderRequest) -> bool {
+        self.order(request.instrument, &request.cid).is_some()
+    }
+
+    fn process_request(&mut self, request: &ExecutionRequest, time: DateTime<Utc>) {
+        match request {
+            ExecutionRequest::Open(order) => {
+                let Some(state) = self.instrument_mut(order.instrument) else {
+                    return;
+                };
+
+                if state.orders.contains_key(&order.cid) {
+                    warn!(?order, "Ignoring OrderRequest reusing the ClientOrderId of an open order.");
+                    return;
+                }
+                state.orders.insert(order.cid.clone(), Order::new(order.clone(), time));
+            },
+            ExecutionRequest::Cancel(cancel) => {
+                self.update_order(cancel.instrument, &cancel.cid, OrderStatus::PendingCancel, time);
+            },
+        }
+    }
+}
+
+impl CommandState for DefaultEngineState {
+    fn cancel_requests(&self, filter: &InstrumentFilter) -> Vec<CancelRequest> {
+        self.instruments()
+            .filter(|(instrument, state)| filter.matches(*instrument, &state.spec))
+            .flat_map(|(instrument, state)| {
+                state
+                    .orders
+                    .values()
+                    .filter(|order| order.status != OrderStatus::PendingCancel)
+                    .map(move |order| CancelRequest::new(instrument, order.request.cid.clone()))
+            })
+            .collect()
+    }
+
+    fn close_requests(&self, filter: &InstrumentFilter, seq: usize) -> Vec<OrderRequest> {
+        self.instruments()
+            .filter(|(instrument, state)| filter.matches(*instrument, &state.spec))
+            .flat_map(|(instrument, state)| {
+                let PositionLeg { quantity: long, .. } = state.position.long;
+                let PositionLeg { quantity: short, .. } = state.position.short;
+
+                // Closes still working, including those pending cancel, already cover part of each leg
+                let closing = |side: OrderSide| {
+                    state
+                        .orders
+                        .values()
+                        .filter(|order| order.request.offset == Offset::Close && order.request.side == side)
+                        .map(Order::quantity_remaining)
+                        .sum::<Decimal>()
+                };
+                let long = (long - closing(OrderSide::Sell)).max(Decimal::ZERO);
+                let short = (short - closing(OrderSide::Buy)).max(Decimal::ZERO);
+                if long.is_zero() && short.is_zero() {
+                    return [None, None];
+                }
+
+                let Some(price) = state.price_mark() else {
+                    warn!(?instrument, name = %state.spec.name, "Not closing position of instrument without a price.");
+                    return [None, None];
+                };
+
+                let close = |side: OrderSide, leg: &str, quantity: Decimal| OrderRequest {
+                    instrument,
+                    cid: ClientOrderId::new(format!("close-{seq}-{}-{leg}", instrument.index()).into()),
+                    side,
+                    offset: Offset::Close,
+                    kind: OrderKind::Market,
+                    time_in_force: TimeInForce::ImmediateOrCancel,
+                    price,
+                    quantity,
+                };
+
+                [
+                    (!long.is_zero()).then(|| close(OrderSide::Sell, "long", long)),
+                    (!short.is_zero()).then(|| close(OrderSide::Buy, "short", short)),
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+impl PnlState for DefaultEngineState {
+    fn pnl(&self) -> Decimal {
+        DefaultEngineState::pnl(self)
+    }
+}
+
+pub type BarterEngineState<GlobalData, InstrumentData> = barter::engine::state::EngineState<GlobalData, InstrumentData>;
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn state() -> DefaultEngineState {
+        DefaultEngineState::new([InstrumentSpec {
+            name: "rb2510".into(),
+            product: "rb".into(),
+            quote_asset: AssetIndex::new(0),
+            multiplier: dec!(10),
+            tick_size: dec!(1),
+        }])
+    }
+
+    fn account(kind: AccountEventKind) -> AccountEvent {
+        AccountEvent { time_exchange: time(0), kind }
+    }
+
+    fn fill(cid: &str, side: OrderSide, offset: Offset, quantity: Decimal) -> AccountEvent {
+        account(AccountEventKind::Fill(Fill {
+            instrument: InstrumentIndex::new(0),
+            cid: ClientOrderId::new(cid.into()),
+            side,
+            offset,
+            price: dec!(3500),
+            quantity,
+            fee: Decimal::ZERO,
+        }))
+    }
+
+    fn trade(price: Decimal) -> MarketEvent {
+        MarketEvent {
+            instrument: InstrumentIndex::new(0),
+            time_exchange: time(0),
+            kind: MarketEventKind::Trade(PublicTrade {
+                price,
+                quantity: Decimal::ONE,
+                side: OrderSide::Buy,
+            }),
+        }
+    }
+
+    // Long 3 and short 1, opened by fills of orders placed outside the engine
+    fn hedged() -> DefaultEngineState {
+        let mut state = state();
+        state.process_account(&fill("long", OrderSide::Buy, Offset::Open, dec!(3)));
+        state.process_account(&fill("short", OrderSide::Sell, Offset::Open, dec!(1)));
+        state
+    }
+
+    fn legs(requests: &[OrderRequest]) -> Vec<(OrderSide, Decimal)> {
+        requests.iter().map(|request| (request.side, request.quantity)).collect()
+    }
+
+    #[test]
+    fn test_close_requests_close_both_legs_at_the_mark() {
+        let mut state = hedged();
+        state.process_market(&trade(dec!(3510)));
+
+        let requests = state.close_requests(&InstrumentFilter::All, 7);
+        assert_eq!(legs(&requests), vec![(OrderSide::Sell, dec!(3)), (OrderSide::Buy, dec!(1))]);
+        assert!(requests.iter().all(|request| request.offset == Offset::Close && request.price == dec!(3510)));
+        assert_eq!(requests[0].cid, ClientOrderId::new("close-7-0-long".into()));
+    }
+
+    #[test]
+    fn test_close_requests_skip_instrument_without_price() {
+        let state = hedged();
+        assert!(state.close_requests(&InstrumentFilter::All, 1).is_empty());
+    }
+
+    #[test]
+    fn test_close_requests_net_out_pending_closes() {
+        let mut state = hedged();
+        state.process_market(&trade(dec!(3510)));
+
+        let first = state.close_requests(&InstrumentFilter::All, 1);
+        for request in &first {
+            state.process_request(&ExecutionRequest::Open(request.clone()), time(1));
+        }
+
+        // Repeating the command while the closes are in flight sends nothing more
+        assert!(state.close_requests(&InstrumentFilter::All, 2).is_empty());
+
+        // A partial fill shrinks the leg and the working close alike
+        state.process_account(&fill("close-1-0-long", OrderSide::Sell, Offset::Close, dec!(2)));
+        assert!(state.close_requests(&InstrumentFilter::All, 3).is_empty());
+
+        // Only the legs whose close was cancelled are closed again
+        state.process_account(&account(AccountEventKind::OrderUpdate(OrderUpdate {
+            instrument: InstrumentIndex::new(0),
+            cid: first[1].cid.clone(),
+            kind: OrderUpdateKind::Cancelled,
+        })));
+        assert_eq!(legs(&state.close_requests(&InstrumentFilter::All, 4)), vec![(OrderSide::Buy, dec!(1))]);
+
+        // An unrelated open order on the same side is not a close
+        state.process_request(
+            &ExecutionRequest::Open(OrderRequest {
+                instrument: InstrumentIndex::new(0),
+                cid: ClientOrderId::new("open-short".into()),
+                side: OrderSide::Sell,
+                offset: Offset::Open,
+                kind: OrderKind::Limit,
+                time_in_force: TimeInForce::GoodUntilCancelled,
+                price: dec!(3600),
+                quantity: dec!(5),
+            }),
+            time(2),
+        );
+        assert_eq!(legs(&state.close_requests(&InstrumentFilter::All, 5)), vec![(OrderSide::Buy, dec!(1))]);
+    }
+
+    fn order(cid: &str, side: OrderSide, quantity: Decimal) -> OrderRequest {
+        OrderRequest {
+            instrument: InstrumentIndex::new(0),
+            cid: ClientOrderId::new(cid.into()),
+            side,
+            offset: Offset::Open,
+            kind: OrderKind::Limit,
+            time_in_force: TimeInForce::GoodUntilCancelled,
+            price: dec!(3500),
+            quantity,
+        }
+    }
+
+    fn update(cid: &str, kind: OrderUpdateKind) -> AccountEvent {
+        account(AccountEventKind::OrderUpdate(OrderUpdate {
+            instrument: InstrumentIndex::new(0),
+            cid: ClientOrderId::new(cid.into()),
+            kind,
+        }))
+    }
+
+    fn status(state: &DefaultEngineState, cid: &str) -> Option<OrderStatus> {
+        state.order(InstrumentIndex::new(0), &ClientOrderId::new(cid.into())).map(|order| order.status)
+    }
+
+    fn open(state: &mut DefaultEngineState, cid: &str, quantity: Decimal) {
+        state.process_request(&ExecutionRequest::Open(order(cid, OrderSide::Buy, quantity)), time(0));
+    }
+
+    fn cancel(state: &mut DefaultEngineState, cid: &str) {
+        let cancel = CancelRequest::new(InstrumentIndex::new(0), ClientOrderId::new(cid.into()));
+        state.process_request(&ExecutionRequest::Cancel(cancel), time(0));
+    }
+
+    #[test]
+    fn test_order_lifecycle() {
+        let mut state = state();
+        open(&mut state, "a", dec!(3));
+        assert_eq!(status(&state, "a"), Some(OrderStatus::PendingNew));
+
+        // A reused ClientOrderId does not replace the open order
+        state.process_request(&ExecutionRequest::Open(order("a", OrderSide::Sell, dec!(9))), time(1));
+        assert_eq!(
+            state.order(InstrumentIndex::new(0), &ClientOrderId::new("a".into())).unwrap().request.side,
+            OrderSide::Buy
+        );
+
+        state.process_account(&update("a", OrderUpdateKind::Accepted));
+        assert_eq!(status(&state, "a"), Some(OrderStatus::Open));
+
+        state.process_account(&fill("a", OrderSide::Buy, Offset::Open, dec!(1)));
+        assert_eq!(status(&state, "a"), Some(OrderStatus::PartiallyFilled));
+        assert_eq!(
+            state
+                .order(InstrumentIndex::new(0), &ClientOrderId::new("a".into()))
+                .unwrap()
+                .quantity_remaining(),
+            dec!(2)
+        );
+
+        // Completing the fill removes the order, and the position follows every fill
+        state.process_account(&fill("a", OrderSide::Buy, Offset::Open, dec!(2)));
+        assert_eq!(status(&state, "a"), None);
+        assert_eq!(state.instrument(InstrumentIndex::new(0)).unwrap().position.long.quantity, dec!(3));
+    }
+
+    #[test]
+    fn test_order_cancel_lifecycle() {
+        let mut state = state();
+        open(&mut state, "a", dec!(3));
+        cancel(&mut state, "a");
+        assert_eq!(status(&state, "a"), Some(OrderStatus::PendingCancel));
+
+        // The acknowledgement of the order can arrive after the cancel was sent
+        state.process_account(&update("a", OrderUpdateKind::Accepted));
+        assert_eq!(status(&state, "a"), Some(OrderStatus::PendingCancel));
+
+        // Fills while a cancel is in flight leave it pending
+        state.process_account(&fill("a", OrderSide::Buy, Offset::Open, dec!(1)));
+        assert_eq!(status(&state, "a"), Some(OrderStatus::PendingCancel));
+
+        let rejected = OrderUpdateKind::CancelRejected { reason: "too late".into() };
+        state.process_account(&update("a", rejected.clone()));
+        assert_eq!(status(&state, "a"), Some(OrderStatus::PartiallyFilled));
+
+        open(&mut state, "b", dec!(1));
+        cancel(&mut state, "b");
+        state.process_account(&update("b", rejected));
+        assert_eq!(status(&state, "b"), Some(OrderStatus::Open));
+
+        cancel(&mut state, "a");
+        state.process_account(&update("a", OrderUpdateKind::Cancelled));
+        assert_eq!(status(&state, "a"), None);
+
+        state.process_account(&update("b", OrderUpdateKind::Rejected { reason: "margin".into() }));
+        assert_eq!(status(&state, "b"), None);
+        assert_eq!(state.orders_open().count(), 0);
+    }
+
+    #[test]
+    fn test_updates_for_unknown_orders_and_instruments() {
+        let mut state = state();
+
+        // A fill of an order placed elsewhere still moves the position, other updates are ignored
+        state.process_account(&update("ghost", OrderUpdateKind::Accepted));
+        state.process_account(&fill("ghost", OrderSide::Sell, Offset::Open, dec!(2)));
+        assert_eq!(status(&state, "ghost"), None);
+        assert_eq!(state.instrument(InstrumentIndex::new(0)).unwrap().position.short.quantity, dec!(2));
+
+        let before = state.clone();
+        let mut unknown = order("x", OrderSide::Buy, dec!(1));
+        unknown.instrument = InstrumentIndex::new(7);
+        state.process_request(&ExecutionRequest::Open(unknown), time(0));
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn test_balance_update() {
+        let mut state = state();
+        let asset = AssetIndex::new(0);
+        assert_eq!(state.balance(asset), None);
+
+        for total in [dec!(1000), dec!(900)] {
+            let balance = Balance { total, free: dec!(800) };
+            state.process_account(&account(AccountEventKind::Balance { asset, balance }));
+            assert_eq!(state.balance(asset), Some(&balance));
+        }
+        assert_eq!(state.balances().count(), 1);
+    }
+
+    #[test]
+    fn test_pnl_marks_positions() {
+        let mut state = hedged();
+        state.process_account(&fill("long", OrderSide::Sell, Offset::Close, dec!(1)));
+        state.process_market(&trade(dec!(3510)));
+
+        // Long 2 and short 1 opened at 3500, marked at 3510 with a multiplier of 10
+        let instrument = state.instrument(InstrumentIndex::new(0)).unwrap();
+        assert_eq!(instrument.pnl_unrealised(), dec!(100));
+        assert_eq!(state.pnl(), dec!(100));
+    }
+}
@@ -0,0 +1,180 @@
+use rust_decimal::Decimal;
+
+use crate::engine::order::{Offset, OrderSide};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PositionLeg {
+    pub quantity: Decimal,
+    pub price_entry_average: Decimal,
+    pub pnl_realised: Decimal,
+}
+
+impl PositionLeg {
+    fn open(&mut self, price: Decimal, quantity: Decimal) {
+        let quantity_total = self.quantity + quantity;
+        if quantity_total.is_zero() {
+            return;
+        }
+
+        self.price_entry_average = (self.price_entry_average * self.quantity + price * quantity) / quantity_total;
+        self.quantity = quantity_total;
+    }
+
+    // Returns the part of `quantity` exceeding the leg, which could not be closed.
+    fn close(&mut self, price: Decimal, quantity: Decimal, multiplier: Decimal, direction: Decimal) -> Decimal {
+        let closed = quantity.min(self.quantity);
+
+        self.pnl_realised += (price - self.price_entry_average) * closed * multiplier * direction;
+        self.quantity -= closed;
+        if self.quantity.is_zero() {
+            self.price_entry_average = Decimal::ZERO;
+        }
+
+        quantity - closed
+    }
+}
+
+// Long and short legs are held separately, as futures exchanges do, a net position has one of them empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub long: PositionLeg,
+    pub short: PositionLeg,
+    pub fees: Decimal,
+}
+
+impl Position {
+    pub fn quantity_net(&self) -> Decimal {
+        self.long.quantity - self.short.quantity
+    }
+
+    pub fn pnl_realised(&self) -> Decimal {
+        self.long.pnl_realised + self.short.pnl_realised - self.fees
+    }
+
+    pub fn pnl_unrealised(&self, price_mark: Decimal, multiplier: Decimal) -> Decimal {
+        let long = (price_mark - self.long.price_entry_average) * self.long.quantity;
+        let short = (self.short.price_entry_average - price_mark) * self.short.quantity;
+
+        (long + short) * multiplier
+    }
+
+    // Returns any quantity that tried to close more than the leg held.
+    pub fn apply_fill(&mut self, side: OrderSide, offset: Offset, price: Decimal, quantity: Decimal, multiplier: Decimal, fee: Decimal) -> Decimal {
+        self.fees += fee;
+
+        match (offset, side) {
+            (Offset::Open, OrderSide::Buy) => {
+                self.long.open(price, quantity);
+                Decimal::ZERO
+            },
+            (Offset::Open, OrderSide::Sell) => {
+                self.short.open(price, quantity);
+                Decimal::ZERO
+            },
+            (Offset::Close, OrderSide::Buy) => self.short.close(price, quantity, multiplier, Decimal::NEGATIVE_ONE),
+            (Offset::Close, OrderSide::Sell) => self.long.close(price, quantity, multiplier, Decimal::ONE),
+            (Offset::Auto, OrderSide::Buy) => {
+                let remaining = self.short.close(price, quantity, multiplier, Decimal::NEGATIVE_ONE);
+                self.long.open(price, remaining);
+                Decimal::ZERO
+            },
+            (Offset::Auto, OrderSide::Sell) => {
+                let remaining = self.long.close(price, quantity, multiplier, Decimal::ONE);
+                self.short.open(price, remaining);
+                Decimal::ZERO
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const MULTIPLIER: Decimal = dec!(10);
+
+    fn fill(position: &mut Position, side: OrderSide, offset: Offset, price: Decimal, quantity: Decimal) -> Decimal {
+        position.apply_fill(side, offset, price, quantity, MULTIPLIER, dec!(1))
+    }
+
+    #[test]
+    fn test_open_averages_entry_price() {
+        let mut position = Position::default();
+        fill(&mut position, OrderSide::Buy, Offset::Open, dec!(100), dec!(2));
+        fill(&mut position, OrderSide::Buy, Offset::Open, dec!(130), dec!(1));
+
+        assert_eq!(position.long.quantity, dec!(3));
+        assert_eq!(position.long.price_entry_average, dec!(110));
+        assert_eq!(position.short, PositionLeg::default());
+    }
+
+    #[test]
+    fn test_close_realises_pnl_with_multiplier_and_fees() {
+        let mut position = Position::default();
+        fill(&mut position, OrderSide::Buy, Offset::Open, dec!(100), dec!(3));
+        fill(&mut position, OrderSide::Sell, Offset::Open, dec!(100), dec!(2));
+
+        assert_eq!(fill(&mut position, OrderSide::Sell, Offset::Close, dec!(120), dec!(2)), Decimal::ZERO);
+        assert_eq!(fill(&mut position, OrderSide::Buy, Offset::Close, dec!(90), dec!(2)), Decimal::ZERO);
+
+        // (120 - 100) * 2 * 10 on the long leg, (100 - 90) * 2 * 10 on the short, less four fees
+        assert_eq!(position.long.pnl_realised, dec!(400));
+        assert_eq!(position.short.pnl_realised, dec!(200));
+        assert_eq!(position.pnl_realised(), dec!(596));
+        assert_eq!(
+            position.short,
+            PositionLeg {
+                quantity: Decimal::ZERO,
+                price_entry_average: Decimal::ZERO,
+                pnl_realised: dec!(200),
+            }
+        );
+    }
+
+    #[test]
+    fn test_close_beyond_leg_returns_excess() {
+        let mut position = Position::default();
+        fill(&mut position, OrderSide::Buy, Offset::Open, dec!(100), dec!(1));
+
+        assert_eq!(fill(&mut position, OrderSide::Sell, Offset::Close, dec!(110), dec!(3)), dec!(2));
+        assert_eq!(position.long.quantity, Decimal::ZERO);
+        assert_eq!(position.long.pnl_realised, dec!(100));
+        // Closing never opens the opposite leg
+        assert_eq!(position.short.quantity, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_auto_nets_against_opposite_leg() {
+        let mut position = Position::default();
+        fill(&mut position, OrderSide::Buy, Offset::Auto, dec!(100), dec!(2));
+        assert_eq!(position.long.quantity, dec!(2));
+
+        // Closes the long 2, then opens a short with the remaining 3 at the fill price
+        assert_eq!(fill(&mut position, OrderSide::Sell, Offset::Auto, dec!(110), dec!(5)), Decimal::ZERO);
+        assert_eq!(position.long.quantity, Decimal::ZERO);
+        assert_eq!(position.long.pnl_realised, dec!(200));
+        assert_eq!(position.short.quantity, dec!(3));
+        assert_eq!(position.short.price_entry_average, dec!(110));
+        assert_eq!(position.quantity_net(), dec!(-3));
+
+        // Exactly flat leaves both legs empty
+        fill(&mut position, OrderSide::Buy, Offset::Auto, dec!(105), dec!(3));
+        assert_eq!(position.quantity_net(), Decimal::ZERO);
+        assert_eq!(position.short.pnl_realised, dec!(150));
+        assert_eq!(position.fees, dec!(3));
+    }
+
+    #[test]
+    fn test_pnl_unrealised_of_both_legs() {
+        let mut position = Position::default();
+        fill(&mut position, OrderSide::Buy, Offset::Open, dec!(110), dec!(1));
+        fill(&mut position, OrderSide::Sell, Offset::Open, dec!(110), dec!(3));
+
+        // (120 - 110) * 1 * 10 - (120 - 110) * 3 * 10
+        assert_eq!(position.pnl_unrealised(dec!(120), MULTIPLIER), dec!(-200));
+        assert_eq!(position.pnl_unrealised(dec!(110), MULTIPLIER), Decimal::ZERO);
+        assert_eq!(Position::default().pnl_unrealised(dec!(120), MULTIPLIER), Decimal::ZERO);
+    }
+}
@@ -0,0 +1,99 @@
+use fnv::FnvHashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{
+        audit::AuditState,
+        event::Balance,
+        state::{DefaultEngineState, InstrumentState},
+    },
+    route::{AssetIndex, InstrumentIndex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentSummary {
+    pub position_long: Decimal,
+    pub position_short: Decimal,
+    pub pnl_realised: Decimal,
+    pub orders_open: usize,
+}
+
+impl From<&InstrumentState> for InstrumentSummary {
+    fn from(state: &InstrumentState) -> Self {
+        Self {
+            position_long: state.position.long.quantity,
+            position_short: state.position.short.quantity,
+            pnl_realised: state.position.pnl_realised(),
+            orders_open: state.orders.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSnapshot {
+    instruments: Vec<InstrumentSummary>,
+    balances: FnvHashMap<AssetIndex, Balance>,
+}
+
+// Only what changed, market data updates are left to the audited input event.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub instruments: Vec<InstrumentChange>,
+    pub balances: Vec<BalanceChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentChange {
+    pub instrument: InstrumentIndex,
+    pub before: InstrumentSummary,
+    pub after: InstrumentSummary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub asset: AssetIndex,
+    pub before: Option<Balance>,
+    pub after: Balance,
+}
+
+impl AuditState for DefaultEngineState {
+    type Snapshot = StateSnapshot;
+    type Diff = StateDiff;
+
+    fn snapshot(&self) -> Self::Snapshot {
+        StateSnapshot {
+            instruments: self.instruments.iter().map(InstrumentSummary::from).collect(),
+            balances: self.balances.clone(),
+        }
+    }
+
+    fn diff(&self, before: &Self::Snapshot) -> Self::Diff {
+        let instruments = self
+            .instruments()
+            .zip(&before.instruments)
+            .filter_map(|((instrument, state), before)| {
+                let after = InstrumentSummary::from(state);
+                (after != *before).then_some(InstrumentChange {
+                    instrument,
+                    before: *before,
+                    after,
+                })
+            })
+            .collect();
+
+        let balances = self
+            .balances()
+            .filter_map(|(asset, after)| {
+                let before = before.balances.get(asset).copied();
+                (before != Some(*after)).then_some(BalanceChange {
+                    asset: *asset,
+                    before,
+                    after: *after,
+                })
+            })
+            .collect();
+
+        StateDiff { instruments, balances }
+    }
+}
@@ -1,3 +0,0 @@
-pub trait StrategyManager {}
-
-pub type BarterDefaultStrategy<State> = barter::strategy::DefaultStrategy<State>;
@@ -0,0 +1,296 @@
+use chrono::{DateTime, Utc};
+use derive_more::Constructor;
+use thiserror::Error;
+
+use crate::engine::{
+    AccountUpdate,
+    Engine,
+    EngineEvent,
+    EventTime,
+    audit::{AuditState, NoAudit},
+    clock::SimulatedClock,
+    command::CommandState,
+    exec::ExecutionManager,
+    kill_switch::PnlState,
+    order::ExecutionRequest,
+    risk::DefaultRiskManager,
+    state::EngineState,
+    strategy::StrategyManager,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptInput<Market, Account> {
+    Start,
+    Event(EngineEvent<Market, Account>),
+    Stop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Constructor)]
+pub struct ScriptStep<Market, Account> {
+    pub input: ScriptInput<Market, Account>,
+    pub expected: Vec<ExecutionRequest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("script step {step} sent {actual:?}, expected {expected:?}")]
+pub struct ScriptMismatch {
+    pub step: usize,
+    pub expected: Vec<ExecutionRequest>,
+    pub actual: Vec<ExecutionRequest>,
+}
+
+// Drives a strategy through a scripted event sequence on a `SimulatedClock`, so timers fire on the
+// event times. Requests skip risk checks and go nowhere, but are still recorded in state.
+#[derive(Debug)]
+pub struct StrategyHarness<State, Strategy> {
+    engine: Engine<SimulatedClock, State, DiscardExecution, DefaultRiskManager, Strategy, NoAudit>,
+}
+
+#[derive(Debug)]
+struct DiscardExecution;
+
+impl ExecutionManager for DiscardExecution {
+    fn send(&mut self, _: ExecutionRequest) {}
+}
+
+impl<State, Strategy> StrategyHarness<State, Strategy>
+where
+    State: EngineState + AuditState + CommandState + PnlState,
+    State::Market: EventTime,
+    State::Account: EventTime + AccountUpdate,
+    Strategy: StrategyManager<State>,
+{
+    pub fn new(time_init: DateTime<Utc>, state: State, strategy: Strategy) -> Self {
+        Self {
+            engine: Engine::new(SimulatedClock::new(time_init), state, DiscardExecution, DefaultRiskManager, strategy, NoAudit),
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        self.engine.state()
+    }
+
+    pub fn strategy(&self) -> &Strategy {
+        self.engine.strategy()
+    }
+
+    pub fn step(&mut self, input: ScriptInput<State::Market, State::Account>) -> Vec<ExecutionRequest> {
+        let output = match input {
+            ScriptInput::Start => self.engine.start(),
+            ScriptInput::Event(event) => self.engine.process(event),
+            ScriptInput::Stop => self.engine.stop(),
+        };
+
+        output.sent
+    }
+
+    // Stops at the first step whose requests differ from the expected ones.
+    pub fn run_script<Steps>(&mut self, steps: Steps) -> Result<(), ScriptMismatch>
+    where
+        Steps: IntoIterator<Item = ScriptStep<State::Market, State::Account>>,
+    {
+        for (step, ScriptStep { input, expected }) in steps.into_iter().enumerate() {
+            let actual = self.step(input);
+            if actual != expected {
+                return Err(ScriptMismatch { step, expected, actual });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::{
+        engine::{
+            command::InstrumentFilter,
+            event::{AccountEvent, AccountEventKind, Fill, MarketEvent, MarketEventKind, PublicTrade},
+            order::{CancelRequest, ClientOrderId, Offset, OrderKind, OrderRequest, OrderSide, TimeInForce},
+            state::{DefaultEngineState, InstrumentSpec},
+            strategy::{Timer, TimerId},
+        },
+        route::{AssetIndex, InstrumentIndex},
+    };
+
+    type Step = ScriptStep<MarketEvent, AccountEvent>;
+
+    // Bids on start, protects a fill with a stop, takes profit above 105, and pulls resting orders
+    // every 30 seconds and on stop.
+    #[derive(Debug, Default)]
+    struct Bidder {
+        is_profit_taken: bool,
+    }
+
+    impl StrategyManager<DefaultEngineState> for Bidder {
+        fn timers(&self) -> Vec<Timer> {
+            vec![Timer::new(TimerId::new("stale".into()), TimeDelta::seconds(30))]
+        }
+
+        fn on_start(&mut self, _: &DefaultEngineState, _: DateTime<Utc>) -> Vec<ExecutionRequest> {
+            vec![open("entry", OrderSide::Buy, Offset::Open, dec!(100))]
+        }
+
+        fn on_stop(&mut self, state: &DefaultEngineState, _: DateTime<Utc>) -> Vec<ExecutionRequest> {
+            cancel_all(state)
+        }
+
+        fn on_market(&mut self, state: &DefaultEngineState, event: &MarketEvent) -> Vec<ExecutionRequest> {
+            let is_long = state.instrument(event.instrument).is_some_and(|state| !state.position.long.quantity.is_zero());
+            match event.kind {
+                MarketEventKind::Trade(trade) if is_long && !self.is_profit_taken && trade.price >= dec!(105) => {
+                    self.is_profit_taken = true;
+                    vec![open("take-profit", OrderSide::Sell, Offset::Close, trade.price)]
+                },
+                _ => Vec::new(),
+            }
+        }
+
+        fn on_fill(&mut self, _: &DefaultEngineState, fill: &Fill) -> Vec<ExecutionRequest> {
+            if fill.cid == cid("entry") {
+                vec![open("stop", OrderSide::Sell, Offset::Close, fill.price - dec!(5))]
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn on_timer(&mut self, state: &DefaultEngineState, _: &TimerId, _: DateTime<Utc>) -> Vec<ExecutionRequest> {
+            cancel_all(state)
+        }
+    }
+
+    fn cancel_all(state: &DefaultEngineState) -> Vec<ExecutionRequest> {
+        state
+            .cancel_requests(&InstrumentFilter::All)
+            .into_iter()
+            .map(ExecutionRequest::Cancel)
+            .collect()
+    }
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn cid(cid: &str) -> ClientOrderId {
+        ClientOrderId::new(cid.into())
+    }
+
+    fn open(name: &str, side: OrderSide, offset: Offset, price: Decimal) -> ExecutionRequest {
+        ExecutionRequest::Open(OrderRequest {
+            instrument: InstrumentIndex::new(0),
+            cid: cid(name),
+            side,
+            offset,
+            kind: OrderKind::Limit,
+            time_in_force: TimeInForce::GoodUntilCancelled,
+            price,
+            quantity: dec!(2),
+        })
+    }
+
+    fn cancel(name: &str) -> ExecutionRequest {
+        ExecutionRequest::Cancel(CancelRequest::new(InstrumentIndex::new(0), cid(name)))
+    }
+
+    fn trade(seconds: i64, price: Decimal) -> ScriptInput<MarketEvent, AccountEvent> {
+        ScriptInput::Event(EngineEvent::Market(MarketEvent {
+            instrument: InstrumentIndex::new(0),
+            time_exchange: time(seconds),
+            kind: MarketEventKind::Trade(PublicTrade {
+                price,
+                quantity: Decimal::ONE,
+                side: OrderSide::Buy,
+            }),
+        }))
+    }
+
+    fn fill(seconds: i64, name: &str, price: Decimal) -> ScriptInput<MarketEvent, AccountEvent> {
+        ScriptInput::Event(EngineEvent::Account(AccountEvent {
+            time_exchange: time(seconds),
+            kind: AccountEventKind::Fill(Fill {
+                instrument: InstrumentIndex::new(0),
+                cid: cid(name),
+                side: OrderSide::Buy,
+                offset: Offset::Open,
+                price,
+                quantity: dec!(2),
+                fee: Decimal::ZERO,
+            }),
+        }))
+    }
+
+    fn harness() -> StrategyHarness<DefaultEngineState, Bidder> {
+        let state = DefaultEngineState::new([InstrumentSpec {
+            name: "rb2510".into(),
+            product: "rb".into(),
+            quote_asset: AssetIndex::new(0),
+            multiplier: dec!(10),
+            tick_size: dec!(1),
+        }]);
+
+        StrategyHarness::new(time(0), state, Bidder::default())
+    }
+
+    fn script() -> Vec<Step> {
+        vec![
+            Step::new(ScriptInput::Start, vec![open("entry", OrderSide::Buy, Offset::Open, dec!(100))]),
+            Step::new(trade(10, dec!(101)), Vec::new()),
+            Step::new(fill(15, "entry", dec!(100)), vec![open("stop", OrderSide::Sell, Offset::Close, dec!(95))]),
+            // The timer due at 30s fires on the first event past it
+            Step::new(trade(40, dec!(102)), vec![cancel("stop")]),
+            Step::new(trade(45, dec!(106)), vec![open("take-profit", OrderSide::Sell, Offset::Close, dec!(106))]),
+            Step::new(trade(50, dec!(107)), Vec::new()),
+            // The stop is already pending cancel
+            Step::new(ScriptInput::Stop, vec![cancel("take-profit")]),
+        ]
+    }
+
+    #[test]
+    fn test_run_script_drives_every_callback() {
+        let mut harness = harness();
+        assert_eq!(harness.run_script(script()), Ok(()));
+
+        assert!(harness.strategy().is_profit_taken);
+        let state = harness.state().instrument(InstrumentIndex::new(0)).unwrap();
+        assert_eq!(state.position.long.quantity, dec!(2));
+        assert_eq!(state.orders.len(), 2);
+    }
+
+    #[test]
+    fn test_run_script_stops_at_first_mismatch() {
+        let mut script = script();
+        script[2].expected = Vec::new();
+
+        let mut harness = harness();
+        assert_eq!(
+            harness.run_script(script),
+            Err(ScriptMismatch {
+                step: 2,
+                expected: Vec::new(),
+                actual: vec![open("stop", OrderSide::Sell, Offset::Close, dec!(95))],
+            })
+        );
+
+        // Steps after the mismatch are never run
+        assert!(!harness.strategy().is_profit_taken);
+        assert_eq!(
+            harness.state().instrument(InstrumentIndex::new(0)).unwrap().trade_last.unwrap().price,
+            dec!(101)
+        );
+    }
+
+    #[test]
+    fn test_step_fires_timers_on_event_time() {
+        let mut harness = harness();
+        harness.step(ScriptInput::Start);
+
+        // Nothing is due until 30s, however far the next event is past it
+        assert!(harness.step(trade(29, dec!(101))).is_empty());
+        assert_eq!(harness.step(trade(95, dec!(101))), vec![cancel("entry")]);
+    }
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use derive_more::{Constructor, Display};
+use smol_str::SmolStr;
+
+use crate::engine::{
+    event::{Fill, OrderUpdate},
+    order::ExecutionRequest,
+    state::EngineState,
+};
+
+pub mod harness;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display, Constructor)]
+pub struct TimerId(SmolStr);
+
+// Fired by the engine once the clock passes each `interval` since the last firing. Timers are
+// driven by processed events, so a quiet feed in live trading delays them.
+#[derive(Debug, Clone, PartialEq, Eq, Constructor)]
+pub struct Timer {
+    pub id: TimerId,
+    pub interval: TimeDelta,
+}
+
+// Every callback is handed the state after the event was applied, and returns the order and cancel
+// requests to send.
+pub trait StrategyManager<State>
+where
+    State: EngineState,
+{
+    // Timers are read once when the engine starts.
+    fn timers(&self) -> Vec<Timer> {
+        Vec::new()
+    }
+
+    fn on_start(&mut self, _state: &State, _time: DateTime<Utc>) -> Vec<ExecutionRequest> {
+        Vec::new()
+    }
+
+    // Last chance to cancel or flatten before the engine shuts down.
+    fn on_stop(&mut self, _state: &State, _time: DateTime<Utc>) -> Vec<ExecutionRequest> {
+        Vec::new()
+    }
+
+    fn on_market(&mut self, state: &State, event: &State::Market) -> Vec<ExecutionRequest>;
+
+    fn on_order_update(&mut self, _state: &State, _update: &OrderUpdate) -> Vec<ExecutionRequest> {
+        Vec::new()
+    }
+
+    fn on_fill(&mut self, _state: &State, _fill: &Fill) -> Vec<ExecutionRequest> {
+        Vec::new()
+    }
+
+    // Account events that are neither order updates nor fills, eg/ balance snapshots.
+    fn on_account(&mut self, _state: &State, _event: &State::Account) -> Vec<ExecutionRequest> {
+        Vec::new()
+    }
+
+    fn on_timer(&mut self, _state: &State, _timer: &TimerId, _time: DateTime<Utc>) -> Vec<ExecutionRequest> {
+        Vec::new()
+    }
+}
+
+pub type BarterDefaultStrategy<State> = barter::strategy::DefaultStrategy<State>;
@@ -1,16 +1,22 @@
-use crate::protocol::websocket::BarterWsMessage;
+use crate::protocol::websocket::WsMessage;
 
-mod simulation;
+pub mod simulation;
 
 pub trait Exchange {}
 
 pub trait ExchangeExt {}
 
+// Per connection subscription limits published by an exchange, used to shard large subscription sets.
+pub trait SubscriptionLimit {
+    const SUBSCRIPTIONS_PER_CONNECTION_MAX: usize;
+    const CONNECTIONS_MAX: Option<usize> = None;
+}
+
 pub trait BarterConnector: barter_data::exchange::Connector {}
 pub trait BarterExchangeServer: barter_data::exchange::ExchangeServer {}
 
 #[derive(Debug)]
 pub struct PingInterval {
     pub interval: tokio::time::Interval,
-    pub ping: fn() -> BarterWsMessage,
+    pub ping: fn() -> WsMessage,
 }
@@ -0,0 +1,416 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use parking_lot::Mutex;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tracing::{debug, warn};
+
+use crate::protocol::fix::{
+    BEGIN_STRING_FIX44,
+    codec::{FixDecoder, FixMessage, format_sending_time},
+    msg_type,
+    tag,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct MockFixScript {
+    // The n-th accepted connection plays the n-th script, any further connection is dropped on accept.
+    pub connections: Vec<MockFixConnectionScript>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockFixConnectionScript {
+    pub steps: Vec<MockFixStep>,
+}
+
+// Steps play after the acceptor has answered the initiator's Logon.
+#[derive(Debug, Clone)]
+pub enum MockFixStep {
+    // Header fields are stamped by the acceptor.
+    Send(FixMessage),
+    // Burns outgoing sequence numbers, so the next message arrives with a gap.
+    SkipSequence(u64),
+    Delay { ms: u64 },
+    AwaitMessage,
+    Logout { text: Option<String> },
+    Disconnect,
+}
+
+// Loopback FIX acceptor for offline session tests. TestRequests are answered with a Heartbeat,
+// ResendRequests with a gap fill and Logouts with a Logout. Outgoing sequence numbers carry over
+// between connections, unless the initiator logs on with ResetSeqNumFlag.
+#[derive(Debug)]
+pub struct MockFixAcceptor {
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<FixMessage>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockFixAcceptor {
+    pub async fn start(script: MockFixScript) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let log = Arc::clone(&received);
+        let handle = tokio::spawn(async move {
+            let mut connections = script.connections.into_iter();
+            let mut next_outgoing = 1;
+
+            // One session at a time, as a FIX acceptor only allows a single connection per session
+            while let Ok((tcp, peer)) = listener.accept().await {
+                let Some(connection) = connections.next() else {
+                    debug!(?peer, "Mock FIX script exhausted, dropping connection.");
+                    continue;
+                };
+
+                let mut counterparty = MockFixCounterparty {
+                    tcp,
+                    decoder: FixDecoder::default(),
+                    next_outgoing,
+                    comp_ids: None,
+                    is_logout_sent: false,
+                    received: Arc::clone(&log),
+                };
+                counterparty.play(connection).await;
+                next_outgoing = counterparty.next_outgoing;
+            }
+        });
+
+        Ok(Self { addr, received, handle })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // Every message received from initiators so far, in arrival order.
+    pub fn received(&self) -> Vec<FixMessage> {
+        self.received.lock().clone()
+    }
+}
+
+impl Drop for MockFixAcceptor {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[derive(Debug)]
+struct MockFixCounterparty {
+    tcp: TcpStream,
+    decoder: FixDecoder,
+    next_outgoing: u64,
+    comp_ids: Option<(String, String)>,
+    is_logout_sent: bool,
+    received: Arc<Mutex<Vec<FixMessage>>>,
+}
+
+impl MockFixCounterparty {
+    async fn play(&mut self, connection: MockFixConnectionScript) {
+        let Some(logon) = self.next_message().await else {
+            return;
+        };
+        if logon.msg_type() != msg_type::LOGON {
+            warn!(?logon, "Mock FIX acceptor expected Logon, dropping connection.");
+            return;
+        }
+
+        if logon.is_flag_set(tag::RESET_SEQ_NUM_FLAG) {
+            self.next_outgoing = 1;
+        }
+        self.comp_ids = match (logon.get_str(tag::TARGET_COMP_ID), logon.get_str(tag::SENDER_COMP_ID)) {
+            (Ok(sender), Ok(target)) => Some((sender.to_owned(), target.to_owned())),
+            _ => None,
+        };
+
+        let mut response = FixMessage::new(msg_type::LOGON).with(tag::ENCRYPT_METHOD, 0);
+        if let Ok(heartbeat) = logon.get_str(tag::HEART_BT_INT) {
+            response.push(tag::HEART_BT_INT, heartbeat);
+        }
+        if logon.is_flag_set(tag::RESET_SEQ_NUM_FLAG) {
+            response.push(tag::RESET_SEQ_NUM_FLAG, "Y");
+        }
+        if !self.send(response).await {
+            return;
+        }
+
+        for step in connection.steps {
+            let message = match step {
+                MockFixStep::Send(message) => message,
+                MockFixStep::SkipSequence(count) => {
+                    self.next_outgoing += count;
+                    continue;
+                },
+                MockFixStep::Delay { ms } => {
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                    continue;
+                },
+                MockFixStep::AwaitMessage => match self.next_message().await {
+                    Some(_) => continue,
+                    None => return,
+                },
+                MockFixStep::Logout { text } => {
+                    let mut logout = FixMessage::new(msg_type::LOGOUT);
+                    if let Some(text) = text {
+                        logout.push(tag::TEXT, text);
+                    }
+                    self.is_logout_sent = true;
+                    logout
+                },
+                // Dropping the socket without a Logout simulates an abrupt disconnect
+                MockFixStep::Disconnect => return,
+            };
+
+            if !self.send(message).await {
+                return;
+            }
+        }
+
+        // Keep the session serviced once the script is played out, until the initiator goes away
+        while self.next_message().await.is_some() {}
+    }
+
+    // Answers session level requests before handing the message back, `None` once the connection is over.
+    async fn next_message(&mut self) -> Option<FixMessage> {
+        let mut read_buffer = [0u8; 4096];
+
+        loop {
+            match self.decoder.decode() {
+                Some(Ok(message)) => {
+                    debug!(?message, "Mock FIX acceptor received message.");
+                    self.received.lock().push(message.clone());
+
+                    let is_open = match message.msg_type() {
+                        msg_type::TEST_REQUEST if !self.is_logout_sent => {
+                            let heartbeat = FixMessage::new(msg_type::HEARTBEAT).with(tag::TEST_REQ_ID, message.get_str(tag::TEST_REQ_ID).unwrap_or_default());
+                            self.send(heartbeat).await
+                        },
+                        msg_type::RESEND_REQUEST => {
+                            let begin = message.get_parsed::<u64>(tag::BEGIN_SEQ_NO).unwrap_or(1);
+                            let gap_fill = FixMessage::new(msg_type::SEQUENCE_RESET)
+                                .with(tag::GAP_FILL_FLAG, "Y")
+                                .with(tag::NEW_SEQ_NO, self.next_outgoing);
+                            self.send_with_sequence(gap_fill, begin, true).await
+                        },
+                        // Only a Logout initiated by the other side is answered
+                        msg_type::LOGOUT if !self.is_logout_sent => {
+                            self.send(FixMessage::new(msg_type::LOGOUT)).await;
+                            false
+                        },
+                        msg_type::LOGOUT => false,
+                        _ => true,
+                    };
+
+                    return is_open.then_some(message);
+                },
+                Some(Err(error)) => warn!(%error, "Mock FIX acceptor received garbled message."),
+                None => match self.tcp.read(&mut read_buffer).await {
+                    Ok(0) | Err(_) => return None,
+                    Ok(read) => self.decoder.extend(&read_buffer[..read]),
+                },
+            }
+        }
+    }
+
+    async fn send(&mut self, message: FixMessage) -> bool {
+        let sequence = self.next_outgoing;
+        self.next_outgoing += 1;
+
+        self.send_with_sequence(message, sequence, false).await
+    }
+
+    async fn send_with_sequence(&mut self, message: FixMessage, sequence: u64, poss_dup: bool) -> bool {
+        let (sender, target) = self.comp_ids.clone().unwrap_or_default();
+        let sending_time = format_sending_time(Utc::now());
+
+        let mut stamped = FixMessage::new(message.msg_type())
+            .with(tag::SENDER_COMP_ID, sender)
+            .with(tag::TARGET_COMP_ID, target)
+            .with(tag::MSG_SEQ_NUM, sequence)
+            .with(tag::SENDING_TIME, &sending_time);
+        if poss_dup {
+            stamped.push(tag::POSS_DUP_FLAG, "Y");
+            stamped.push(tag::ORIG_SENDING_TIME, &sending_time);
+        }
+        message.fields().for_each(|(tag, value)| stamped.push_bytes(tag, value));
+
+        let mut buffer = Vec::new();
+        stamped.encode(BEGIN_STRING_FIX44, &mut buffer);
+
+        match self.tcp.write_all(&buffer).await {
+            Ok(()) => true,
+            Err(error) => {
+                warn!(?error, "Mock FIX acceptor failed to send message.");
+                false
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::protocol::fix::{FixConnection, FixSession, FixSessionConfig, MemorySequenceStore};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn execution_report(order_id: &str) -> FixMessage {
+        FixMessage::new("8").with(37, order_id)
+    }
+
+    async fn connect(acceptor: &MockFixAcceptor, config: FixSessionConfig) -> FixConnection {
+        let session = FixSession::new(config, MemorySequenceStore::default()).unwrap();
+        FixConnection::connect(acceptor.addr(), session).await.unwrap()
+    }
+
+    async fn next(connection: &mut FixConnection) -> FixMessage {
+        tokio::time::timeout(TIMEOUT, connection.next())
+            .await
+            .expect("timed out waiting for FIX message")
+            .expect("FIX connection ended")
+            .unwrap()
+    }
+
+    // Waits until the acceptor has received a message satisfying `is_match`, returning everything received.
+    async fn received_until(acceptor: &MockFixAcceptor, is_match: impl Fn(&FixMessage) -> bool) -> Vec<FixMessage> {
+        tokio::time::timeout(TIMEOUT, async {
+            loop {
+                let received = acceptor.received();
+                if received.iter().any(&is_match) {
+                    return received;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the acceptor to receive a message")
+    }
+
+    fn script(steps: Vec<MockFixStep>) -> MockFixScript {
+        MockFixScript {
+            connections: vec![MockFixConnectionScript { steps }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logon_application_messages_and_logout() {
+        let acceptor = MockFixAcceptor::start(script(vec![MockFixStep::Send(execution_report("1"))])).await.unwrap();
+        let mut connection = connect(&acceptor, FixSessionConfig::new("CLIENT", "VENUE")).await;
+
+        let report = next(&mut connection).await;
+        assert_eq!(report.get_str(37).unwrap(), "1");
+        assert_eq!(report.get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(), 2);
+        assert_eq!(report.get_str(tag::SENDER_COMP_ID).unwrap(), "VENUE");
+
+        let logon = &acceptor.received()[0];
+        assert_eq!(logon.msg_type(), msg_type::LOGON);
+        assert_eq!(logon.get_str(tag::SENDER_COMP_ID).unwrap(), "CLIENT");
+        assert_eq!(logon.get_str(tag::HEART_BT_INT).unwrap(), "30");
+        assert_eq!(logon.get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(), 1);
+
+        // The acceptor answers the Logout, which ends the connection
+        connection.logout(Some("done".to_owned())).unwrap();
+        assert!(tokio::time::timeout(TIMEOUT, connection.next()).await.unwrap().is_none());
+        assert_eq!(acceptor.received().last().unwrap().msg_type(), msg_type::LOGOUT);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_and_test_request() {
+        // Once played out the acceptor stays silent apart from answering TestRequests
+        let test_request = FixMessage::new(msg_type::TEST_REQUEST).with(tag::TEST_REQ_ID, "probe");
+        let acceptor = MockFixAcceptor::start(script(vec![MockFixStep::Send(test_request)])).await.unwrap();
+
+        let mut config = FixSessionConfig::new("CLIENT", "VENUE");
+        config.heartbeat_interval = Duration::from_secs(1);
+        let connection = connect(&acceptor, config).await;
+
+        let received = received_until(&acceptor, |message| message.msg_type() == msg_type::TEST_REQUEST).await;
+        let answer = received
+            .iter()
+            .find(|message| message.msg_type() == msg_type::HEARTBEAT && message.get(tag::TEST_REQ_ID).is_some());
+        assert_eq!(answer.unwrap().get_str(tag::TEST_REQ_ID).unwrap(), "probe");
+
+        // The answered TestRequest keeps the session alive
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        connection.send(FixMessage::new("D").with(11, "order-1")).unwrap();
+        received_until(&acceptor, |message| message.msg_type() == "D").await;
+    }
+
+    #[tokio::test]
+    async fn test_incoming_gap_requests_resend_and_recovers() {
+        let acceptor = MockFixAcceptor::start(script(vec![
+            MockFixStep::Send(execution_report("1")),
+            MockFixStep::SkipSequence(2),
+            MockFixStep::Send(execution_report("2")),
+            // The ResendRequest is answered with a gap fill up to the next outgoing sequence
+            MockFixStep::AwaitMessage,
+            MockFixStep::Send(execution_report("3")),
+        ]))
+        .await
+        .unwrap();
+        let mut connection = connect(&acceptor, FixSessionConfig::new("CLIENT", "VENUE")).await;
+
+        assert_eq!(next(&mut connection).await.get_str(37).unwrap(), "1");
+
+        // "2" arrived out of sequence and was gap filled, so it is never delivered
+        let report = next(&mut connection).await;
+        assert_eq!(report.get_str(37).unwrap(), "3");
+        assert_eq!(report.get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(), 6);
+
+        let received = acceptor.received();
+        let resend = received.iter().find(|message| message.msg_type() == msg_type::RESEND_REQUEST).unwrap();
+        assert_eq!(resend.get_parsed::<u64>(tag::BEGIN_SEQ_NO).unwrap(), 3);
+        assert_eq!(resend.get_parsed::<u64>(tag::END_SEQ_NO).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_outgoing_resend_replays_and_gap_fills() {
+        let resend_request = FixMessage::new(msg_type::RESEND_REQUEST).with(tag::BEGIN_SEQ_NO, 1).with(tag::END_SEQ_NO, 0);
+        let acceptor = MockFixAcceptor::start(script(vec![MockFixStep::AwaitMessage, MockFixStep::Send(resend_request)]))
+            .await
+            .unwrap();
+        let connection = connect(&acceptor, FixSessionConfig::new("CLIENT", "VENUE")).await;
+
+        connection.send(FixMessage::new("D").with(11, "order-1")).unwrap();
+        let received = received_until(&acceptor, |message| message.is_flag_set(tag::POSS_DUP_FLAG) && message.msg_type() == "D").await;
+
+        // Logon (1) is gap filled, the order (2) replayed with its original sending time
+        let replay = &received[2..];
+        assert_eq!(replay[0].msg_type(), msg_type::SEQUENCE_RESET);
+        assert!(replay[0].is_flag_set(tag::GAP_FILL_FLAG));
+        assert_eq!(replay[0].get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(), 1);
+        assert_eq!(replay[0].get_parsed::<u64>(tag::NEW_SEQ_NO).unwrap(), 2);
+
+        assert_eq!(replay[1].get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(), 2);
+        assert_eq!(replay[1].get_str(11).unwrap(), "order-1");
+        assert_eq!(
+            replay[1].get_str(tag::ORIG_SENDING_TIME).unwrap(),
+            received[1].get_str(tag::SENDING_TIME).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sequence_reset_moves_incoming_sequence() {
+        let reset = FixMessage::new(msg_type::SEQUENCE_RESET).with(tag::NEW_SEQ_NO, 10);
+        let acceptor = MockFixAcceptor::start(script(vec![
+            MockFixStep::Send(reset),
+            MockFixStep::SkipSequence(7),
+            MockFixStep::Send(execution_report("1")),
+        ]))
+        .await
+        .unwrap();
+        let mut connection = connect(&acceptor, FixSessionConfig::new("CLIENT", "VENUE")).await;
+
+        let report = next(&mut connection).await;
+        assert_eq!(report.get_str(37).unwrap(), "1");
+        assert_eq!(report.get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(), 10);
+        assert!(acceptor.received().iter().all(|message| message.msg_type() != msg_type::RESEND_REQUEST));
+    }
+}
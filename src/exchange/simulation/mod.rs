@@ -1,2 +1,12 @@
+#[cfg(any(test, feature = "test-support"))]
+mod fix;
+#[cfg(any(test, feature = "test-support"))]
+mod websocket;
+
+#[cfg(any(test, feature = "test-support"))]
+pub use fix::{MockFixAcceptor, MockFixConnectionScript, MockFixScript, MockFixStep};
+#[cfg(any(test, feature = "test-support"))]
+pub use websocket::{MockConnectionScript, MockStep, MockWebsocketScript, MockWebsocketServer};
+
 #[derive(Debug)]
 pub struct SimulatedExchange {}
@@ -0,0 +1,313 @@
+use std::{net::SocketAddr, path::Path, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::{net::TcpListener, task::JoinHandle};
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, frame::coding::CloseCode};
+use tracing::{debug, warn};
+
+use crate::protocol::websocket::WsMessage;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockWebsocketScript {
+    // The n-th accepted connection plays the n-th script, any further connection is dropped on accept.
+    #[serde(default)]
+    pub connections: Vec<MockConnectionScript>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockConnectionScript {
+    #[serde(default)]
+    pub steps: Vec<MockStep>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MockStep {
+    Text { payload: String },
+    Binary { payload: Vec<u8> },
+    Ping,
+    Pong,
+    Delay { ms: u64 },
+    AwaitMessage,
+    Close { code: Option<u16>, reason: Option<String> },
+    Disconnect,
+}
+
+#[derive(Debug)]
+pub struct MockWebsocketServer {
+    addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl MockWebsocketServer {
+    pub async fn start(script: MockWebsocketScript) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let handle = tokio::spawn(async move {
+            let mut connections = script.connections.into_iter();
+
+            while let Ok((tcp, peer)) = listener.accept().await {
+                let Some(connection) = connections.next() else {
+                    debug!(?peer, "Mock WebSocket script exhausted, dropping connection.");
+                    continue;
+                };
+
+                tokio::spawn(async move {
+                    match tokio_tungstenite::accept_async(tcp).await {
+                        Ok(websocket) => play_connection(websocket, connection).await,
+                        Err(error) => warn!(?peer, ?error, "Mock WebSocket handshake failed."),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, handle })
+    }
+
+    pub async fn from_fixture(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let fixture = tokio::fs::read_to_string(path).await?;
+        let script = toml::from_str::<MockWebsocketScript>(&fixture)?;
+
+        Ok(Self::start(script).await?)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+}
+
+impl Drop for MockWebsocketServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn play_connection<Socket>(mut websocket: tokio_tungstenite::WebSocketStream<Socket>, connection: MockConnectionScript)
+where
+    Socket: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    for step in connection.steps {
+        let message = match step {
+            MockStep::Text { payload } => WsMessage::text(payload),
+            MockStep::Binary { payload } => WsMessage::binary(payload),
+            MockStep::Ping => WsMessage::Ping(Default::default()),
+            MockStep::Pong => WsMessage::Pong(Default::default()),
+            MockStep::Delay { ms } => {
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                continue;
+            },
+            MockStep::AwaitMessage => {
+                let message = websocket.next().await;
+                debug!(?message, "Mock WebSocket received message.");
+                continue;
+            },
+            MockStep::Close { code, reason } => WsMessage::Close(Some(CloseFrame {
+                code: code.map_or(CloseCode::Normal, CloseCode::from),
+                reason: reason.unwrap_or_default().into(),
+            })),
+            // Dropping the socket without a close handshake simulates an abrupt disconnect
+            MockStep::Disconnect => return,
+        };
+
+        if let Err(error) = websocket.send(message).await {
+            warn!(?error, "Mock WebSocket failed to send scripted message.");
+            return;
+        }
+    }
+
+    // Keep the connection open once the script is played out, until the client goes away
+    while let Some(Ok(message)) = websocket.next().await {
+        debug!(?message, "Mock WebSocket received message.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, marker::PhantomData, sync::Arc};
+
+    use parking_lot::Mutex;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::protocol::{
+        error::SocketError,
+        stream::{
+            BackoffJitter,
+            ExchangeStream,
+            ReconnectionAttempt,
+            ReconnectionLimit,
+            ReconnectionPolicy,
+            RecoverableStream,
+            StreamEvent,
+            init_recoverable_stream,
+        },
+        transformer::Transformer,
+        websocket::{WebSocket, WebsocketJsonParser, connect, is_websocket_disconnected},
+    };
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/websocket_trades.toml");
+
+    #[derive(Debug, Deserialize)]
+    struct TradeMessage {
+        #[serde(rename = "s")]
+        symbol: String,
+        #[serde(rename = "t")]
+        id: u64,
+        #[serde(rename = "p")]
+        price: Decimal,
+        #[serde(rename = "q")]
+        quantity: Decimal,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Trade {
+        symbol: String,
+        id: u64,
+        notional: Decimal,
+    }
+
+    #[derive(Debug)]
+    struct TradeError(Box<dyn std::error::Error>);
+
+    impl From<Box<dyn std::error::Error>> for TradeError {
+        fn from(error: Box<dyn std::error::Error>) -> Self {
+            Self(error)
+        }
+    }
+
+    impl TradeError {
+        fn socket(&self) -> &SocketError {
+            self.0.downcast_ref::<SocketError>().expect("parser errors are SocketErrors")
+        }
+
+        fn is_disconnect(&self) -> bool {
+            matches!(self.socket(), SocketError::WebSocket(error) if is_websocket_disconnected(error))
+        }
+    }
+
+    struct TradeTransformer;
+
+    impl Transformer for TradeTransformer {
+        type Error = TradeError;
+        type Input = TradeMessage;
+        type Output = Trade;
+        type OutputIter = Vec<Result<Self::Output, Self::Error>>;
+
+        fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+            vec![Ok(Trade {
+                symbol: input.symbol,
+                id: input.id,
+                notional: input.price * input.quantity,
+            })]
+        }
+    }
+
+    type TradeStream = ExchangeStream<WebsocketJsonParser, WebSocket, TradeTransformer>;
+
+    async fn subscribe(url: String) -> Result<TradeStream, SocketError> {
+        let mut websocket = connect(url).await?;
+        websocket
+            .send(WsMessage::text(r#"{"method":"SUBSCRIBE","params":["btcusdt@trade"]}"#))
+            .await
+            .map_err(|error| SocketError::WebSocket(Box::new(error)))?;
+
+        Ok(ExchangeStream {
+            stream: websocket,
+            transformer: TradeTransformer,
+            buffer: VecDeque::new(),
+            protocol_marker: PhantomData,
+        })
+    }
+
+    fn trade(symbol: &str, id: u64, notional: Decimal) -> Trade {
+        Trade {
+            symbol: symbol.to_owned(),
+            id,
+            notional,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exchange_stream_parse_transform_disconnect() {
+        let server = MockWebsocketServer::from_fixture(FIXTURE).await.unwrap();
+
+        let items = subscribe(server.url()).await.unwrap().collect::<Vec<_>>().await;
+
+        let [first, malformed, second, disconnect] = items.as_slice() else {
+            panic!("expected 4 items, got {items:?}");
+        };
+        assert_eq!(first.as_ref().unwrap(), &trade("BTCUSDT", 1, dec!(961.8465)));
+        assert!(matches!(malformed.as_ref().unwrap_err().socket(), SocketError::Deserialise { .. }));
+        assert!(!malformed.as_ref().unwrap_err().is_disconnect());
+        assert_eq!(second.as_ref().unwrap(), &trade("ETHUSDT", 2, dec!(3210.5)));
+        assert!(disconnect.as_ref().unwrap_err().is_disconnect());
+    }
+
+    #[tokio::test]
+    async fn test_exchange_stream_ends_on_close_frame() {
+        let script = toml::from_str::<MockWebsocketScript>(&std::fs::read_to_string(FIXTURE).unwrap()).unwrap();
+        let server = MockWebsocketServer::start(MockWebsocketScript {
+            connections: script.connections[1..].to_vec(),
+        })
+        .await
+        .unwrap();
+
+        let items = subscribe(server.url()).await.unwrap().collect::<Vec<_>>().await;
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].as_ref().unwrap(), &trade("BTCUSDT", 3, dec!(32062.000)));
+    }
+
+    #[tokio::test]
+    async fn test_recoverable_stream_reconnects() {
+        let server = MockWebsocketServer::from_fixture(FIXTURE).await.unwrap();
+        let url = server.url();
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let policy = ReconnectionPolicy {
+            backoff_ms_initial: 10,
+            backoff_multiplier: 2.0,
+            backoff_ms_max: 100,
+            jitter: BackoffJitter::None,
+            attempts_max: Some(2),
+            backoff_ms_budget: None,
+        };
+
+        let streams = init_recoverable_stream(move || subscribe(url.clone())).await.unwrap();
+        let events = streams
+            .with_reconnect_policy(policy, 0, {
+                let attempts = Arc::clone(&attempts);
+                move |attempt| {
+                    if let ReconnectionAttempt::Terminated { attempt, limit } = attempt {
+                        attempts.lock().push((attempt, limit));
+                    }
+                }
+            })
+            .filter_map(|result| std::future::ready(result.ok()))
+            .with_termination_on_error(TradeError::is_disconnect, 0)
+            .with_reconnection_events("mock")
+            .map(|event| match event {
+                StreamEvent::Reconnecting(origin) => Err(origin),
+                StreamEvent::Item(item) => Ok(item.map_err(|error| error.0.to_string())),
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        // The disconnect ends the first connection without surfacing as an error, the third
+        // connection is refused by the server until the policy gives up
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[0], Ok(Ok(trade("BTCUSDT", 1, dec!(961.8465)))));
+        assert!(matches!(&events[1], Ok(Err(error)) if error.contains("Deserialising JSON")));
+        assert_eq!(events[2], Ok(Ok(trade("ETHUSDT", 2, dec!(3210.5)))));
+        assert_eq!(events[3], Err("mock"));
+        assert_eq!(events[4], Ok(Ok(trade("BTCUSDT", 3, dec!(32062.000)))));
+        assert_eq!(events[5], Err("mock"));
+        assert_eq!(*attempts.lock(), vec![(2, ReconnectionLimit::AttemptsExhausted)]);
+    }
+}
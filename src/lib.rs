@@ -1,12 +1,13 @@
+mod book;
+pub mod engine;
+pub mod exchange;
+pub mod protocol;
+pub mod route;
 pub mod trace;
 pub mod transport;
 
-mod engine;
-mod exchange;
 mod execution;
 mod indexer;
 mod path;
 mod plot;
-mod protocol;
-mod route;
 mod subscription;
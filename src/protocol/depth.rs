@@ -0,0 +1,332 @@
+use std::{fmt::Debug, hash::Hash, marker::PhantomData};
+
+use fnv::FnvHashMap;
+use thiserror::Error;
+use tracing::warn;
+
+// The book module is private, this is its public path. `OrderBookTransformer::book` and the checksum
+// functions hand out the `OrderBook` by shared reference only, so the local book is never edited
+// outside the transformer.
+pub use crate::book::{Level, OrderBook, Side};
+use crate::protocol::transformer::Transformer;
+
+const CHECKSUM_DEPTH_OKX: usize = 25;
+const CHECKSUM_DEPTH_KRAKEN: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepthUpdate<Key, Checksum> {
+    pub key: Key,
+    pub kind: DepthUpdateKind,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+    pub checksum: Option<Checksum>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthUpdateKind {
+    Snapshot,
+    Delta,
+}
+
+// Levels as applied to the local book, where a zero amount removed the level, plus the resulting top
+// of book. The full book stays with the transformer rather than being copied into every update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBookUpdate<Key> {
+    pub key: Key,
+    pub kind: DepthUpdateKind,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+    pub best_bid: Option<Level>,
+    pub best_ask: Option<Level>,
+}
+
+#[derive(Debug, Error)]
+pub enum OrderBookError<Key, Checksum>
+where
+    Key: Debug,
+    Checksum: Debug,
+{
+    #[error("OrderBook {key:?} requires resync, checksum mismatch: expected {expected:?}, computed {computed:?}")]
+    ChecksumMismatch { key: Key, expected: Checksum, computed: Checksum },
+
+    #[error("OrderBook {key:?} requires resync, received delta without a snapshot")]
+    MissingSnapshot { key: Key },
+
+    #[error("OrderBook protocol error: {0}")]
+    Protocol(String),
+}
+
+impl<Key, Checksum> OrderBookError<Key, Checksum>
+where
+    Key: Debug,
+    Checksum: Debug,
+{
+    pub fn is_resync_required(&self) -> bool {
+        matches!(self, Self::ChecksumMismatch { .. } | Self::MissingSnapshot { .. })
+    }
+}
+
+impl<Key, Checksum> From<Box<dyn std::error::Error>> for OrderBookError<Key, Checksum>
+where
+    Key: Debug,
+    Checksum: Debug,
+{
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        Self::Protocol(error.to_string())
+    }
+}
+
+// `Checksum` is the type the exchange sends, eg/ `i32` for OKX and `u32` for Kraken.
+#[derive(Debug)]
+pub struct OrderBookTransformer<Input, Key, Checksum, FnChecksum> {
+    books: FnvHashMap<Key, OrderBook>,
+    checksum: FnChecksum,
+    marker: PhantomData<fn(Input) -> Checksum>,
+}
+
+impl<Input, Key, Checksum, FnChecksum> OrderBookTransformer<Input, Key, Checksum, FnChecksum>
+where
+    Input: Into<DepthUpdate<Key, Checksum>>,
+    Key: Debug + Clone + Eq + Hash,
+    Checksum: Debug + Copy + Eq,
+    FnChecksum: Fn(&OrderBook) -> Checksum,
+{
+    pub fn new(checksum: FnChecksum) -> Self {
+        Self {
+            books: FnvHashMap::default(),
+            checksum,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn book(&self, key: &Key) -> Option<&OrderBook> {
+        self.books.get(key)
+    }
+}
+
+impl<Input, Key, Checksum, FnChecksum> Transformer for OrderBookTransformer<Input, Key, Checksum, FnChecksum>
+where
+    Input: Into<DepthUpdate<Key, Checksum>>,
+    Key: Debug + Clone + Eq + Hash,
+    Checksum: Debug + Copy + Eq,
+    FnChecksum: Fn(&OrderBook) -> Checksum,
+{
+    type Error = OrderBookError<Key, Checksum>;
+    type Input = Input;
+    type Output = OrderBookUpdate<Key>;
+    type OutputIter = Option<Result<Self::Output, Self::Error>>;
+
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        let DepthUpdate {
+            key,
+            kind,
+            bids,
+            asks,
+            checksum,
+        } = input.into();
+
+        let book = match kind {
+            DepthUpdateKind::Snapshot => self
+                .books
+                .entry(key.clone())
+                .insert_entry(OrderBook::from_levels(bids.iter().copied(), asks.iter().copied()))
+                .into_mut(),
+            DepthUpdateKind::Delta => {
+                let Some(book) = self.books.get_mut(&key) else {
+                    return Some(Err(OrderBookError::MissingSnapshot { key }));
+                };
+
+                bids.iter().for_each(|level| book.apply(Side::Bid, *level));
+                asks.iter().for_each(|level| book.apply(Side::Ask, *level));
+
+                book
+            },
+        };
+
+        if let Some(expected) = checksum {
+            let computed = (self.checksum)(book);
+
+            if computed != expected {
+                // The local book can no longer be trusted, deltas are refused until the next snapshot
+                warn!(?key, ?expected, ?computed, "OrderBook checksum mismatch, discarding local book.");
+                self.books.remove(&key);
+
+                return Some(Err(OrderBookError::ChecksumMismatch { key, expected, computed }));
+            }
+        }
+
+        Some(Ok(OrderBookUpdate {
+            best_bid: book.best_bid(),
+            best_ask: book.best_ask(),
+            key,
+            kind,
+            bids,
+            asks,
+        }))
+    }
+}
+
+// OKX: signed CRC32 over the top 25 levels interleaved as `bid_price:bid_amount:ask_price:ask_amount:...`,
+// continuing with the deeper side alone once the other runs out. Decimals keep the scale they were
+// parsed with, so levels render exactly as the exchange sent them.
+pub fn checksum_okx(book: &OrderBook) -> i32 {
+    let mut bids = book.bids().take(CHECKSUM_DEPTH_OKX);
+    let mut asks = book.asks().take(CHECKSUM_DEPTH_OKX);
+    let mut fields = Vec::with_capacity(CHECKSUM_DEPTH_OKX * 4);
+
+    loop {
+        let (bid, ask) = (bids.next(), asks.next());
+        if bid.is_none() && ask.is_none() {
+            break;
+        }
+
+        for level in [bid, ask].into_iter().flatten() {
+            fields.push(level.price.to_string());
+            fields.push(level.amount.to_string());
+        }
+    }
+
+    crc32fast::hash(fields.join(":").as_bytes()) as i32
+}
+
+// Kraken: CRC32 over the top 10 asks then the top 10 bids, each price and amount concatenated without
+// separators after dropping the decimal point and leading zeros. Requires decimals parsed with the
+// precision Kraken checksums them at, as for the strings sent by the v1 book feed.
+pub fn checksum_kraken(book: &OrderBook) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+
+    for level in book.asks().take(CHECKSUM_DEPTH_KRAKEN).chain(book.bids().take(CHECKSUM_DEPTH_KRAKEN)) {
+        for field in [level.price, level.amount] {
+            let field = field.to_string().replace('.', "");
+            hasher.update(field.trim_start_matches('0').as_bytes());
+        }
+    }
+
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn level(price: &str, amount: &str) -> Level {
+        Level::new(Decimal::from_str(price).unwrap(), Decimal::from_str(amount).unwrap())
+    }
+
+    // Example book of the Kraken v1 checksum guide.
+    fn kraken_book() -> (Vec<Level>, Vec<Level>) {
+        let bids = [
+            "0.05000", "0.04995", "0.04990", "0.04980", "0.04975", "0.04970", "0.04965", "0.04960", "0.04955", "0.04950",
+        ];
+        let asks = [
+            "0.05005", "0.05010", "0.05015", "0.05020", "0.05025", "0.05030", "0.05035", "0.05040", "0.05045", "0.05050",
+        ];
+
+        (
+            bids.iter().map(|price| level(price, "0.00000500")).collect(),
+            asks.iter().map(|price| level(price, "0.00000500")).collect(),
+        )
+    }
+
+    #[test]
+    fn test_checksum_kraken() {
+        let (bids, asks) = kraken_book();
+
+        assert_eq!(checksum_kraken(&OrderBook::from_levels(bids, asks)), 974947235);
+    }
+
+    #[test]
+    fn test_checksum_okx() {
+        // Check string `3366.1:7:3366.8:9:3366:6:3368:8` of the OKX order book checksum guide
+        let book = OrderBook::from_levels([level("3366.1", "7"), level("3366", "6")], [level("3366.8", "9"), level("3368", "8")]);
+
+        assert_eq!(checksum_okx(&book), -1881014294);
+    }
+
+    #[test]
+    fn test_checksum_okx_uneven_depth() {
+        let book = OrderBook::from_levels([level("3366.1", "7"), level("3366", "6"), level("3365.5", "1.50")], [level("3366.8", "9")]);
+
+        assert_eq!(checksum_okx(&book), crc32fast::hash(b"3366.1:7:3366.8:9:3366:6:3365.5:1.50") as i32);
+    }
+
+    type KrakenTransformer = OrderBookTransformer<DepthUpdate<&'static str, u32>, &'static str, u32, fn(&OrderBook) -> u32>;
+
+    fn update(kind: DepthUpdateKind, bids: Vec<Level>, asks: Vec<Level>, checksum: Option<u32>) -> DepthUpdate<&'static str, u32> {
+        DepthUpdate {
+            key: "XBT/USD",
+            kind,
+            bids,
+            asks,
+            checksum,
+        }
+    }
+
+    #[test]
+    fn test_transform_snapshot_and_delta() {
+        let mut transformer = KrakenTransformer::new(checksum_kraken);
+        let (bids, asks) = kraken_book();
+
+        let snapshot = transformer
+            .transform(update(DepthUpdateKind::Snapshot, bids, asks, Some(974947235)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.bids.len(), 10);
+        assert_eq!(snapshot.best_bid, Some(level("0.05000", "0.00000500")));
+        assert_eq!(snapshot.best_ask, Some(level("0.05005", "0.00000500")));
+
+        let delta = vec![level("0.05000", "0"), level("0.04999", "0.00001000")];
+        let mut book = transformer.book(&"XBT/USD").unwrap().clone();
+        delta.iter().for_each(|level| book.apply(Side::Bid, *level));
+
+        let update = transformer
+            .transform(update(DepthUpdateKind::Delta, delta.clone(), Vec::new(), Some(checksum_kraken(&book))))
+            .unwrap()
+            .unwrap();
+        assert_eq!(update.kind, DepthUpdateKind::Delta);
+        assert_eq!(update.bids, delta);
+        assert!(update.asks.is_empty());
+        assert_eq!(update.best_bid, Some(level("0.04999", "0.00001000")));
+        assert_eq!(transformer.book(&"XBT/USD"), Some(&book));
+    }
+
+    #[test]
+    fn test_transform_checksum_mismatch_requires_resync() {
+        let mut transformer = KrakenTransformer::new(checksum_kraken);
+        let (bids, asks) = kraken_book();
+        transformer.transform(update(DepthUpdateKind::Snapshot, bids, asks, None)).unwrap().unwrap();
+
+        let error = transformer
+            .transform(update(DepthUpdateKind::Delta, vec![level("0.04990", "0")], Vec::new(), Some(974947235)))
+            .unwrap()
+            .unwrap_err();
+        assert!(error.is_resync_required());
+        assert!(matches!(error, OrderBookError::ChecksumMismatch { expected: 974947235, .. }));
+        assert!(transformer.book(&"XBT/USD").is_none());
+
+        // Deltas are refused until the next snapshot
+        let error = transformer
+            .transform(update(DepthUpdateKind::Delta, vec![level("0.04990", "1")], Vec::new(), None))
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(error, OrderBookError::MissingSnapshot { key: "XBT/USD" }));
+    }
+
+    #[test]
+    fn test_transform_okx_signed_checksum() {
+        let mut transformer = OrderBookTransformer::<DepthUpdate<u8, i32>, u8, i32, _>::new(checksum_okx);
+        let snapshot = DepthUpdate {
+            key: 1,
+            kind: DepthUpdateKind::Snapshot,
+            bids: vec![level("3366.1", "7"), level("3366", "6")],
+            asks: vec![level("3366.8", "9"), level("3368", "8")],
+            checksum: Some(-1881014294),
+        };
+
+        assert!(transformer.transform(snapshot).unwrap().is_ok());
+    }
+}
@@ -0,0 +1,277 @@
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+use encoding_rs::GB18030;
+use serde::{
+    Deserializer,
+    de::{self, SeqAccess, Visitor},
+};
+use thiserror::Error;
+
+use crate::protocol::{
+    parser::{ProtocolParser, WebsocketParser},
+    websocket::{WsError, WsMessage},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    // Malformed byte sequences are an error.
+    #[default]
+    Strict,
+    // Malformed byte sequences become U+FFFD.
+    Lossy,
+}
+
+pub trait DecodePolicy {
+    const MODE: DecodeMode;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StrictDecode;
+
+impl DecodePolicy for StrictDecode {
+    const MODE: DecodeMode = DecodeMode::Strict;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LossyDecode;
+
+impl DecodePolicy for LossyDecode {
+    const MODE: DecodeMode = DecodeMode::Lossy;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid {encoding} byte sequence: {payload}")]
+pub struct DecodeError {
+    pub encoding: &'static str,
+    pub payload: String,
+}
+
+// GB18030 is a superset of GBK, so one decoder covers both. Trailing NULs padding fixed width C
+// char arrays are trimmed, and pure ASCII input is returned without allocating.
+pub fn decode_gbk(bytes: &[u8], mode: DecodeMode) -> Result<Cow<'_, str>, DecodeError> {
+    let bytes = trim_nul(bytes);
+
+    match mode {
+        DecodeMode::Strict => GB18030.decode_without_bom_handling_and_without_replacement(bytes).ok_or_else(|| DecodeError {
+            encoding: GB18030.name(),
+            payload: bytes.escape_ascii().to_string(),
+        }),
+        DecodeMode::Lossy => Ok(GB18030.decode_without_bom_handling(bytes).0),
+    }
+}
+
+pub fn encode_gbk(text: &str) -> Cow<'_, [u8]> {
+    GB18030.encode(text).0
+}
+
+fn trim_nul(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+    &bytes[..end]
+}
+
+// Field adapters for GBK byte fields, eg/ `#[serde(deserialize_with = "deserialize_gbk")]`.
+// They accept raw bytes or a sequence of `u8`, a string is taken as already decoded.
+pub fn deserialize_gbk<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_with_mode(deserializer, DecodeMode::Strict)
+}
+
+pub fn deserialize_gbk_lossy<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_with_mode(deserializer, DecodeMode::Lossy)
+}
+
+fn deserialize_with_mode<'de, D>(deserializer: D, mode: DecodeMode) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Human readable formats such as JSON hand a string's UTF-8 to `deserialize_bytes` as bytes,
+    // so they have to say whether the field is a string or a byte array
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(GbkVisitor(mode))
+    } else {
+        deserializer.deserialize_bytes(GbkVisitor(mode))
+    }
+}
+
+struct GbkVisitor(DecodeMode);
+
+impl<'de> Visitor<'de> for GbkVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("GBK encoded bytes")
+    }
+
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        decode_gbk(bytes, self.0).map(Cow::into_owned).map_err(E::custom)
+    }
+
+    fn visit_str<E>(self, text: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(text.trim_end_matches('\0').to_owned())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+
+        self.visit_bytes(&bytes)
+    }
+}
+
+// Decodes GBK Binary frames into Text before handing them to `Parser`, Text frames are UTF-8 already.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WebsocketGbkParser<Parser, Policy = StrictDecode> {
+    marker: PhantomData<(Parser, Policy)>,
+}
+
+impl<Parser, Policy> ProtocolParser for WebsocketGbkParser<Parser, Policy> {}
+
+impl<Parser, Policy> WebsocketParser for WebsocketGbkParser<Parser, Policy>
+where
+    Parser: WebsocketParser<Message = WsMessage, Error = WsError>,
+    Policy: DecodePolicy,
+{
+    type Stream = Parser::Stream;
+    type Message = WsMessage;
+    type Error = WsError;
+
+    fn parse<Output>(input: Result<Self::Message, Self::Error>) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: serde::de::DeserializeOwned,
+    {
+        let input = match input {
+            Ok(WsMessage::Binary(payload)) => match decode_gbk(&payload, Policy::MODE) {
+                Ok(text) => Ok(WsMessage::text(text.into_owned())),
+                Err(error) => return Some(Err(error.into())),
+            },
+            other => other,
+        };
+
+        Parser::parse(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::protocol::{error::SocketError, websocket::WebsocketJsonParser};
+
+    // 浦发银行 (SPD Bank) & 资金不足 (insufficient funds) as sent by GBK encoded CTP style gateways.
+    const INSTRUMENT_NAME_GBK: &[u8] = &[0xC6, 0xD6, 0xB7, 0xA2, 0xD2, 0xF8, 0xD0, 0xD0];
+    const ERROR_TEXT_GBK: &[u8] = &[0xD7, 0xCA, 0xBD, 0xF0, 0xB2, 0xBB, 0xD7, 0xE3];
+
+    #[derive(Debug, Deserialize)]
+    struct OrderRejected {
+        #[serde(deserialize_with = "deserialize_gbk")]
+        text: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OrderRejectedLossy {
+        #[serde(deserialize_with = "deserialize_gbk_lossy")]
+        text: String,
+    }
+
+    fn json_frame(text_gbk: &[u8]) -> WsMessage {
+        WsMessage::binary([br#"{"text":""#, text_gbk, br#""}"#].concat())
+    }
+
+    #[test]
+    fn test_decode_gbk_instrument_name() {
+        // Fixed width C char arrays arrive padded with NULs
+        let mut padded = INSTRUMENT_NAME_GBK.to_vec();
+        padded.resize(21, 0);
+
+        assert_eq!(decode_gbk(&padded, DecodeMode::Strict).unwrap(), "浦发银行");
+        assert_eq!(decode_gbk(ERROR_TEXT_GBK, DecodeMode::Strict).unwrap(), "资金不足");
+        assert_eq!(encode_gbk("浦发银行").as_ref(), INSTRUMENT_NAME_GBK);
+    }
+
+    #[test]
+    fn test_decode_gbk_ascii_borrows() {
+        let decoded = decode_gbk(b"rb2410\0\0\0", DecodeMode::Strict).unwrap();
+
+        assert!(matches!(decoded, Cow::Borrowed("rb2410")));
+        assert_eq!(decode_gbk(&[0; 8], DecodeMode::Strict).unwrap(), "");
+    }
+
+    #[test]
+    fn test_decode_gbk_invalid_strict_vs_lossy() {
+        // 0xFF is never valid, 0x81 0x7F is a lead byte with an invalid trail, a trailing 0xC6 is truncated
+        let cases: [(&[u8], &str); 3] = [
+            (&[0xC6, 0xD6, 0xFF], "浦\u{FFFD}"),
+            (&[0x81, 0x7F, 0xC6, 0xD6], "\u{FFFD}\u{7F}浦"),
+            (&[0xC6, 0xD6, 0xC6], "浦\u{FFFD}"),
+        ];
+
+        for (bytes, lossy) in cases {
+            let error = decode_gbk(bytes, DecodeMode::Strict).unwrap_err();
+            assert_eq!(error.encoding, "gb18030");
+            assert_eq!(error.payload, bytes.escape_ascii().to_string());
+
+            assert_eq!(decode_gbk(bytes, DecodeMode::Lossy).unwrap(), lossy);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_gbk_field() {
+        let bytes = serde_json::to_string(ERROR_TEXT_GBK).unwrap();
+        let rejected = serde_json::from_str::<OrderRejected>(&format!(r#"{{"text":{bytes}}}"#)).unwrap();
+        assert_eq!(rejected.text, "资金不足");
+
+        // Strings are already decoded, only the NUL padding is trimmed
+        let rejected = serde_json::from_str::<OrderRejected>(r#"{"text":"资金不足\u0000\u0000"}"#).unwrap();
+        assert_eq!(rejected.text, "资金不足");
+
+        let invalid = r#"{"text":[198,214,255]}"#;
+        let error = serde_json::from_str::<OrderRejected>(invalid).unwrap_err();
+        assert!(error.to_string().contains(r"invalid gb18030 byte sequence: \xc6\xd6\xff"), "{error}");
+        assert_eq!(serde_json::from_str::<OrderRejectedLossy>(invalid).unwrap().text, "浦\u{FFFD}");
+    }
+
+    #[test]
+    fn test_websocket_gbk_parser() {
+        let rejected = WebsocketGbkParser::<WebsocketJsonParser>::parse::<OrderRejected>(Ok(json_frame(ERROR_TEXT_GBK)));
+        assert_eq!(rejected.unwrap().unwrap().text, "资金不足");
+
+        // Text frames are passed through untouched
+        let rejected = WebsocketGbkParser::<WebsocketJsonParser>::parse::<OrderRejected>(Ok(WsMessage::text(r#"{"text":"资金不足"}"#)));
+        assert_eq!(rejected.unwrap().unwrap().text, "资金不足");
+    }
+
+    #[test]
+    fn test_websocket_gbk_parser_invalid_frame() {
+        let frame = json_frame(&[0xD7, 0xCA, 0xFF]);
+
+        let error = WebsocketGbkParser::<WebsocketJsonParser, StrictDecode>::parse::<OrderRejected>(Ok(frame.clone()))
+            .unwrap()
+            .unwrap_err();
+        assert!(error.downcast_ref::<DecodeError>().is_some(), "{error}");
+
+        let rejected = WebsocketGbkParser::<WebsocketJsonParser, LossyDecode>::parse::<OrderRejected>(Ok(frame)).unwrap();
+        assert_eq!(rejected.unwrap().text, "资\u{FFFD}");
+
+        let error = WebsocketGbkParser::<WebsocketJsonParser>::parse::<OrderRejected>(Ok(WsMessage::text("{")))
+            .unwrap()
+            .unwrap_err();
+        assert!(error.downcast_ref::<SocketError>().is_some(), "{error}");
+    }
+}
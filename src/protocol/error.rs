@@ -0,0 +1,34 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::protocol::{rate_limit::RateLimitError, websocket::WsError};
+
+#[derive(Debug, Error)]
+pub enum SocketError {
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<WsError>),
+
+    #[error("Deserialising JSON error: {error} for payload: {payload}")]
+    Deserialise { error: serde_json::Error, payload: String },
+
+    #[error("Deserialising JSON in place error: {error} for payload: {payload}")]
+    DeserialiseInPlace { error: simd_json::Error, payload: String },
+
+    #[error("HTTP error: {0}")]
+    Http(reqwest::Error),
+
+    #[error("HTTP request timed out")]
+    HttpTimeout(reqwest::Error),
+
+    #[error("HTTP response (status={0}) error: {1}")]
+    HttpResponse(StatusCode, String),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(#[from] RateLimitError),
+}
+
+impl From<reqwest::Error> for SocketError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() { Self::HttpTimeout(error) } else { Self::Http(error) }
+    }
+}
@@ -0,0 +1,654 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use fnv::FnvHashSet;
+use serde::{
+    Deserialize,
+    de::{DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, Visitor, value::BorrowedStrDeserializer},
+    forward_to_deserialize_any,
+};
+use smol_str::SmolStr;
+use tracing::warn;
+
+use crate::protocol::fix::{error::FixError, tag};
+
+pub const SOH: u8 = 0x01;
+
+const BEGIN_STRING_PREFIX: &[u8] = b"8=FIX";
+const TRAILER_LEN: usize = b"10=000\x01".len();
+pub(crate) const BODY_LEN_MAX_DEFAULT: usize = 1024 * 1024;
+
+pub type FixGroupInstance<'a> = Vec<(u32, &'a [u8])>;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct FixMessage {
+    msg_type: SmolStr,
+    fields: Vec<(u32, Vec<u8>)>,
+}
+
+impl FixMessage {
+    pub fn new(msg_type: impl Into<SmolStr>) -> Self {
+        Self {
+            msg_type: msg_type.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn msg_type(&self) -> &str {
+        &self.msg_type
+    }
+
+    pub fn with(mut self, tag: u32, value: impl Display) -> Self {
+        self.push(tag, value);
+        self
+    }
+
+    pub fn push(&mut self, tag: u32, value: impl Display) {
+        self.fields.push((tag, value.to_string().into_bytes()));
+    }
+
+    pub fn push_bytes(&mut self, tag: u32, value: impl Into<Vec<u8>>) {
+        self.fields.push((tag, value.into()));
+    }
+
+    // Replaces the first occurrence of `tag`, or appends it if absent.
+    pub fn set(&mut self, tag: u32, value: impl Display) {
+        match self.fields.iter_mut().find(|(field, _)| *field == tag) {
+            Some((_, existing)) => *existing = value.to_string().into_bytes(),
+            None => self.push(tag, value),
+        }
+    }
+
+    pub fn get(&self, tag: u32) -> Option<&[u8]> {
+        self.fields.iter().find(|(field, _)| *field == tag).map(|(_, value)| value.as_slice())
+    }
+
+    pub fn get_str(&self, tag: u32) -> Result<&str, FixError> {
+        let value = self.get(tag).ok_or(FixError::MissingField(tag))?;
+
+        std::str::from_utf8(value).map_err(|_| FixError::InvalidField {
+            tag,
+            value: String::from_utf8_lossy(value).into_owned(),
+        })
+    }
+
+    pub fn get_parsed<T>(&self, tag: u32) -> Result<T, FixError>
+    where
+        T: FromStr,
+    {
+        let value = self.get_str(tag)?;
+
+        value.parse().map_err(|_| FixError::InvalidField { tag, value: value.to_owned() })
+    }
+
+    pub fn is_flag_set(&self, tag: u32) -> bool {
+        self.get(tag) == Some(b"Y")
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.fields.iter().map(|(tag, value)| (*tag, value.as_slice()))
+    }
+
+    // Splits the repeating group counted by `count_tag` into its instances. Each instance starts at
+    // `member_tags[0]` and runs until the next delimiter or the first tag that is not a group member.
+    pub fn group(&self, count_tag: u32, member_tags: &[u32]) -> Result<Vec<FixGroupInstance<'_>>, FixError> {
+        let Some(position) = self.fields.iter().position(|(field, _)| *field == count_tag) else {
+            return Ok(Vec::new());
+        };
+        let count = self.get_parsed::<usize>(count_tag)?;
+        let delimiter = *member_tags
+            .first()
+            .ok_or_else(|| FixError::Malformed(format!("group {count_tag} has no member tags")))?;
+
+        let mut instances: Vec<FixGroupInstance<'_>> = Vec::with_capacity(count);
+        for (tag, value) in self.fields[position + 1..].iter() {
+            if *tag == delimiter {
+                if instances.len() == count {
+                    break;
+                }
+                instances.push(Vec::new());
+            } else if !member_tags.contains(tag) || instances.is_empty() {
+                break;
+            }
+
+            if let Some(instance) = instances.last_mut() {
+                instance.push((*tag, value.as_slice()));
+            }
+        }
+
+        if instances.len() != count {
+            return Err(FixError::Malformed(format!(
+                "group {count_tag} declares {count} instances, found {}",
+                instances.len()
+            )));
+        }
+
+        Ok(instances)
+    }
+
+    // Deserializes the first occurrence of every tag, keyed by tag number. Repeating groups are
+    // only reachable through `FixMessage::group`.
+    pub fn deserialize<'a, Output>(&'a self) -> Result<Output, FixError>
+    where
+        Output: Deserialize<'a>,
+    {
+        Output::deserialize(MessageDeserializer { message: self })
+    }
+
+    pub fn encode(&self, begin_string: &str, buffer: &mut Vec<u8>) {
+        let mut body = Vec::with_capacity(self.fields.iter().map(|(_, value)| value.len() + 8).sum::<usize>() + 8);
+        push_field(&mut body, tag::MSG_TYPE, self.msg_type.as_bytes());
+        self.fields.iter().for_each(|(tag, value)| push_field(&mut body, *tag, value));
+
+        let start = buffer.len();
+        push_field(buffer, tag::BEGIN_STRING, begin_string.as_bytes());
+        push_field(buffer, tag::BODY_LENGTH, body.len().to_string().as_bytes());
+        buffer.extend_from_slice(&body);
+
+        let checksum = checksum(&buffer[start..]);
+        push_field(buffer, tag::CHECKSUM, format!("{checksum:03}").as_bytes());
+    }
+}
+
+impl std::fmt::Debug for FixMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FixMessage(35={}", self.msg_type)?;
+        for (tag, value) in &self.fields {
+            write!(f, "|{tag}={}", String::from_utf8_lossy(value))?;
+        }
+        write!(f, ")")
+    }
+}
+
+pub fn format_sending_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%d-%H:%M:%S%.3f").to_string()
+}
+
+pub fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn push_field(buffer: &mut Vec<u8>, tag: u32, value: &[u8]) {
+    buffer.extend_from_slice(tag.to_string().as_bytes());
+    buffer.push(b'=');
+    buffer.extend_from_slice(value);
+    buffer.push(SOH);
+}
+
+// Length prefixed data fields may legally contain SOH, so their value is read by length.
+fn data_tag_for_length(tag: u32) -> Option<u32> {
+    match tag {
+        90 => Some(91),
+        93 => Some(89),
+        95 => Some(96),
+        212 => Some(213),
+        348 => Some(349),
+        350 => Some(351),
+        352 => Some(353),
+        354 => Some(355),
+        356 => Some(357),
+        360 => Some(361),
+        362 => Some(363),
+        364 => Some(365),
+        445 => Some(446),
+        618 => Some(619),
+        621 => Some(622),
+        _ => None,
+    }
+}
+
+// BodyLength is untrusted, frames declaring more than `body_len_max` are discarded rather than
+// buffered until they complete.
+#[derive(Debug)]
+pub struct FixDecoder {
+    buffer: Vec<u8>,
+    body_len_max: usize,
+}
+
+impl Default for FixDecoder {
+    fn default() -> Self {
+        Self::new(BODY_LEN_MAX_DEFAULT)
+    }
+}
+
+impl FixDecoder {
+    pub fn new(body_len_max: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            body_len_max,
+        }
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    // Returns `None` until a complete frame is buffered. A malformed frame is dropped & reported,
+    // decoding resumes at the next BeginString.
+    pub fn decode(&mut self) -> Option<Result<FixMessage, FixError>> {
+        let start = self.buffer.windows(BEGIN_STRING_PREFIX.len()).position(|window| window == BEGIN_STRING_PREFIX);
+        match start {
+            Some(0) => {},
+            Some(start) => {
+                warn!(discarded = start, "Discarding bytes preceding FIX BeginString.");
+                self.buffer.drain(..start);
+            },
+            None => {
+                // Keep a possible partial prefix, anything before it can never start a frame
+                let keep = self.buffer.len().min(BEGIN_STRING_PREFIX.len() - 1);
+                self.buffer.drain(..self.buffer.len() - keep);
+                return None;
+            },
+        }
+
+        let begin_end = self.buffer.iter().position(|byte| *byte == SOH)?;
+        let length_start = begin_end + 1;
+        if self.buffer.len() < length_start + 2 {
+            return None;
+        }
+        if &self.buffer[length_start..length_start + 2] != b"9=" {
+            return Some(Err(self.discard_frame("BodyLength must be the second field")));
+        }
+
+        let length_end = length_start + self.buffer[length_start..].iter().position(|byte| *byte == SOH)?;
+        let Some(body_len) = std::str::from_utf8(&self.buffer[length_start + 2..length_end])
+            .ok()
+            .and_then(|length| length.parse::<usize>().ok())
+        else {
+            return Some(Err(self.discard_frame("BodyLength is not a number")));
+        };
+
+        let body_start = length_end + 1;
+        let frame = (body_len <= self.body_len_max)
+            .then(|| body_start.checked_add(body_len))
+            .flatten()
+            .and_then(|body_end| Some((body_end, body_end.checked_add(TRAILER_LEN)?)));
+        let Some((body_end, frame_end)) = frame else {
+            self.skip_frame();
+            return Some(Err(FixError::BodyTooLong {
+                length: body_len,
+                max: self.body_len_max,
+            }));
+        };
+        if self.buffer.len() < frame_end {
+            return None;
+        }
+
+        let trailer = &self.buffer[body_end..frame_end];
+        let expected = match (trailer.starts_with(b"10="), trailer[TRAILER_LEN - 1] == SOH) {
+            (true, true) => std::str::from_utf8(&trailer[3..6]).ok().and_then(|value| value.parse::<u8>().ok()),
+            _ => None,
+        };
+        let Some(expected) = expected else {
+            return Some(Err(self.discard_frame("CheckSum must follow BodyLength bytes")));
+        };
+
+        let computed = checksum(&self.buffer[..body_end]);
+        let frame = self.buffer.drain(..frame_end).collect::<Vec<_>>();
+        if computed != expected {
+            return Some(Err(FixError::Checksum { expected, computed }));
+        }
+
+        Some(parse_body(&frame[body_start..body_end]))
+    }
+
+    fn discard_frame(&mut self, reason: &str) -> FixError {
+        self.skip_frame();
+        FixError::Malformed(reason.to_owned())
+    }
+
+    // Skip past the current BeginString so the next decode searches for a fresh frame
+    fn skip_frame(&mut self) {
+        self.buffer.drain(..BEGIN_STRING_PREFIX.len());
+    }
+}
+
+fn parse_body(bytes: &[u8]) -> Result<FixMessage, FixError> {
+    let malformed = |reason: &str| FixError::Malformed(format!("{reason}: {}", String::from_utf8_lossy(bytes)));
+
+    let mut fields = Vec::new();
+    let mut data_length = None;
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let separator = position
+            + bytes[position..]
+                .iter()
+                .position(|byte| *byte == b'=')
+                .ok_or_else(|| malformed("field without '='"))?;
+        let tag = std::str::from_utf8(&bytes[position..separator])
+            .ok()
+            .and_then(|tag| tag.parse::<u32>().ok())
+            .ok_or_else(|| malformed("non numeric tag"))?;
+
+        let value_start = separator + 1;
+        let value_end = match data_length.take() {
+            Some((data_tag, length)) if data_tag == tag => value_start + length,
+            _ => {
+                value_start
+                    + bytes[value_start..]
+                        .iter()
+                        .position(|byte| *byte == SOH)
+                        .ok_or_else(|| malformed("unterminated field"))?
+            },
+        };
+        if bytes.get(value_end) != Some(&SOH) {
+            return Err(malformed("data field length mismatch"));
+        }
+
+        let value = bytes[value_start..value_end].to_vec();
+        if let Some(data_tag) = data_tag_for_length(tag) {
+            let length = std::str::from_utf8(&value).ok().and_then(|length| length.parse::<usize>().ok());
+            data_length = Some((data_tag, length.ok_or_else(|| malformed("non numeric data length"))?));
+        }
+
+        fields.push((tag, value));
+        position = value_end + 1;
+    }
+
+    let mut fields = fields.into_iter();
+    let msg_type = match fields.next() {
+        Some((tag::MSG_TYPE, value)) => String::from_utf8(value).map_err(|_| malformed("non UTF-8 MsgType"))?,
+        _ => return Err(malformed("MsgType must be the third field")),
+    };
+
+    Ok(FixMessage {
+        msg_type: SmolStr::new(msg_type),
+        fields: fields.collect(),
+    })
+}
+
+struct MessageDeserializer<'a> {
+    message: &'a FixMessage,
+}
+
+impl<'de> Deserializer<'de> for MessageDeserializer<'de> {
+    type Error = FixError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let mut seen = FnvHashSet::default();
+        let fields = std::iter::once((tag::MSG_TYPE, self.message.msg_type.as_bytes()))
+            .chain(self.message.fields())
+            .filter(|(tag, _)| seen.insert(*tag))
+            .collect::<Vec<_>>();
+
+        visitor.visit_map(FieldAccess {
+            fields: fields.into_iter(),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct FieldAccess<'de> {
+    fields: std::vec::IntoIter<(u32, &'de [u8])>,
+    value: Option<&'de [u8]>,
+}
+
+impl<'de> MapAccess<'de> for FieldAccess<'de> {
+    type Error = FixError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some((tag, value)) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+
+        seed.deserialize(tag.to_string().into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self.value.take().ok_or(FixError::Deserialise("value requested before key".to_owned()))?;
+
+        seed.deserialize(ValueDeserializer(value))
+    }
+}
+
+struct ValueDeserializer<'de>(&'de [u8]);
+
+impl<'de> ValueDeserializer<'de> {
+    fn as_str(&self) -> Result<&'de str, FixError> {
+        std::str::from_utf8(self.0).map_err(|_| FixError::Deserialise(format!("non UTF-8 value: {}", String::from_utf8_lossy(self.0))))
+    }
+
+    fn parse<T>(&self) -> Result<T, FixError>
+    where
+        T: FromStr,
+    {
+        let value = self.as_str()?;
+        value
+            .parse()
+            .map_err(|_| FixError::Deserialise(format!("invalid {} value: {value}", std::any::type_name::<T>())))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.$visit(self.parse::<$ty>()?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = FixError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match std::str::from_utf8(self.0) {
+            Ok(value) => visitor.visit_borrowed_str(value),
+            Err(_) => visitor.visit_borrowed_bytes(self.0),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            b"Y" => visitor.visit_bool(true),
+            b"N" => visitor.visit_bool(false),
+            other => Err(FixError::Deserialise(format!("invalid boolean value: {}", String::from_utf8_lossy(other)))),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char,
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.0)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.0)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Enumerated fields map onto unit variants, eg/ `#[serde(rename = "2")] Filled`
+    fn deserialize_enum<V>(self, _: &'static str, _: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let variant: BorrowedStrDeserializer<'de, FixError> = BorrowedStrDeserializer::new(self.as_str()?);
+        visitor.visit_enum(variant)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 str string unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::fix::BEGIN_STRING_FIX44;
+
+    fn order(cl_ord_id: &str) -> FixMessage {
+        FixMessage::new("D").with(tag::MSG_SEQ_NUM, 2).with(11, cl_ord_id).with(38, 5)
+    }
+
+    fn encoded(message: &FixMessage) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        message.encode(BEGIN_STRING_FIX44, &mut buffer);
+        buffer
+    }
+
+    fn decoder_with(bytes: &[u8]) -> FixDecoder {
+        let mut decoder = FixDecoder::default();
+        decoder.extend(bytes);
+        decoder
+    }
+
+    // Frame with a hand written BodyLength and a checksum that matches whatever precedes it
+    fn framed(body_length: &str, body: &[u8]) -> Vec<u8> {
+        let mut frame = format!("8=FIX.4.4\x019={body_length}\x01").into_bytes();
+        frame.extend_from_slice(body);
+        let checksum = checksum(&frame);
+        frame.extend_from_slice(format!("10={checksum:03}\x01").as_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let message = order("a").with(95, 3).with(96, "x\x01y");
+        let mut decoder = decoder_with(&encoded(&message));
+
+        assert_eq!(decoder.decode().unwrap().unwrap(), message);
+        assert!(decoder.decode().is_none());
+    }
+
+    #[test]
+    fn test_decode_split_frames() {
+        let mut bytes = encoded(&order("a"));
+        bytes.extend(encoded(&order("b")));
+
+        let mut decoder = FixDecoder::default();
+        let mut decoded = Vec::new();
+        for byte in bytes {
+            decoder.extend(&[byte]);
+            while let Some(message) = decoder.decode() {
+                decoded.push(message.unwrap());
+            }
+        }
+
+        assert_eq!(decoded, vec![order("a"), order("b")]);
+    }
+
+    #[test]
+    fn test_decode_skips_garbage() {
+        let mut decoder = decoder_with(b"\x01garbage8=FI");
+        assert!(decoder.decode().is_none());
+        // Only a possible partial BeginString is kept
+        assert_eq!(decoder.buffer, b"8=FI");
+
+        let mut bytes = b"noise".to_vec();
+        bytes.extend(encoded(&order("a")));
+        let mut decoder = decoder_with(&bytes);
+        assert_eq!(decoder.decode().unwrap().unwrap(), order("a"));
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_header() {
+        let mut bytes = b"8=FIX.4.4\x0135=D\x01".to_vec();
+        bytes.extend(framed("x", b"35=0\x01"));
+        bytes.extend(encoded(&order("a")));
+        let mut decoder = decoder_with(&bytes);
+
+        assert!(matches!(decoder.decode(), Some(Err(FixError::Malformed(reason))) if reason.contains("second field")));
+        assert!(matches!(decoder.decode(), Some(Err(FixError::Malformed(reason))) if reason.contains("not a number")));
+        assert_eq!(decoder.decode().unwrap().unwrap(), order("a"));
+    }
+
+    #[test]
+    fn test_decode_rejects_wrong_checksum() {
+        let mut bytes = encoded(&order("a"));
+        let checksum_start = bytes.len() - 4;
+        let wrong = if &bytes[checksum_start..checksum_start + 3] == b"000" {
+            b"001"
+        } else {
+            b"000"
+        };
+        bytes[checksum_start..checksum_start + 3].copy_from_slice(wrong);
+        bytes.extend(encoded(&order("b")));
+        let mut decoder = decoder_with(&bytes);
+
+        assert!(matches!(decoder.decode(), Some(Err(FixError::Checksum { .. }))));
+        assert_eq!(decoder.decode().unwrap().unwrap(), order("b"));
+    }
+
+    #[test]
+    fn test_decode_rejects_oversized_body_length() {
+        for body_length in ["18446744073709551615", "18446744073709551600", "999999999999"] {
+            let mut bytes = framed(body_length, b"35=0\x01");
+            bytes.extend(encoded(&order("a")));
+            let mut decoder = decoder_with(&bytes);
+
+            assert!(
+                matches!(decoder.decode(), Some(Err(FixError::BodyTooLong { length, .. })) if length.to_string() == body_length),
+                "{body_length}"
+            );
+            assert_eq!(decoder.decode().unwrap().unwrap(), order("a"));
+        }
+    }
+
+    #[test]
+    fn test_decode_body_len_max_is_configurable() {
+        let bytes = encoded(&order("a"));
+        let mut decoder = FixDecoder::new(8);
+        decoder.extend(&bytes);
+
+        assert!(matches!(decoder.decode(), Some(Err(FixError::BodyTooLong { max: 8, .. }))));
+        assert!(decoder.decode().is_none());
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt, channel::mpsc};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        TcpStream,
+        ToSocketAddrs,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    time::Instant,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::protocol::fix::{
+    codec::{FixDecoder, FixMessage},
+    error::FixError,
+    session::{FixSession, FixSessionEvent, FixSessionState, SequenceStore},
+};
+
+const READ_BUFFER_LEN: usize = 8 * 1024;
+const TIMER_RESOLUTION_MAX: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+enum FixCommand {
+    Send(FixMessage),
+    Logout(Option<String>),
+}
+
+// Initiator side FIX connection over TCP. Session upkeep (heartbeats, test requests, resends) runs in
+// a background task, the `Stream` impl yields in sequence application messages until Logout.
+#[derive(Debug)]
+pub struct FixConnection {
+    commands: mpsc::UnboundedSender<FixCommand>,
+    messages: mpsc::UnboundedReceiver<Result<FixMessage, FixError>>,
+}
+
+impl FixConnection {
+    // Resolves once the counterparty has answered the Logon.
+    pub async fn connect<Addr, Store>(addr: Addr, session: FixSession<Store>) -> Result<Self, FixError>
+    where
+        Addr: ToSocketAddrs,
+        Store: SequenceStore + Send + 'static,
+    {
+        let tcp = TcpStream::connect(addr).await?;
+        tcp.set_nodelay(true)?;
+        let (reader, writer) = tcp.into_split();
+
+        let (messages_tx, messages) = mpsc::unbounded();
+        let mut driver = FixDriver {
+            decoder: FixDecoder::new(session.config().body_len_max),
+            session,
+            reader,
+            writer,
+            read_buffer: vec![0; READ_BUFFER_LEN],
+            write_buffer: Vec::new(),
+            messages: messages_tx,
+        };
+
+        let timeout = driver.session.config().heartbeat_interval;
+        let logon = match tokio::time::timeout(timeout, driver.logon()).await {
+            Ok(logon) => logon,
+            Err(_) => Err(FixError::Timeout("Logon", timeout)),
+        };
+        if let Err(error) = logon {
+            driver.disconnected();
+            return Err(error);
+        }
+
+        let (commands, commands_rx) = mpsc::unbounded();
+        tokio::spawn(driver.run(commands_rx));
+
+        Ok(Self { commands, messages })
+    }
+
+    pub fn send(&self, message: FixMessage) -> Result<(), FixError> {
+        self.commands.unbounded_send(FixCommand::Send(message)).map_err(|_| FixError::ConnectionClosed)
+    }
+
+    pub fn logout(&self, text: Option<String>) -> Result<(), FixError> {
+        self.commands.unbounded_send(FixCommand::Logout(text)).map_err(|_| FixError::ConnectionClosed)
+    }
+}
+
+impl Stream for FixConnection {
+    type Item = Result<FixMessage, FixError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.poll_next_unpin(cx)
+    }
+}
+
+#[derive(Debug)]
+enum Flow {
+    Continue,
+    LoggedOut(Option<String>),
+}
+
+#[derive(Debug)]
+struct FixDriver<Store> {
+    session: FixSession<Store>,
+    reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
+    decoder: FixDecoder,
+    read_buffer: Vec<u8>,
+    write_buffer: Vec<u8>,
+    messages: mpsc::UnboundedSender<Result<FixMessage, FixError>>,
+}
+
+impl<Store> FixDriver<Store>
+where
+    Store: SequenceStore,
+{
+    async fn logon(&mut self) -> Result<(), FixError> {
+        let logon = self.session.logon(Instant::now())?;
+        self.write(&logon).await?;
+
+        loop {
+            while let Some(message) = self.decode() {
+                let events = self.session.on_message(message, Instant::now())?;
+                if let Flow::LoggedOut(text) = self.handle(events).await? {
+                    return Err(FixError::LogonRejected(text.unwrap_or_default()));
+                }
+
+                // Frames following the Logon response stay buffered for `run`
+                if self.session.state() == FixSessionState::Active {
+                    return Ok(());
+                }
+            }
+
+            self.read().await?;
+        }
+    }
+
+    async fn run(mut self, commands: mpsc::UnboundedReceiver<FixCommand>) {
+        match self.run_session(commands).await {
+            Ok(()) => info!("FIX connection closed after Logout."),
+            Err(error) => {
+                error!(%error, "FIX connection terminated.");
+                let _ = self.messages.unbounded_send(Err(error));
+            },
+        }
+
+        self.disconnected();
+    }
+
+    // The sequence numbers used so far are persisted, so the next Logon continues from them
+    fn disconnected(&mut self) {
+        self.session.disconnected();
+        if let Err(error) = self.session.flush() {
+            error!(%error, "Failed to persist FIX sequence numbers.");
+        }
+    }
+
+    async fn run_session(&mut self, mut commands: mpsc::UnboundedReceiver<FixCommand>) -> Result<(), FixError> {
+        let resolution = (self.session.config().heartbeat_interval / 2).min(TIMER_RESOLUTION_MAX);
+        let mut timer = tokio::time::interval(resolution);
+        let mut is_commands_open = true;
+
+        loop {
+            while let Some(message) = self.decode() {
+                let events = self.session.on_message(message, Instant::now())?;
+                if let Flow::LoggedOut(_) = self.handle(events).await? {
+                    return Ok(());
+                }
+            }
+
+            tokio::select! {
+                read = self.reader.read(&mut self.read_buffer) => match read? {
+                    0 => return Err(FixError::ConnectionClosed),
+                    read => self.decoder.extend(&self.read_buffer[..read]),
+                },
+                _ = timer.tick() => {
+                    let events = self.session.on_timer(Instant::now())?;
+                    if let Flow::LoggedOut(_) = self.handle(events).await? {
+                        return Ok(());
+                    }
+                },
+                command = commands.next(), if is_commands_open => {
+                    let now = Instant::now();
+                    let message = match command {
+                        Some(FixCommand::Send(message)) => match self.session.send(message, now) {
+                            Ok(message) => message,
+                            Err(error) => {
+                                warn!(%error, "Failed to send FIX application message.");
+                                let _ = self.messages.unbounded_send(Err(error));
+                                continue;
+                            },
+                        },
+                        Some(FixCommand::Logout(text)) => self.session.logout(text.as_deref(), now)?,
+                        // Every `FixConnection` handle is gone, so the session is wound down politely
+                        None => {
+                            is_commands_open = false;
+                            self.session.logout(None, now)?
+                        },
+                    };
+
+                    self.write(&message).await?;
+                },
+            }
+        }
+    }
+
+    async fn handle(&mut self, events: Vec<FixSessionEvent>) -> Result<Flow, FixError> {
+        let mut flow = Flow::Continue;
+
+        for event in events {
+            match event {
+                FixSessionEvent::Send(message) => self.write(&message).await?,
+                FixSessionEvent::Received(message) => {
+                    let _ = self.messages.unbounded_send(Ok(message));
+                },
+                FixSessionEvent::LoggedOn => {},
+                FixSessionEvent::LoggedOut(text) => flow = Flow::LoggedOut(text),
+                FixSessionEvent::Disconnect(error) => return Err(error),
+            }
+        }
+
+        Ok(flow)
+    }
+
+    fn decode(&mut self) -> Option<FixMessage> {
+        // Garbled messages are ignored as per the FIX session spec, a resend recovers their content
+        while let Some(decoded) = self.decoder.decode() {
+            match decoded {
+                Ok(message) => {
+                    debug!(?message, "Received FIX message.");
+                    return Some(message);
+                },
+                Err(error) => warn!(%error, "Dropping garbled FIX message."),
+            }
+        }
+
+        None
+    }
+
+    async fn read(&mut self) -> Result<(), FixError> {
+        match self.reader.read(&mut self.read_buffer).await? {
+            0 => Err(FixError::ConnectionClosed),
+            read => {
+                self.decoder.extend(&self.read_buffer[..read]);
+                Ok(())
+            },
+        }
+    }
+
+    async fn write(&mut self, message: &FixMessage) -> Result<(), FixError> {
+        debug!(?message, "Sending FIX message.");
+
+        self.write_buffer.clear();
+        self.session.encode(message, &mut self.write_buffer);
+        self.writer.write_all(&self.write_buffer).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FixError {
+    #[error("FIX I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("FIX connection closed")]
+    ConnectionClosed,
+
+    #[error("FIX message malformed: {0}")]
+    Malformed(String),
+
+    #[error("FIX BodyLength {length} exceeds the maximum of {max}")]
+    BodyTooLong { length: usize, max: usize },
+
+    #[error("FIX checksum mismatch: expected {expected}, computed {computed}")]
+    Checksum { expected: u8, computed: u8 },
+
+    #[error("FIX message missing required tag {0}")]
+    MissingField(u32),
+
+    #[error("FIX tag {tag} has invalid value: {value}")]
+    InvalidField { tag: u32, value: String },
+
+    #[error("FIX deserialise error: {0}")]
+    Deserialise(String),
+
+    #[error("FIX session is not logged on")]
+    NotLoggedOn,
+
+    #[error("FIX MsgSeqNum too low: expected {expected}, received {received}")]
+    SequenceTooLow { expected: u64, received: u64 },
+
+    #[error("FIX Logon rejected: {0}")]
+    LogonRejected(String),
+
+    #[error("FIX counterparty sent {0} before Logon")]
+    LogonRequired(String),
+
+    #[error("FIX counterparty silent for {0:?}")]
+    HeartbeatTimeout(Duration),
+
+    #[error("FIX {0} was not answered within {1:?}")]
+    Timeout(&'static str, Duration),
+}
+
+impl serde::de::Error for FixError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Self::Deserialise(msg.to_string())
+    }
+}
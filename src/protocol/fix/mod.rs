@@ -0,0 +1,78 @@
+use serde::de::DeserializeOwned;
+
+use crate::protocol::parser::{FixParser, ProtocolParser};
+
+pub mod codec;
+pub mod connection;
+pub mod error;
+pub mod session;
+
+pub use codec::{FixDecoder, FixMessage};
+pub use connection::FixConnection;
+pub use error::FixError;
+pub use session::{FileSequenceStore, FixSession, FixSessionConfig, FixSessionEvent, FixSessionState, MemorySequenceStore, SequenceNumbers, SequenceStore};
+
+pub const BEGIN_STRING_FIX44: &str = "FIX.4.4";
+
+pub mod tag {
+    pub const BEGIN_SEQ_NO: u32 = 7;
+    pub const BEGIN_STRING: u32 = 8;
+    pub const BODY_LENGTH: u32 = 9;
+    pub const CHECKSUM: u32 = 10;
+    pub const END_SEQ_NO: u32 = 16;
+    pub const MSG_SEQ_NUM: u32 = 34;
+    pub const MSG_TYPE: u32 = 35;
+    pub const NEW_SEQ_NO: u32 = 36;
+    pub const POSS_DUP_FLAG: u32 = 43;
+    pub const REF_SEQ_NUM: u32 = 45;
+    pub const SENDER_COMP_ID: u32 = 49;
+    pub const SENDING_TIME: u32 = 52;
+    pub const TARGET_COMP_ID: u32 = 56;
+    pub const TEXT: u32 = 58;
+    pub const ENCRYPT_METHOD: u32 = 98;
+    pub const HEART_BT_INT: u32 = 108;
+    pub const TEST_REQ_ID: u32 = 112;
+    pub const ORIG_SENDING_TIME: u32 = 122;
+    pub const GAP_FILL_FLAG: u32 = 123;
+    pub const RESET_SEQ_NUM_FLAG: u32 = 141;
+    pub const USERNAME: u32 = 553;
+    pub const PASSWORD: u32 = 554;
+}
+
+pub mod msg_type {
+    pub const HEARTBEAT: &str = "0";
+    pub const TEST_REQUEST: &str = "1";
+    pub const RESEND_REQUEST: &str = "2";
+    pub const REJECT: &str = "3";
+    pub const SEQUENCE_RESET: &str = "4";
+    pub const LOGOUT: &str = "5";
+    pub const LOGON: &str = "A";
+
+    pub fn is_admin(msg_type: &str) -> bool {
+        matches!(msg_type, HEARTBEAT | TEST_REQUEST | RESEND_REQUEST | REJECT | SEQUENCE_RESET | LOGOUT | LOGON)
+    }
+}
+
+// Deserializes application messages by tag number, eg/ `#[serde(rename = "37")] order_id: SmolStr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FixTagValueParser;
+
+impl ProtocolParser for FixTagValueParser {}
+
+impl FixParser for FixTagValueParser {
+    type Message = FixMessage;
+    type Error = FixError;
+
+    fn parse<Output>(input: Result<Self::Message, Self::Error>) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: DeserializeOwned,
+    {
+        let output = match input {
+            Ok(message) if msg_type::is_admin(message.msg_type()) && message.msg_type() != msg_type::REJECT => return None,
+            Ok(message) => message.deserialize::<Output>(),
+            Err(error) => Err(error),
+        };
+
+        Some(output.map_err(Into::into))
+    }
+}
@@ -0,0 +1,751 @@
+use std::{collections::VecDeque, path::PathBuf, time::Duration};
+
+use chrono::Utc;
+use smol_str::SmolStr;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::protocol::fix::{
+    BEGIN_STRING_FIX44,
+    codec::{BODY_LEN_MAX_DEFAULT, FixMessage, format_sending_time},
+    error::FixError,
+    msg_type,
+    tag,
+};
+
+const HEARTBEAT_INTERVAL_DEFAULT: Duration = Duration::from_secs(30);
+const RESEND_BUFFER_LEN_DEFAULT: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct FixSessionConfig {
+    pub begin_string: SmolStr,
+    pub sender_comp_id: SmolStr,
+    pub target_comp_id: SmolStr,
+    pub heartbeat_interval: Duration,
+    pub reset_seq_num_on_logon: bool,
+    pub username: Option<SmolStr>,
+    pub password: Option<SmolStr>,
+    // Application messages kept in memory to answer ResendRequests, older ones are gap filled.
+    pub resend_buffer_len: usize,
+    // Largest BodyLength accepted from the counterparty, larger frames are discarded.
+    pub body_len_max: usize,
+}
+
+impl FixSessionConfig {
+    pub fn new(sender_comp_id: impl Into<SmolStr>, target_comp_id: impl Into<SmolStr>) -> Self {
+        Self {
+            begin_string: SmolStr::new_static(BEGIN_STRING_FIX44),
+            sender_comp_id: sender_comp_id.into(),
+            target_comp_id: target_comp_id.into(),
+            heartbeat_interval: HEARTBEAT_INTERVAL_DEFAULT,
+            reset_seq_num_on_logon: false,
+            username: None,
+            password: None,
+            resend_buffer_len: RESEND_BUFFER_LEN_DEFAULT,
+            body_len_max: BODY_LEN_MAX_DEFAULT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceNumbers {
+    pub next_outgoing: u64,
+    pub next_incoming: u64,
+}
+
+impl Default for SequenceNumbers {
+    fn default() -> Self {
+        Self {
+            next_outgoing: 1,
+            next_incoming: 1,
+        }
+    }
+}
+
+pub trait SequenceStore {
+    fn load(&mut self) -> Result<SequenceNumbers, FixError>;
+
+    fn store(&mut self, sequence: SequenceNumbers) -> Result<(), FixError>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemorySequenceStore(pub SequenceNumbers);
+
+impl SequenceStore for MemorySequenceStore {
+    fn load(&mut self) -> Result<SequenceNumbers, FixError> {
+        Ok(self.0)
+    }
+
+    fn store(&mut self, sequence: SequenceNumbers) -> Result<(), FixError> {
+        self.0 = sequence;
+        Ok(())
+    }
+}
+
+// Persists `next_outgoing next_incoming` as text, so a restarted process resumes the session
+// instead of resetting it.
+#[derive(Debug, Clone)]
+pub struct FileSequenceStore {
+    path: PathBuf,
+}
+
+impl FileSequenceStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl SequenceStore for FileSequenceStore {
+    fn load(&mut self) -> Result<SequenceNumbers, FixError> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(SequenceNumbers::default()),
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut numbers = contents.split_whitespace().map(str::parse::<u64>);
+        match (numbers.next(), numbers.next()) {
+            (Some(Ok(next_outgoing)), Some(Ok(next_incoming))) => Ok(SequenceNumbers { next_outgoing, next_incoming }),
+            _ => Err(FixError::Malformed(format!("sequence store {} is corrupt: {contents:?}", self.path.display()))),
+        }
+    }
+
+    fn store(&mut self, sequence: SequenceNumbers) -> Result<(), FixError> {
+        // Write & rename, so a crash mid-write never leaves a truncated store behind
+        let staging = self.path.with_extension("tmp");
+        std::fs::write(&staging, format!("{} {}\n", sequence.next_outgoing, sequence.next_incoming))?;
+        std::fs::rename(&staging, &self.path)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixSessionState {
+    Disconnected,
+    LogonSent,
+    Active,
+    LogoutSent,
+}
+
+#[derive(Debug)]
+pub enum FixSessionEvent {
+    // Stamped message to be written to the transport.
+    Send(FixMessage),
+    // In sequence application message (or session Reject) for the application.
+    Received(FixMessage),
+    LoggedOn,
+    LoggedOut(Option<String>),
+    Disconnect(FixError),
+}
+
+#[derive(Debug)]
+struct SentMessage {
+    sequence: u64,
+    sending_time: String,
+    message: FixMessage,
+}
+
+// Transport agnostic FIX session state machine. The owner feeds it decoded messages & timer ticks,
+// and writes out every `FixSessionEvent::Send` in order.
+#[derive(Debug)]
+pub struct FixSession<Store> {
+    config: FixSessionConfig,
+    store: Store,
+    sequence: SequenceNumbers,
+    state: FixSessionState,
+    last_sent: Instant,
+    last_received: Instant,
+    test_request_sent: Option<Instant>,
+    test_request_id: u64,
+    resend_until: Option<u64>,
+    sent: VecDeque<SentMessage>,
+    // Sequence numbers changed since the last `flush`, persisted on the next timer tick
+    is_dirty: bool,
+}
+
+impl<Store> FixSession<Store>
+where
+    Store: SequenceStore,
+{
+    pub fn new(config: FixSessionConfig, mut store: Store) -> Result<Self, FixError> {
+        let sequence = store.load()?;
+        let now = Instant::now();
+
+        Ok(Self {
+            config,
+            store,
+            sequence,
+            state: FixSessionState::Disconnected,
+            last_sent: now,
+            last_received: now,
+            test_request_sent: None,
+            test_request_id: 0,
+            resend_until: None,
+            sent: VecDeque::new(),
+            is_dirty: false,
+        })
+    }
+
+    pub fn config(&self) -> &FixSessionConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> FixSessionState {
+        self.state
+    }
+
+    pub fn sequence(&self) -> SequenceNumbers {
+        self.sequence
+    }
+
+    pub fn encode(&self, message: &FixMessage, buffer: &mut Vec<u8>) {
+        message.encode(&self.config.begin_string, buffer)
+    }
+
+    pub fn logon(&mut self, now: Instant) -> Result<FixMessage, FixError> {
+        let mut logon = FixMessage::new(msg_type::LOGON)
+            .with(tag::ENCRYPT_METHOD, 0)
+            .with(tag::HEART_BT_INT, self.config.heartbeat_interval.as_secs());
+
+        if self.config.reset_seq_num_on_logon {
+            self.sequence = SequenceNumbers::default();
+            self.sent.clear();
+            self.is_dirty = true;
+            logon.push(tag::RESET_SEQ_NUM_FLAG, "Y");
+        }
+        if let Some(username) = &self.config.username {
+            logon.push(tag::USERNAME, username);
+        }
+        if let Some(password) = &self.config.password {
+            logon.push(tag::PASSWORD, password);
+        }
+
+        self.state = FixSessionState::LogonSent;
+        self.last_received = now;
+        self.test_request_sent = None;
+        self.resend_until = None;
+
+        Ok(self.stamp(logon, now))
+    }
+
+    pub fn logout(&mut self, text: Option<&str>, now: Instant) -> Result<FixMessage, FixError> {
+        let mut logout = FixMessage::new(msg_type::LOGOUT);
+        if let Some(text) = text {
+            logout.push(tag::TEXT, text);
+        }
+
+        self.state = FixSessionState::LogoutSent;
+        Ok(self.stamp(logout, now))
+    }
+
+    pub fn send(&mut self, message: FixMessage, now: Instant) -> Result<FixMessage, FixError> {
+        if self.state != FixSessionState::Active {
+            return Err(FixError::NotLoggedOn);
+        }
+
+        Ok(self.stamp(message, now))
+    }
+
+    // Called by the transport once the connection is gone, sequence numbers are kept for the next Logon.
+    pub fn disconnected(&mut self) {
+        self.state = FixSessionState::Disconnected;
+        self.test_request_sent = None;
+        self.resend_until = None;
+    }
+
+    pub fn on_message(&mut self, message: FixMessage, now: Instant) -> Result<Vec<FixSessionEvent>, FixError> {
+        self.last_received = now;
+        self.test_request_sent = None;
+
+        let received = message.get_parsed::<u64>(tag::MSG_SEQ_NUM)?;
+        let expected = self.sequence.next_incoming;
+        let is_gap_fill = message.is_flag_set(tag::GAP_FILL_FLAG);
+
+        if self.state == FixSessionState::LogonSent && !matches!(message.msg_type(), msg_type::LOGON | msg_type::LOGOUT) {
+            return Ok(vec![FixSessionEvent::Disconnect(FixError::LogonRequired(message.msg_type().to_owned()))]);
+        }
+
+        // SequenceReset-Reset ignores MsgSeqNum and moves the expected sequence forward unconditionally
+        if message.msg_type() == msg_type::SEQUENCE_RESET && !is_gap_fill {
+            let new_seq_no = message.get_parsed::<u64>(tag::NEW_SEQ_NO)?;
+            if new_seq_no < expected {
+                warn!(expected, new_seq_no, "Ignoring FIX SequenceReset that would decrease the incoming sequence.");
+            } else {
+                info!(expected, new_seq_no, "FIX incoming sequence reset by counterparty.");
+                self.advance_incoming(new_seq_no);
+            }
+            return Ok(Vec::new());
+        }
+
+        if received < expected {
+            if message.is_flag_set(tag::POSS_DUP_FLAG) {
+                debug!(expected, received, "Ignoring already processed FIX PossDup message.");
+                return Ok(Vec::new());
+            }
+
+            let error = FixError::SequenceTooLow { expected, received };
+            let logout = self.logout(Some(&error.to_string()), now)?;
+            return Ok(vec![FixSessionEvent::Send(logout), FixSessionEvent::Disconnect(error)]);
+        }
+
+        let mut events = Vec::new();
+
+        if received > expected {
+            let resend = self.resend_until.is_none().then(|| {
+                warn!(expected, received, "FIX sequence gap detected, requesting resend.");
+                FixMessage::new(msg_type::RESEND_REQUEST)
+                    .with(tag::BEGIN_SEQ_NO, expected)
+                    .with(tag::END_SEQ_NO, 0)
+            });
+            self.resend_until = Some(self.resend_until.map_or(received, |until| until.max(received)));
+
+            // ResendRequests are serviced straight away so that two sides with gaps don't wait on each
+            // other, and ahead of our own request so the replay stops short of it
+            if message.msg_type() == msg_type::RESEND_REQUEST {
+                self.process(message, now, &mut events)?;
+                if let Some(resend) = resend {
+                    events.push(FixSessionEvent::Send(self.stamp(resend, now)));
+                }
+                return Ok(events);
+            }
+            if let Some(resend) = resend {
+                events.push(FixSessionEvent::Send(self.stamp(resend, now)));
+            }
+
+            // Logon & Logout are honoured straight away, a GapFill is processed without moving the
+            // incoming sequence, anything else arrives again with the resend
+            if !matches!(message.msg_type(), msg_type::LOGON | msg_type::LOGOUT | msg_type::SEQUENCE_RESET) {
+                return Ok(events);
+            }
+        } else {
+            let next_incoming = match message.msg_type() {
+                msg_type::SEQUENCE_RESET => message.get_parsed::<u64>(tag::NEW_SEQ_NO)?,
+                _ => received + 1,
+            };
+            self.advance_incoming(next_incoming);
+
+            if self.resend_until.is_some_and(|until| self.sequence.next_incoming > until) {
+                info!(next_incoming = self.sequence.next_incoming, "FIX sequence gap filled.");
+                self.resend_until = None;
+            }
+        }
+
+        self.process(message, now, &mut events)?;
+
+        Ok(events)
+    }
+
+    // Persists the sequence numbers if they changed. Stores may block on I/O, so the session only
+    // flushes on `on_timer`, and owners flush once more when the connection is closed.
+    pub fn flush(&mut self) -> Result<(), FixError> {
+        if self.is_dirty {
+            self.store.store(self.sequence)?;
+            self.is_dirty = false;
+        }
+
+        Ok(())
+    }
+
+    pub fn on_timer(&mut self, now: Instant) -> Result<Vec<FixSessionEvent>, FixError> {
+        self.flush()?;
+
+        let heartbeat = self.config.heartbeat_interval;
+        let mut events = Vec::new();
+
+        match self.state {
+            FixSessionState::Disconnected => {},
+            FixSessionState::LogonSent if now.duration_since(self.last_sent) >= heartbeat => {
+                events.push(FixSessionEvent::Disconnect(FixError::Timeout("Logon", heartbeat)));
+            },
+            FixSessionState::LogoutSent if now.duration_since(self.last_sent) >= heartbeat => {
+                events.push(FixSessionEvent::Disconnect(FixError::Timeout("Logout", heartbeat)));
+            },
+            FixSessionState::LogonSent | FixSessionState::LogoutSent => {},
+            FixSessionState::Active => {
+                // Silence past the heartbeat plus some transmission slack is probed with a TestRequest,
+                // which in turn has one heartbeat interval to be answered
+                match self.test_request_sent {
+                    Some(sent_at) if now.duration_since(sent_at) >= heartbeat => {
+                        let silent_for = now.duration_since(self.last_received);
+                        events.push(FixSessionEvent::Disconnect(FixError::HeartbeatTimeout(silent_for)));
+                        return Ok(events);
+                    },
+                    Some(_) => {},
+                    None if now.duration_since(self.last_received) >= heartbeat + heartbeat / 5 => {
+                        self.test_request_id += 1;
+                        let test_request = FixMessage::new(msg_type::TEST_REQUEST).with(tag::TEST_REQ_ID, self.test_request_id);
+                        events.push(FixSessionEvent::Send(self.stamp(test_request, now)));
+                        self.test_request_sent = Some(now);
+                    },
+                    None => {},
+                }
+
+                if now.duration_since(self.last_sent) >= heartbeat {
+                    events.push(FixSessionEvent::Send(self.stamp(FixMessage::new(msg_type::HEARTBEAT), now)));
+                }
+            },
+        }
+
+        Ok(events)
+    }
+
+    fn process(&mut self, message: FixMessage, now: Instant, events: &mut Vec<FixSessionEvent>) -> Result<(), FixError> {
+        match message.msg_type() {
+            msg_type::LOGON if self.state == FixSessionState::LogonSent => {
+                info!(sequence = ?self.sequence, "FIX session logged on.");
+                self.state = FixSessionState::Active;
+                events.push(FixSessionEvent::LoggedOn);
+            },
+            msg_type::LOGON => warn!(state = ?self.state, "Ignoring unexpected FIX Logon."),
+            msg_type::HEARTBEAT | msg_type::SEQUENCE_RESET => {},
+            msg_type::TEST_REQUEST => {
+                let heartbeat = FixMessage::new(msg_type::HEARTBEAT).with(tag::TEST_REQ_ID, message.get_str(tag::TEST_REQ_ID)?);
+                events.push(FixSessionEvent::Send(self.stamp(heartbeat, now)));
+            },
+            msg_type::RESEND_REQUEST => self.resend(&message, now, events)?,
+            msg_type::LOGOUT => {
+                let text = message.get_str(tag::TEXT).ok().map(str::to_owned);
+                if self.state != FixSessionState::LogoutSent {
+                    let logout = self.logout(None, now)?;
+                    events.push(FixSessionEvent::Send(logout));
+                }
+
+                info!(?text, "FIX session logged out.");
+                self.state = FixSessionState::Disconnected;
+                events.push(FixSessionEvent::LoggedOut(text));
+            },
+            _ => events.push(FixSessionEvent::Received(message)),
+        }
+
+        Ok(())
+    }
+
+    fn resend(&mut self, request: &FixMessage, now: Instant, events: &mut Vec<FixSessionEvent>) -> Result<(), FixError> {
+        // Requests beyond what was actually sent, including EndSeqNo=0 meaning "infinity", are clamped
+        let last_sent = self.sequence.next_outgoing - 1;
+        let begin = request.get_parsed::<u64>(tag::BEGIN_SEQ_NO)?.max(1);
+        let end = match request.get_parsed::<u64>(tag::END_SEQ_NO)? {
+            0 => last_sent,
+            end => end.min(last_sent),
+        };
+        if begin > end {
+            warn!(begin, end, last_sent, "Ignoring FIX ResendRequest for messages never sent.");
+            return Ok(());
+        }
+        info!(begin, end, "FIX counterparty requested resend.");
+
+        // Application messages still buffered are replayed as PossDup, everything else is gap filled,
+        // so the work is bounded by the resend buffer rather than the requested range
+        let sending_time = format_sending_time(Utc::now());
+        let mut next = begin;
+
+        let first = self.sent.partition_point(|sent| sent.sequence < begin);
+        for sent in self.sent.range(first..).take_while(|sent| sent.sequence <= end) {
+            if sent.sequence > next {
+                events.push(FixSessionEvent::Send(self.gap_fill(next, sent.sequence, &sending_time)));
+            }
+
+            events.push(FixSessionEvent::Send(self.with_header(
+                &sent.message,
+                sent.sequence,
+                &sending_time,
+                Some(&sent.sending_time),
+            )));
+            next = sent.sequence + 1;
+        }
+
+        if next <= end {
+            events.push(FixSessionEvent::Send(self.gap_fill(next, end + 1, &sending_time)));
+        }
+
+        self.last_sent = now;
+
+        Ok(())
+    }
+
+    fn gap_fill(&self, sequence: u64, new_seq_no: u64, sending_time: &str) -> FixMessage {
+        let gap_fill = FixMessage::new(msg_type::SEQUENCE_RESET)
+            .with(tag::GAP_FILL_FLAG, "Y")
+            .with(tag::NEW_SEQ_NO, new_seq_no);
+
+        self.with_header(&gap_fill, sequence, sending_time, Some(sending_time))
+    }
+
+    fn stamp(&mut self, message: FixMessage, now: Instant) -> FixMessage {
+        let sequence = self.sequence.next_outgoing;
+        let sending_time = format_sending_time(Utc::now());
+        let stamped = self.with_header(&message, sequence, &sending_time, None);
+
+        self.sequence.next_outgoing += 1;
+        self.is_dirty = true;
+        self.last_sent = now;
+
+        if !msg_type::is_admin(message.msg_type()) {
+            if self.sent.len() >= self.config.resend_buffer_len {
+                self.sent.pop_front();
+            }
+            self.sent.push_back(SentMessage {
+                sequence,
+                sending_time,
+                message,
+            });
+        }
+
+        stamped
+    }
+
+    fn with_header(&self, message: &FixMessage, sequence: u64, sending_time: &str, orig_sending_time: Option<&str>) -> FixMessage {
+        let mut stamped = FixMessage::new(message.msg_type())
+            .with(tag::SENDER_COMP_ID, &self.config.sender_comp_id)
+            .with(tag::TARGET_COMP_ID, &self.config.target_comp_id)
+            .with(tag::MSG_SEQ_NUM, sequence)
+            .with(tag::SENDING_TIME, sending_time);
+
+        if let Some(orig_sending_time) = orig_sending_time {
+            stamped.push(tag::POSS_DUP_FLAG, "Y");
+            stamped.push(tag::ORIG_SENDING_TIME, orig_sending_time);
+        }
+
+        message.fields().for_each(|(tag, value)| stamped.push_bytes(tag, value));
+        stamped
+    }
+
+    fn advance_incoming(&mut self, next_incoming: u64) {
+        self.sequence.next_incoming = next_incoming;
+        self.is_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    // Records every store, so tests can tell when the session touched its store.
+    #[derive(Debug, Clone, Default)]
+    struct RecordingStore(Arc<Mutex<Vec<SequenceNumbers>>>);
+
+    impl SequenceStore for RecordingStore {
+        fn load(&mut self) -> Result<SequenceNumbers, FixError> {
+            Ok(SequenceNumbers::default())
+        }
+
+        fn store(&mut self, sequence: SequenceNumbers) -> Result<(), FixError> {
+            self.0.lock().push(sequence);
+            Ok(())
+        }
+    }
+
+    fn logged_on<Store>(store: Store, now: Instant) -> FixSession<Store>
+    where
+        Store: SequenceStore,
+    {
+        let mut session = FixSession::new(FixSessionConfig::new("CLIENT", "VENUE"), store).unwrap();
+        session.logon(now).unwrap();
+
+        let events = session.on_message(FixMessage::new(msg_type::LOGON).with(tag::MSG_SEQ_NUM, 1), now).unwrap();
+        assert!(matches!(events[..], [FixSessionEvent::LoggedOn]));
+        session
+    }
+
+    fn order(id: &str) -> FixMessage {
+        FixMessage::new("D").with(11, id)
+    }
+
+    fn resend_request(sequence: u64, begin: u64, end: u64) -> FixMessage {
+        FixMessage::new(msg_type::RESEND_REQUEST)
+            .with(tag::MSG_SEQ_NUM, sequence)
+            .with(tag::BEGIN_SEQ_NO, begin)
+            .with(tag::END_SEQ_NO, end)
+    }
+
+    // (MsgSeqNum, MsgType, NewSeqNo) of every message the session asked to send
+    fn sent(events: &[FixSessionEvent]) -> Vec<(u64, String, Option<u64>)> {
+        events
+            .iter()
+            .map(|event| match event {
+                FixSessionEvent::Send(message) => (
+                    message.get_parsed::<u64>(tag::MSG_SEQ_NUM).unwrap(),
+                    message.msg_type().to_owned(),
+                    message.get_parsed::<u64>(tag::NEW_SEQ_NO).ok(),
+                ),
+                event => panic!("expected only Send events, got {event:?}"),
+            })
+            .collect()
+    }
+
+    // Logon (1), orders (2, 3), Heartbeat (4), order (5)
+    fn with_history(now: Instant) -> FixSession<MemorySequenceStore> {
+        let mut session = logged_on(MemorySequenceStore::default(), now);
+        session.send(order("a"), now).unwrap();
+        session.send(order("b"), now).unwrap();
+        let heartbeat = session.on_timer(now + Duration::from_secs(30)).unwrap();
+        assert_eq!(sent(&heartbeat), vec![(4, msg_type::HEARTBEAT.to_owned(), None)]);
+        session.send(order("c"), now).unwrap();
+        session
+    }
+
+    #[test]
+    fn test_resend_replays_buffered_and_gap_fills_admin() {
+        let now = Instant::now();
+        let mut session = with_history(now);
+
+        let events = session.on_message(resend_request(2, 1, 0), now).unwrap();
+        assert_eq!(
+            sent(&events),
+            vec![
+                (1, msg_type::SEQUENCE_RESET.to_owned(), Some(2)),
+                (2, "D".to_owned(), None),
+                (3, "D".to_owned(), None),
+                (4, msg_type::SEQUENCE_RESET.to_owned(), Some(5)),
+                (5, "D".to_owned(), None),
+            ]
+        );
+
+        let FixSessionEvent::Send(replayed) = &events[1] else { unreachable!() };
+        assert!(replayed.is_flag_set(tag::POSS_DUP_FLAG));
+        assert_eq!(replayed.get_str(11).unwrap(), "a");
+
+        // Resends reuse the original sequence numbers
+        assert_eq!(session.sequence().next_outgoing, 6);
+    }
+
+    #[test]
+    fn test_resend_clamps_to_last_sent() {
+        let now = Instant::now();
+        let mut session = with_history(now);
+
+        let events = session.on_message(resend_request(2, 3, u64::MAX), now).unwrap();
+        assert_eq!(
+            sent(&events),
+            vec![
+                (3, "D".to_owned(), None),
+                (4, msg_type::SEQUENCE_RESET.to_owned(), Some(5)),
+                (5, "D".to_owned(), None),
+            ]
+        );
+
+        let events = session.on_message(resend_request(3, 2, 2), now).unwrap();
+        assert_eq!(sent(&events), vec![(2, "D".to_owned(), None)]);
+
+        // Nothing was ever sent from 6 onwards, and BeginSeqNo 0 is read as 1
+        assert!(session.on_message(resend_request(4, 6, 0), now).unwrap().is_empty());
+        assert_eq!(
+            sent(&session.on_message(resend_request(5, 0, 1), now).unwrap()),
+            vec![(1, msg_type::SEQUENCE_RESET.to_owned(), Some(2))]
+        );
+    }
+
+    #[test]
+    fn test_resend_request_serviced_across_mutual_gap() {
+        let now = Instant::now();
+        let mut session = with_history(now);
+
+        // The counterparty skipped 2 & 3 and asks for our 2 onwards in the same breath
+        let events = session.on_message(resend_request(4, 2, 0), now).unwrap();
+        assert_eq!(
+            sent(&events),
+            vec![
+                (2, "D".to_owned(), None),
+                (3, "D".to_owned(), None),
+                (4, msg_type::SEQUENCE_RESET.to_owned(), Some(5)),
+                (5, "D".to_owned(), None),
+                (6, msg_type::RESEND_REQUEST.to_owned(), None),
+            ]
+        );
+        let FixSessionEvent::Send(request) = &events[4] else { unreachable!() };
+        assert_eq!(request.get_parsed::<u64>(tag::BEGIN_SEQ_NO).unwrap(), 2);
+        assert_eq!(session.sequence().next_incoming, 2);
+
+        // A GapFill beyond the gap leaves the incoming sequence alone
+        let gap_fill = FixMessage::new(msg_type::SEQUENCE_RESET)
+            .with(tag::MSG_SEQ_NUM, 5)
+            .with(tag::GAP_FILL_FLAG, "Y")
+            .with(tag::NEW_SEQ_NO, 9);
+        assert!(session.on_message(gap_fill, now).unwrap().is_empty());
+        assert_eq!(session.sequence().next_incoming, 2);
+
+        // Its own resend closes the gap, with the serviced ResendRequest gap filled
+        for sequence in [2, 3] {
+            let replay = order("x").with(tag::MSG_SEQ_NUM, sequence).with(tag::POSS_DUP_FLAG, "Y");
+            assert!(matches!(session.on_message(replay, now).unwrap()[..], [FixSessionEvent::Received(_)]));
+        }
+        let gap_fill = FixMessage::new(msg_type::SEQUENCE_RESET)
+            .with(tag::MSG_SEQ_NUM, 4)
+            .with(tag::GAP_FILL_FLAG, "Y")
+            .with(tag::NEW_SEQ_NO, 6);
+        assert!(session.on_message(gap_fill, now).unwrap().is_empty());
+        assert_eq!(session.sequence().next_incoming, 6);
+        assert_eq!(session.resend_until, None);
+    }
+
+    #[test]
+    fn test_resend_gap_fills_messages_evicted_from_buffer() {
+        let now = Instant::now();
+        let mut config = FixSessionConfig::new("CLIENT", "VENUE");
+        config.resend_buffer_len = 1;
+
+        let mut session = FixSession::new(config, MemorySequenceStore::default()).unwrap();
+        session.logon(now).unwrap();
+        session.on_message(FixMessage::new(msg_type::LOGON).with(tag::MSG_SEQ_NUM, 1), now).unwrap();
+        for index in 0..1000 {
+            session.send(order(&index.to_string()), now).unwrap();
+        }
+
+        let events = session.on_message(resend_request(2, 1, 0), now).unwrap();
+        assert_eq!(
+            sent(&events),
+            vec![(1, msg_type::SEQUENCE_RESET.to_owned(), Some(1001)), (1001, "D".to_owned(), None),]
+        );
+    }
+
+    #[test]
+    fn test_sequence_numbers_persisted_on_timer() {
+        let now = Instant::now();
+        let store = RecordingStore::default();
+        let mut session = logged_on(store.clone(), now);
+        session.send(order("a"), now).unwrap();
+        assert!(store.0.lock().is_empty());
+
+        session.on_timer(now).unwrap();
+        assert_eq!(
+            *store.0.lock(),
+            vec![SequenceNumbers {
+                next_outgoing: 3,
+                next_incoming: 2
+            }]
+        );
+
+        // Unchanged sequence numbers are not stored again
+        session.on_timer(now).unwrap();
+        session.flush().unwrap();
+        assert_eq!(store.0.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_file_sequence_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("fix-sequence-{}.store", std::process::id()));
+        let mut store = FileSequenceStore::new(&path);
+        assert_eq!(store.load().unwrap(), SequenceNumbers::default());
+
+        let now = Instant::now();
+        let mut session = logged_on(store.clone(), now);
+        session.send(order("a"), now).unwrap();
+        session.flush().unwrap();
+
+        let resumed = FixSession::new(FixSessionConfig::new("CLIENT", "VENUE"), FileSequenceStore::new(&path)).unwrap();
+        assert_eq!(
+            resumed.sequence(),
+            SequenceNumbers {
+                next_outgoing: 3,
+                next_incoming: 2
+            }
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
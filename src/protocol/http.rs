@@ -0,0 +1,520 @@
+use std::{borrow::Cow, time::Duration};
+
+use fnv::FnvHashMap;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::protocol::{
+    error::SocketError,
+    parser::HttpParser,
+    rate_limit::{InvalidRateLimit, RateLimit, RateLimitMode, RateLimiter, acquire_all},
+};
+
+const DEFAULT_HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub trait RestRequest {
+    type Response: DeserializeOwned;
+    type QueryParams: Serialize;
+    type Body: Serialize;
+
+    fn path(&self) -> Cow<'static, str>;
+
+    fn method() -> reqwest::Method;
+
+    fn query_params(&self) -> Option<&Self::QueryParams> {
+        None
+    }
+
+    fn body(&self) -> Option<&Self::Body> {
+        None
+    }
+
+    fn timeout() -> Duration {
+        DEFAULT_HTTP_REQUEST_TIMEOUT
+    }
+
+    // Key of the rate limit bucket this request is charged against, the path unless the endpoint
+    // embeds identifiers in it.
+    fn endpoint(&self) -> Cow<'static, str> {
+        self.path()
+    }
+
+    fn weight(&self) -> u32 {
+        1
+    }
+}
+
+pub trait RequestSigner {
+    fn sign<Request>(&self, request: &Request, built: reqwest::Request) -> Result<reqwest::Request, SocketError>
+    where
+        Request: RestRequest;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unsigned;
+
+impl RequestSigner for Unsigned {
+    fn sign<Request>(&self, _: &Request, built: reqwest::Request) -> Result<reqwest::Request, SocketError>
+    where
+        Request: RestRequest,
+    {
+        Ok(built)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EndpointRateLimit {
+    pub weight_max: u32,
+    pub window: Duration,
+}
+
+impl From<EndpointRateLimit> for RateLimit {
+    fn from(limit: EndpointRateLimit) -> Self {
+        Self::FixedWindow {
+            weight_max: limit.weight_max,
+            window: limit.window,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RestClient<Signer, Parser> {
+    http_client: reqwest::Client,
+    base_url: String,
+    signer: Signer,
+    parser: Parser,
+    rate_limiters: FnvHashMap<String, RateLimiter>,
+    // Charged for every request regardless of endpoint, eg/ connection or account wide weight limits
+    shared_rate_limiters: Vec<RateLimiter>,
+}
+
+impl<Signer, Parser> RestClient<Signer, Parser> {
+    pub fn new(base_url: impl Into<String>, signer: Signer, parser: Parser) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            base_url: base_url.into(),
+            signer,
+            parser,
+            rate_limiters: FnvHashMap::default(),
+            shared_rate_limiters: Vec::new(),
+        }
+    }
+
+    pub fn with_rate_limit(self, endpoint: impl Into<String>, limit: impl Into<RateLimit>) -> Result<Self, InvalidRateLimit> {
+        Ok(self.with_rate_limiter(endpoint, RateLimiter::new(limit.into(), RateLimitMode::Queue)?))
+    }
+
+    pub fn with_rate_limiter(mut self, endpoint: impl Into<String>, limiter: RateLimiter) -> Self {
+        self.rate_limiters.insert(endpoint.into(), limiter);
+
+        self
+    }
+
+    pub fn with_shared_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.shared_rate_limiters.push(limiter);
+
+        self
+    }
+}
+
+impl<Signer, Parser> RestClient<Signer, Parser>
+where
+    Signer: RequestSigner,
+    Parser: HttpParser,
+{
+    pub async fn execute<Request>(&self, request: Request) -> Result<Request::Response, Parser::OutputError>
+    where
+        Request: RestRequest,
+    {
+        let endpoint = request.endpoint();
+        let limiters = self.shared_rate_limiters.iter().chain(self.rate_limiters.get(endpoint.as_ref()));
+        acquire_all(limiters, request.weight()).await.map_err(SocketError::from)?;
+
+        let built = self.build(&request)?;
+        let response = self.http_client.execute(built).await.map_err(SocketError::from)?;
+
+        let status = response.status();
+        let payload = response.bytes().await.map_err(SocketError::from)?;
+
+        self.parser.parse::<Request::Response>(status, &payload)
+    }
+
+    pub fn build<Request>(&self, request: &Request) -> Result<reqwest::Request, SocketError>
+    where
+        Request: RestRequest,
+    {
+        let url = format!("{}{}", self.base_url, request.path());
+        let mut builder = self.http_client.request(Request::method(), url).timeout(Request::timeout());
+
+        if let Some(query_params) = request.query_params() {
+            builder = builder.query(query_params);
+        }
+
+        if let Some(body) = request.body() {
+            builder = builder.json(body);
+        }
+
+        let built = builder.build()?;
+
+        self.signer.sign(request, built)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, sync::Arc};
+
+    use parking_lot::Mutex;
+    use reqwest::StatusCode;
+    use serde::Deserialize;
+    use thiserror::Error;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::protocol::{parser::ProtocolParser, rate_limit::RateLimitError};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct RecordedRequest {
+        head: String,
+        body: String,
+    }
+
+    impl RecordedRequest {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name).then_some(value.trim())
+            })
+        }
+    }
+
+    // Minimal HTTP/1.1 server answering every request with the next scripted response, and
+    // recording what it received.
+    #[derive(Debug, Clone)]
+    struct MockHttpServer {
+        base_url: String,
+        responses: Arc<Mutex<VecDeque<(u16, &'static str)>>>,
+        requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    }
+
+    impl MockHttpServer {
+        async fn start(responses: impl IntoIterator<Item = (u16, &'static str)>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let server = Self {
+                base_url: format!("http://{}", listener.local_addr().unwrap()),
+                responses: Arc::new(Mutex::new(responses.into_iter().collect())),
+                requests: Arc::default(),
+            };
+
+            let accept = server.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(accept.clone().serve(stream));
+                }
+            });
+
+            server
+        }
+
+        async fn serve(self, stream: TcpStream) {
+            let mut stream = BufReader::new(stream);
+
+            loop {
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+
+                let request = RecordedRequest { head, body: String::new() };
+                let length = request.header("content-length").map_or(0, |length| length.parse().unwrap());
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).await.unwrap();
+                self.requests.lock().push(RecordedRequest {
+                    body: String::from_utf8(body).unwrap(),
+                    ..request
+                });
+
+                let (status, body) = self.responses.lock().pop_front().unwrap_or((500, "no scripted response"));
+                let response = format!(
+                    "HTTP/1.1 {status} MOCK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+            }
+        }
+
+        fn requests(&self) -> Vec<RecordedRequest> {
+            self.requests.lock().clone()
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize)]
+    struct ServerTime {
+        #[serde(rename = "serverTime")]
+        server_time: u64,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct OrderBody {
+        symbol: &'static str,
+        quantity: &'static str,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct TimeQuery {
+        recv_window: u64,
+    }
+
+    struct GetTime;
+
+    impl RestRequest for GetTime {
+        type Response = ServerTime;
+        type QueryParams = TimeQuery;
+        type Body = ();
+
+        fn path(&self) -> Cow<'static, str> {
+            Cow::Borrowed("/api/v3/time")
+        }
+
+        fn method() -> reqwest::Method {
+            reqwest::Method::GET
+        }
+
+        fn query_params(&self) -> Option<&Self::QueryParams> {
+            Some(&TimeQuery { recv_window: 5000 })
+        }
+    }
+
+    struct PostOrder(OrderBody);
+
+    impl RestRequest for PostOrder {
+        type Response = ServerTime;
+        type QueryParams = ();
+        type Body = OrderBody;
+
+        fn path(&self) -> Cow<'static, str> {
+            Cow::Borrowed("/api/v3/order")
+        }
+
+        fn method() -> reqwest::Method {
+            reqwest::Method::POST
+        }
+
+        fn body(&self) -> Option<&Self::Body> {
+            Some(&self.0)
+        }
+
+        fn weight(&self) -> u32 {
+            2
+        }
+    }
+
+    // Signs the path, query and body, standing in for an exchange HMAC.
+    struct TestSigner {
+        api_key: &'static str,
+    }
+
+    impl RequestSigner for TestSigner {
+        fn sign<Request>(&self, _: &Request, mut built: reqwest::Request) -> Result<reqwest::Request, SocketError>
+        where
+            Request: RestRequest,
+        {
+            let body = built.body().and_then(reqwest::Body::as_bytes).map(String::from_utf8_lossy).unwrap_or_default();
+            let payload = format!("{}?{}{body}", built.url().path(), built.url().query().unwrap_or_default());
+            let signature = payload.bytes().fold(0_u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(u32::from(byte)));
+
+            built.url_mut().query_pairs_mut().append_pair("signature", &signature.to_string());
+            built.headers_mut().insert("X-MBX-APIKEY", self.api_key.parse().unwrap());
+
+            Ok(built)
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ApiError {
+        code: i64,
+        msg: String,
+    }
+
+    #[derive(Debug, Error)]
+    enum TestError {
+        #[error("API error {status} {code}: {msg}")]
+        Api { status: StatusCode, code: i64, msg: String },
+
+        #[error(transparent)]
+        Socket(#[from] SocketError),
+    }
+
+    #[derive(Debug)]
+    struct TestParser;
+
+    impl ProtocolParser for TestParser {}
+
+    impl HttpParser for TestParser {
+        type ApiError = ApiError;
+        type OutputError = TestError;
+
+        fn parse_api_error(&self, status: StatusCode, error: Self::ApiError) -> Self::OutputError {
+            TestError::Api {
+                status,
+                code: error.code,
+                msg: error.msg,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_signed_request() {
+        let server = MockHttpServer::start([(200, r#"{"serverTime":1700000000000}"#)]).await;
+        let client = RestClient::new(&server.base_url, TestSigner { api_key: "key" }, TestParser);
+
+        let response = client.execute(GetTime).await.unwrap();
+        assert_eq!(response.server_time, 1700000000000);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("x-mbx-apikey"), Some("key"));
+
+        let request_line = requests[0].head.lines().next().unwrap();
+        let signature = "/api/v3/time?recv_window=5000"
+            .bytes()
+            .fold(0_u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(u32::from(byte)));
+        assert_eq!(request_line, format!("GET /api/v3/time?recv_window=5000&signature={signature} HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_sends_json_body() {
+        let server = MockHttpServer::start([(200, r#"{"serverTime":1}"#)]).await;
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser);
+
+        client
+            .execute(PostOrder(OrderBody {
+                symbol: "BTCUSDT",
+                quantity: "0.01",
+            }))
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].head.starts_with("POST /api/v3/order HTTP/1.1"));
+        assert_eq!(requests[0].header("content-type"), Some("application/json"));
+        assert_eq!(requests[0].body, r#"{"symbol":"BTCUSDT","quantity":"0.01"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_execute_api_error_body() {
+        let server = MockHttpServer::start([(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#)]).await;
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser);
+
+        let error = client.execute(GetTime).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            TestError::Api { status: StatusCode::BAD_REQUEST, code: -1121, ref msg } if msg == "Invalid symbol."
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_unparseable_bodies() {
+        let server = MockHttpServer::start([(502, "<html>Bad Gateway</html>"), (200, r#"{"unexpected":true}"#)]).await;
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser);
+
+        let error = client.execute(GetTime).await.unwrap_err();
+        assert!(matches!(
+            error,
+            TestError::Socket(SocketError::HttpResponse(StatusCode::BAD_GATEWAY, ref payload)) if payload == "<html>Bad Gateway</html>"
+        ));
+
+        let error = client.execute(GetTime).await.unwrap_err();
+        assert!(matches!(error, TestError::Socket(SocketError::Deserialise { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_execute_success_status_is_not_api_error() {
+        let server = MockHttpServer::start([(200, r#"{"code":-1121,"msg":"Invalid symbol."}"#)]).await;
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser);
+
+        let error = client.execute(GetTime).await.unwrap_err();
+        assert!(matches!(
+            error,
+            TestError::Socket(SocketError::Deserialise { ref error, .. }) if error.to_string().contains("serverTime")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_endpoint_rate_limit_rejects() {
+        let server = MockHttpServer::start([(200, r#"{"serverTime":1}"#), (200, r#"{"serverTime":2}"#)]).await;
+        let limit = EndpointRateLimit {
+            weight_max: 2,
+            window: Duration::from_secs(60),
+        };
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser)
+            .with_rate_limiter("/api/v3/time", RateLimiter::new(limit.into(), RateLimitMode::Reject).unwrap());
+
+        client.execute(GetTime).await.unwrap();
+        client.execute(GetTime).await.unwrap();
+        let error = client.execute(GetTime).await.unwrap_err();
+
+        assert!(matches!(error, TestError::Socket(SocketError::RateLimited(RateLimitError { weight: 1, .. }))));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_endpoint_rate_limit_queues() {
+        let server = MockHttpServer::start([(200, r#"{"serverTime":1}"#), (200, r#"{"serverTime":2}"#)]).await;
+        let window = Duration::from_millis(100);
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser)
+            .with_rate_limit("/api/v3/order", EndpointRateLimit { weight_max: 2, window })
+            .unwrap();
+        let order = || {
+            PostOrder(OrderBody {
+                symbol: "BTCUSDT",
+                quantity: "0.01",
+            })
+        };
+
+        let start = Instant::now();
+        client.execute(order()).await.unwrap();
+        client.execute(order()).await.unwrap();
+
+        // Each order weighs the whole limit, so the second waits out the first window
+        assert!(start.elapsed() >= window);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_shared_rate_limit_spans_endpoints() {
+        let server = MockHttpServer::start([(200, r#"{"serverTime":1}"#)]).await;
+        let shared = RateLimiter::new(
+            RateLimit::SlidingWindow {
+                weight_max: 2,
+                window: Duration::from_secs(60),
+            },
+            RateLimitMode::Reject,
+        )
+        .unwrap();
+        let client = RestClient::new(&server.base_url, Unsigned, TestParser).with_shared_rate_limiter(shared.clone());
+
+        client
+            .execute(PostOrder(OrderBody {
+                symbol: "BTCUSDT",
+                quantity: "0.01",
+            }))
+            .await
+            .unwrap();
+        let error = client.execute(GetTime).await.unwrap_err();
+
+        assert!(matches!(error, TestError::Socket(SocketError::RateLimited(_))));
+        assert!(shared.try_acquire(1, Instant::now()).is_err());
+    }
+}
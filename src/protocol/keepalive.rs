@@ -0,0 +1,299 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Sink, SinkExt, Stream, StreamExt, channel::mpsc};
+use tracing::{debug, error, warn};
+
+use crate::{
+    exchange::PingInterval,
+    protocol::websocket::{WsError, WsMessage},
+};
+
+// WebSocket whose pings and pong tracking run in a background task owning the socket, so the connection
+// is kept alive whether or not the consumer is polling. Once too many pongs in a row are missed the
+// stream yields a `TimedOut` error, which `is_websocket_disconnected` treats as a lost connection, and ends.
+#[derive(Debug)]
+pub struct KeepaliveWebSocket {
+    outbound: mpsc::UnboundedSender<WsMessage>,
+    inbound: mpsc::UnboundedReceiver<Result<WsMessage, WsError>>,
+}
+
+impl KeepaliveWebSocket {
+    pub fn new<Socket>(socket: Socket, ping: PingInterval, is_pong: fn(&WsMessage) -> bool, pongs_missed_max: u32) -> Self
+    where
+        Socket: Stream<Item = Result<WsMessage, WsError>> + Sink<WsMessage, Error = WsError> + Send + 'static,
+    {
+        let (outbound, outbound_rx) = mpsc::unbounded();
+        let (inbound_tx, inbound) = mpsc::unbounded();
+
+        let keepalive = Keepalive {
+            ping,
+            is_pong,
+            pongs_missed_max,
+            pongs_missed: 0,
+            awaiting_pong: false,
+        };
+        tokio::spawn(keepalive.run(socket, outbound_rx, inbound_tx));
+
+        Self { outbound, inbound }
+    }
+}
+
+pub fn is_ws_pong(message: &WsMessage) -> bool {
+    matches!(message, WsMessage::Pong(_))
+}
+
+impl Stream for KeepaliveWebSocket {
+    type Item = Result<WsMessage, WsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inbound.poll_next_unpin(cx)
+    }
+}
+
+// Messages are written by the background task, a closed channel means the socket is gone.
+impl Sink<WsMessage> for KeepaliveWebSocket {
+    type Error = WsError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outbound.poll_ready_unpin(cx).map_err(|_| WsError::AlreadyClosed)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: WsMessage) -> Result<(), Self::Error> {
+        self.outbound.start_send_unpin(item).map_err(|_| WsError::AlreadyClosed)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outbound.poll_flush_unpin(cx).map_err(|_| WsError::AlreadyClosed)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outbound.poll_close_unpin(cx).map_err(|_| WsError::AlreadyClosed)
+    }
+}
+
+#[derive(Debug)]
+struct Keepalive {
+    ping: PingInterval,
+    is_pong: fn(&WsMessage) -> bool,
+    pongs_missed_max: u32,
+    pongs_missed: u32,
+    awaiting_pong: bool,
+}
+
+impl Keepalive {
+    async fn run<Socket>(mut self, socket: Socket, mut outbound: mpsc::UnboundedReceiver<WsMessage>, inbound: mpsc::UnboundedSender<Result<WsMessage, WsError>>)
+    where
+        Socket: Stream<Item = Result<WsMessage, WsError>> + Sink<WsMessage, Error = WsError>,
+    {
+        let mut socket = std::pin::pin!(socket);
+
+        loop {
+            let sent = tokio::select! {
+                _ = self.ping.interval.tick() => match self.next_ping() {
+                    Some(ping) => {
+                        debug!(payload = ?ping, "Sending WebSocket ping.");
+                        socket.send(ping).await
+                    },
+                    None => Err(WsError::Io(std::io::Error::new(std::io::ErrorKind::TimedOut, "missed too many WebSocket pongs"))),
+                },
+                message = outbound.next() => match message {
+                    Some(message) => socket.send(message).await,
+                    // The `KeepaliveWebSocket` was dropped or closed
+                    None => {
+                        let _ = socket.close().await;
+                        return;
+                    },
+                },
+                message = socket.next() => {
+                    let Some(message) = message else {
+                        return;
+                    };
+
+                    if message.as_ref().is_ok_and(self.is_pong) {
+                        self.awaiting_pong = false;
+                        self.pongs_missed = 0;
+                    }
+
+                    match inbound.unbounded_send(message) {
+                        Ok(()) => Ok(()),
+                        Err(_) => return,
+                    }
+                },
+            };
+
+            // Ends the stream after the error, as the connection is no longer usable
+            if let Err(error) = sent {
+                let _ = inbound.unbounded_send(Err(error));
+                return;
+            }
+        }
+    }
+
+    // Every tick without a pong for the previous ping counts as a miss, `None` once there are too many.
+    fn next_ping(&mut self) -> Option<WsMessage> {
+        if self.awaiting_pong {
+            self.pongs_missed += 1;
+            warn!(pongs_missed = self.pongs_missed, "WebSocket ping was not answered in time.");
+
+            if self.pongs_missed >= self.pongs_missed_max {
+                error!(pongs_missed = self.pongs_missed, "WebSocket declared dead after missing too many pongs.");
+                return None;
+            }
+        }
+
+        self.awaiting_pong = true;
+        Some((self.ping.ping)())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, time::Duration};
+
+    use tokio::time::Instant;
+    use tokio_tungstenite::tungstenite::Bytes;
+
+    use super::*;
+    use crate::protocol::{stream::RecoverableStream, websocket::is_websocket_disconnected};
+
+    const INTERVAL: Duration = Duration::from_secs(10);
+
+    // The far end of the connection, fed and observed by the test
+    #[derive(Debug)]
+    struct Peer {
+        tx: mpsc::UnboundedSender<Result<WsMessage, WsError>>,
+        rx: mpsc::UnboundedReceiver<WsMessage>,
+    }
+
+    #[derive(Debug)]
+    struct MockSocket {
+        inbound: mpsc::UnboundedReceiver<Result<WsMessage, WsError>>,
+        outbound: mpsc::UnboundedSender<WsMessage>,
+    }
+
+    impl Stream for MockSocket {
+        type Item = Result<WsMessage, WsError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.inbound.poll_next_unpin(cx)
+        }
+    }
+
+    impl Sink<WsMessage> for MockSocket {
+        type Error = WsError;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: WsMessage) -> Result<(), Self::Error> {
+            self.outbound.unbounded_send(item).map_err(|_| WsError::ConnectionClosed)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn ping() -> WsMessage {
+        WsMessage::Ping(Bytes::from_static(b"ping"))
+    }
+
+    fn pong() -> WsMessage {
+        WsMessage::Pong(Bytes::from_static(b"ping"))
+    }
+
+    fn connect(pongs_missed_max: u32) -> (KeepaliveWebSocket, Peer) {
+        let (tx, inbound) = mpsc::unbounded();
+        let (outbound, rx) = mpsc::unbounded();
+        let ping = PingInterval {
+            interval: tokio::time::interval(INTERVAL),
+            ping,
+        };
+        let websocket = KeepaliveWebSocket::new(MockSocket { inbound, outbound }, ping, is_ws_pong, pongs_missed_max);
+
+        (websocket, Peer { tx, rx })
+    }
+
+    fn assert_timed_out(error: WsError) {
+        assert!(matches!(&error, WsError::Io(error) if error.kind() == ErrorKind::TimedOut));
+        assert!(is_websocket_disconnected(&error));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pings_sent_without_consumer_polling() {
+        let (mut websocket, mut peer) = connect(2);
+        let start = Instant::now();
+
+        // The consumer does not poll while the peer answers every ping
+        for _ in 0..4 {
+            assert_eq!(peer.rx.next().await, Some(ping()));
+            peer.tx.unbounded_send(Ok(pong())).unwrap();
+        }
+        assert_eq!(start.elapsed(), 3 * INTERVAL);
+
+        // Pongs are still handed to the consumer, who can write alongside the pings
+        for _ in 0..4 {
+            assert_eq!(websocket.next().await.unwrap().unwrap(), pong());
+        }
+        websocket.send(WsMessage::text("subscribe")).await.unwrap();
+        assert_eq!(peer.rx.next().await, Some(WsMessage::text("subscribe")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_missed_pongs_declare_connection_dead() {
+        let (mut websocket, mut peer) = connect(2);
+        let start = Instant::now();
+
+        // Pinged at 0s and 10s, the second miss at 20s is one too many
+        assert_timed_out(websocket.next().await.unwrap().unwrap_err());
+        assert_eq!(start.elapsed(), 2 * INTERVAL);
+        assert!(websocket.next().await.is_none());
+
+        assert_eq!(peer.rx.next().await, Some(ping()));
+        assert_eq!(peer.rx.next().await, Some(ping()));
+        assert_eq!(peer.rx.next().await, None);
+        assert!(websocket.send(WsMessage::text("subscribe")).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pong_resets_missed_pongs() {
+        let (mut websocket, mut peer) = connect(2);
+        let start = Instant::now();
+
+        // One miss at 10s, then a late pong at 15s
+        assert_eq!(peer.rx.next().await, Some(ping()));
+        assert_eq!(peer.rx.next().await, Some(ping()));
+        tokio::time::sleep(INTERVAL / 2).await;
+        peer.tx.unbounded_send(Ok(pong())).unwrap();
+
+        assert_eq!(websocket.next().await.unwrap().unwrap(), pong());
+        assert_timed_out(websocket.next().await.unwrap().unwrap_err());
+        assert_eq!(start.elapsed(), 4 * INTERVAL);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dead_connection_triggers_reconnect() {
+        let (dead, dead_peer) = connect(1);
+        let (alive, alive_peer) = connect(1);
+        dead_peer.tx.unbounded_send(Ok(WsMessage::text("first"))).unwrap();
+        alive_peer.tx.unbounded_send(Ok(WsMessage::text("second"))).unwrap();
+
+        // The timeout is terminal, so the next connection takes over
+        let messages: Vec<_> = futures::stream::iter([dead, alive])
+            .with_termination_on_error(is_websocket_disconnected, 0)
+            .flatten()
+            .take(2)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(messages, vec![WsMessage::text("first"), WsMessage::text("second")]);
+    }
+}
@@ -0,0 +1,368 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use fnv::FnvHashMap;
+use tokio::time::Instant;
+use tracing::info;
+
+// Log-linear buckets, each power of two split into 2^SUB_BUCKET_BITS, giving ~12.5% resolution.
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+const LINEAR_MAX: u64 = 2 * SUB_BUCKETS as u64;
+const BUCKETS: usize = LINEAR_MAX as usize + (64 - (SUB_BUCKET_BITS as usize + 1)) * SUB_BUCKETS;
+
+const PERCENTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStamps {
+    pub time_exchange: Option<DateTime<Utc>>,
+    // The inner `Stream` yielded the raw message, ie/ it was read off the socket.
+    pub time_received: DateTime<Utc>,
+    pub time_parsed: DateTime<Utc>,
+    pub time_transformed: DateTime<Utc>,
+}
+
+impl LatencyStamps {
+    // Negative when the local clock lags the exchange's.
+    pub fn network(&self) -> Option<chrono::TimeDelta> {
+        self.time_exchange.map(|time_exchange| self.time_received - time_exchange)
+    }
+
+    pub fn parse(&self) -> chrono::TimeDelta {
+        self.time_parsed - self.time_received
+    }
+
+    pub fn transform(&self) -> chrono::TimeDelta {
+        self.time_transformed - self.time_parsed
+    }
+
+    pub fn total(&self) -> chrono::TimeDelta {
+        self.time_transformed - self.time_exchange.unwrap_or(self.time_received)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stamped<T> {
+    pub stamps: LatencyStamps,
+    pub value: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    max: u64,
+    // Samples clamped to zero because the exchange clock ran ahead of ours
+    negative: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            max: 0,
+            negative: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: chrono::TimeDelta) {
+        let nanos = match latency.num_nanoseconds() {
+            Some(nanos) if nanos < 0 => {
+                self.negative += 1;
+                0
+            },
+            Some(nanos) => nanos as u64,
+            None => u64::MAX,
+        };
+
+        self.buckets[bucket_index(nanos)] += 1;
+        self.count += 1;
+        self.max = self.max.max(nanos);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max)
+    }
+
+    // Upper bound of the bucket holding the `quantile`, capped at the largest recorded sample.
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let index = self.buckets.iter().position(|count| {
+            seen += count;
+            seen >= rank
+        })?;
+
+        Some(Duration::from_nanos(bucket_upper(index).min(self.max)))
+    }
+
+    pub fn reset(&mut self) {
+        self.buckets.fill(0);
+        self.count = 0;
+        self.max = 0;
+        self.negative = 0;
+    }
+}
+
+fn bucket_index(nanos: u64) -> usize {
+    if nanos < LINEAR_MAX {
+        return nanos as usize;
+    }
+
+    let exponent = 63 - nanos.leading_zeros();
+    let mantissa = (nanos >> (exponent - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+
+    LINEAR_MAX as usize + (exponent - SUB_BUCKET_BITS - 1) as usize * SUB_BUCKETS + mantissa
+}
+
+fn bucket_lower(index: usize) -> u64 {
+    if index < LINEAR_MAX as usize {
+        return index as u64;
+    }
+
+    let exponent = (index - LINEAR_MAX as usize) / SUB_BUCKETS + SUB_BUCKET_BITS as usize + 1;
+    let mantissa = ((index - LINEAR_MAX as usize) % SUB_BUCKETS) as u64;
+
+    (SUB_BUCKETS as u64 + mantissa) << (exponent - SUB_BUCKET_BITS as usize)
+}
+
+fn bucket_upper(index: usize) -> u64 {
+    if index + 1 >= BUCKETS { u64::MAX } else { bucket_lower(index + 1) - 1 }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistograms {
+    pub network: LatencyHistogram,
+    pub parse: LatencyHistogram,
+    pub transform: LatencyHistogram,
+    pub total: LatencyHistogram,
+}
+
+impl LatencyHistograms {
+    pub fn record(&mut self, stamps: &LatencyStamps) {
+        if let Some(network) = stamps.network() {
+            self.network.record(network);
+        }
+        self.parse.record(stamps.parse());
+        self.transform.record(stamps.transform());
+        self.total.record(stamps.total());
+    }
+
+    fn reset(&mut self) {
+        self.network.reset();
+        self.parse.reset();
+        self.transform.reset();
+        self.total.reset();
+    }
+}
+
+// Aggregates `LatencyStamps` per exchange and message type, logging percentiles every
+// `report_interval` under the `latency` tracing target, eg/ `RUST_LOG=info,latency=info`.
+#[derive(Debug)]
+pub struct LatencyAggregator {
+    histograms: FnvHashMap<(&'static str, &'static str), LatencyHistograms>,
+    report_interval: Duration,
+    report_last: Instant,
+}
+
+impl LatencyAggregator {
+    pub fn new(report_interval: Duration) -> Self {
+        Self {
+            histograms: FnvHashMap::default(),
+            report_interval,
+            report_last: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, exchange: &'static str, kind: &'static str, stamps: &LatencyStamps) {
+        self.histograms.entry((exchange, kind)).or_default().record(stamps);
+
+        if self.report_last.elapsed() >= self.report_interval {
+            self.report();
+        }
+    }
+
+    pub fn histograms(&self, exchange: &'static str, kind: &'static str) -> Option<&LatencyHistograms> {
+        self.histograms.get(&(exchange, kind))
+    }
+
+    // Logs and then resets every histogram, so each report covers a single interval.
+    pub fn report(&mut self) {
+        self.report_last = Instant::now();
+
+        let mut keys = self.histograms.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+
+        for (exchange, kind) in keys {
+            let histograms = self.histograms.get_mut(&(exchange, kind)).expect("key collected from map");
+            for (segment, histogram) in [
+                ("network", &histograms.network),
+                ("parse", &histograms.parse),
+                ("transform", &histograms.transform),
+                ("total", &histograms.total),
+            ] {
+                if histogram.count == 0 {
+                    continue;
+                }
+
+                let [p50, p90, p99, p999] = PERCENTILES.map(|quantile| histogram.percentile(quantile).unwrap_or_default());
+                info!(
+                    target: "latency",
+                    exchange,
+                    kind,
+                    segment,
+                    count = histogram.count,
+                    clock_skewed = histogram.negative,
+                    ?p50,
+                    ?p90,
+                    ?p99,
+                    ?p999,
+                    max = ?histogram.max(),
+                    "Latency percentiles."
+                );
+            }
+
+            histograms.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn histogram(latencies_ns: impl IntoIterator<Item = i64>) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::default();
+        latencies_ns.into_iter().for_each(|nanos| histogram.record(TimeDelta::nanoseconds(nanos)));
+        histogram
+    }
+
+    fn stamps(received_ms: i64, parse_us: i64) -> LatencyStamps {
+        let time_exchange = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let time_received = time_exchange + TimeDelta::milliseconds(received_ms);
+        let time_parsed = time_received + TimeDelta::microseconds(parse_us);
+
+        LatencyStamps {
+            time_exchange: Some(time_exchange),
+            time_received,
+            time_parsed,
+            time_transformed: time_parsed,
+        }
+    }
+
+    #[test]
+    fn test_bucket_boundaries_are_contiguous() {
+        // Exact below the linear max
+        (0..LINEAR_MAX).for_each(|nanos| assert_eq!(bucket_index(nanos), nanos as usize));
+
+        assert_eq!(bucket_lower(0), 0);
+        assert_eq!(bucket_index(u64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_upper(BUCKETS - 1), u64::MAX);
+
+        for index in 0..BUCKETS - 1 {
+            let (lower, upper) = (bucket_lower(index), bucket_upper(index));
+            assert_eq!(bucket_index(lower), index);
+            assert_eq!(bucket_index(upper), index);
+            assert_eq!(bucket_lower(index + 1), upper + 1);
+
+            // ~12.5% resolution
+            assert!((upper - lower) as f64 <= lower as f64 / SUB_BUCKETS as f64, "bucket {index}: {lower}..={upper}");
+        }
+    }
+
+    #[test]
+    fn test_percentile_extremes() {
+        assert_eq!(LatencyHistogram::default().percentile(0.5), None);
+
+        let histogram = histogram((1..=1000).map(|micros| micros * 1000));
+        assert_eq!(histogram.count(), 1000);
+        assert_eq!(histogram.max(), Duration::from_millis(1));
+
+        // The first sample's bucket at the bottom, the max itself at the top
+        let p0 = histogram.percentile(0.0).unwrap();
+        assert!(Duration::from_micros(1) <= p0 && p0 <= Duration::from_nanos(1125));
+        assert_eq!(histogram.percentile(1.0), Some(Duration::from_millis(1)));
+
+        // Out of range quantiles are clamped
+        assert_eq!(histogram.percentile(-1.0), Some(p0));
+        assert_eq!(histogram.percentile(2.0), Some(Duration::from_millis(1)));
+
+        // Upper bounds, within a bucket of the exact value
+        let p50 = histogram.percentile(0.5).unwrap();
+        assert!(Duration::from_micros(500) <= p50 && p50 <= Duration::from_micros(500) * 9 / 8);
+        let p999 = histogram.percentile(0.999).unwrap();
+        assert!(Duration::from_micros(999) <= p999 && p999 <= Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_single_sample_percentiles_are_exact() {
+        let histogram = histogram([1_234_567]);
+        for quantile in PERCENTILES {
+            assert_eq!(histogram.percentile(quantile), Some(Duration::from_nanos(1_234_567)));
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_latencies_are_clamped() {
+        let mut histogram = histogram([-5_000]);
+        assert_eq!((histogram.count(), histogram.negative), (1, 1));
+        assert_eq!(histogram.percentile(1.0), Some(Duration::ZERO));
+
+        histogram.record(TimeDelta::MAX);
+        assert_eq!(histogram.buckets[BUCKETS - 1], 1);
+        assert_eq!(histogram.percentile(1.0), Some(Duration::from_nanos(u64::MAX)));
+
+        histogram.reset();
+        assert_eq!((histogram.count(), histogram.negative, histogram.max()), (0, 0, Duration::ZERO));
+        assert_eq!(histogram.percentile(1.0), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_aggregator_merges_per_exchange_and_kind() {
+        let mut aggregator = LatencyAggregator::new(Duration::from_secs(60));
+
+        aggregator.record("binance", "trade", &stamps(2, 10));
+        aggregator.record("binance", "trade", &stamps(4, 30));
+        aggregator.record("binance", "book", &stamps(8, 20));
+        aggregator.record("okx", "trade", &stamps(16, 40));
+
+        let trades = aggregator.histograms("binance", "trade").unwrap();
+        assert_eq!(trades.network.count(), 2);
+        assert_eq!(trades.network.max(), Duration::from_millis(4));
+        assert_eq!(trades.parse.max(), Duration::from_micros(30));
+        assert_eq!(trades.total.max(), Duration::from_micros(4030));
+
+        assert_eq!(aggregator.histograms("binance", "book").unwrap().network.max(), Duration::from_millis(8));
+        assert_eq!(aggregator.histograms("okx", "trade").unwrap().network.count(), 1);
+        assert!(aggregator.histograms("okx", "book").is_none());
+
+        // Without an exchange time there is no network latency
+        let mut local = stamps(1, 1);
+        local.time_exchange = None;
+        aggregator.record("okx", "trade", &local);
+        let okx = aggregator.histograms("okx", "trade").unwrap();
+        assert_eq!((okx.network.count(), okx.parse.count()), (1, 2));
+
+        // A record past the interval reports and resets every histogram, itself included
+        tokio::time::advance(Duration::from_secs(60)).await;
+        aggregator.record("binance", "trade", &stamps(1, 1));
+        assert_eq!(aggregator.histograms("binance", "trade").unwrap().network.count(), 0);
+        assert_eq!(aggregator.histograms("okx", "trade").unwrap().parse.count(), 0);
+
+        aggregator.record("binance", "trade", &stamps(1, 1));
+        assert_eq!(aggregator.histograms("binance", "trade").unwrap().network.count(), 1);
+    }
+}
@@ -1,4 +1,15 @@
-mod parser;
-mod stream;
-mod transformer;
+pub mod depth;
+pub mod encoding;
+pub mod error;
+pub mod fix;
+pub mod http;
+pub mod keepalive;
+pub mod latency;
+pub mod parser;
+pub mod pool;
+pub mod rate_limit;
+pub mod sbe;
+pub mod sequence;
+pub mod stream;
+pub mod transformer;
 pub mod websocket;
@@ -1,7 +1,11 @@
 use futures::Stream;
-use serde::de::DeserializeOwned;
+use reqwest::StatusCode;
+use serde::{Deserialize, de::DeserializeOwned};
+use tracing::error;
 
-pub(crate) trait ProtocolParser {}
+use crate::protocol::{error::SocketError, sbe::SbeDecode};
+
+pub trait ProtocolParser {}
 
 pub trait WebsocketParser
 where
@@ -16,8 +20,80 @@ where
         Output: DeserializeOwned;
 }
 
+// Deserializes in place from a reusable `Buffer`, so the output may borrow from the frame payload
+// for the duration of a single transform.
+pub trait WebsocketBorrowedParser
+where
+    Self: WebsocketParser,
+{
+    type Buffer: Default;
+
+    fn parse_borrowed<'de, Output>(
+        input: Result<Self::Message, Self::Error>,
+        buffer: &'de mut Self::Buffer,
+    ) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: Deserialize<'de>;
+}
+
+pub trait FixParser
+where
+    Self: ProtocolParser,
+{
+    type Message;
+    type Error;
+
+    fn parse<Output>(input: Result<Self::Message, Self::Error>) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: DeserializeOwned;
+}
+
+// Decodes fixed-layout binary frames straight into `Output`, without going through serde.
+pub trait BinaryParser
+where
+    Self: ProtocolParser,
+{
+    type Message;
+    type Error;
+
+    fn parse<Output>(input: Result<Self::Message, Self::Error>) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: SbeDecode;
+}
+
 pub trait HttpParser
 where
     Self: ProtocolParser,
 {
+    type ApiError: DeserializeOwned;
+    type OutputError: From<SocketError>;
+
+    fn parse<Response>(&self, status: StatusCode, payload: &[u8]) -> Result<Response, Self::OutputError>
+    where
+        Response: DeserializeOwned,
+    {
+        // Only a successful status carries the typed response body, a 2xx that fails to deserialize is
+        // reported as such rather than being mistaken for an exchange error
+        if status.is_success() {
+            return serde_json::from_slice::<Response>(payload).map_err(|error| {
+                let payload = String::from_utf8_lossy(payload).into_owned();
+                error!(?status, ?error, %payload, "Failed to deserialize HTTP response.");
+
+                SocketError::Deserialise { error, payload }.into()
+            });
+        }
+
+        // Otherwise the body should be the exchange specific error
+        match serde_json::from_slice::<Self::ApiError>(payload) {
+            Ok(api_error) => Err(self.parse_api_error(status, api_error)),
+            Err(error) => {
+                let payload = String::from_utf8_lossy(payload).into_owned();
+                error!(?status, ?error, %payload, "Failed to parse HTTP error response.");
+
+                Err(SocketError::HttpResponse(status, payload).into())
+            },
+        }
+    }
+
+    fn parse_api_error(&self, status: StatusCode, error: Self::ApiError) -> Self::OutputError;
 }
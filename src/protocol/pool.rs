@@ -0,0 +1,349 @@
+use std::{collections::VecDeque, fmt::Debug, sync::Arc};
+
+use derive_more::{Constructor, Display};
+use futures::{Stream, StreamExt, channel::oneshot};
+use parking_lot::Mutex;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+use crate::{
+    exchange::SubscriptionLimit,
+    protocol::stream::{ReconnectionAttempt, ReconnectionError, ReconnectionPolicy, RecoverableStream, StreamEvent},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display, Constructor)]
+pub struct ShardId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Constructor)]
+pub struct ShardingPolicy {
+    pub subscriptions_per_connection_max: usize,
+    pub connections_max: Option<usize>,
+}
+
+impl ShardingPolicy {
+    pub fn of<Exchange>() -> Self
+    where
+        Exchange: SubscriptionLimit,
+    {
+        Self {
+            subscriptions_per_connection_max: Exchange::SUBSCRIPTIONS_PER_CONNECTION_MAX,
+            connections_max: Exchange::CONNECTIONS_MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ShardingError {
+    #[error("sharding policy allows no subscriptions per connection")]
+    ZeroCapacity,
+
+    #[error("{subscriptions} subscriptions need {connections} connections, exchange allows {connections_max}")]
+    CapacityExceeded {
+        subscriptions: usize,
+        connections: usize,
+        connections_max: usize,
+    },
+}
+
+#[derive(Debug, Error)]
+#[error("shard {shard} terminated: {error}")]
+pub struct ShardTerminated<InitErr> {
+    pub shard: ShardId,
+    pub error: ReconnectionError<InitErr>,
+}
+
+#[derive(Debug)]
+struct Shard<Sub> {
+    subscriptions: Vec<Sub>,
+    is_terminated: bool,
+    // Ends the shard's current connection, so it reconnects with rehomed subscriptions
+    rehome: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Debug)]
+struct ShardTable<Sub> {
+    policy: ShardingPolicy,
+    shards: Vec<Shard<Sub>>,
+    orphans: VecDeque<Sub>,
+}
+
+impl<Sub> ShardTable<Sub>
+where
+    Sub: Clone + Debug,
+{
+    fn assign(&mut self, shard: ShardId) -> (Vec<Sub>, oneshot::Receiver<()>) {
+        let (rehome_tx, rehome_rx) = oneshot::channel();
+        let assigned = &mut self.shards[shard.0];
+        assigned.rehome = Some(rehome_tx);
+
+        (assigned.subscriptions.clone(), rehome_rx)
+    }
+
+    fn terminate(&mut self, shard: ShardId) {
+        let terminated = &mut self.shards[shard.0];
+        terminated.is_terminated = true;
+        terminated.rehome = None;
+
+        warn!(%shard, orphaned = terminated.subscriptions.len(), "Shard gave up reconnecting, orphaning its subscriptions.");
+        self.orphans.extend(terminated.subscriptions.drain(..));
+        self.rehome();
+    }
+
+    // Orphans go to the least loaded live shards straight away, each of which reconnects to subscribe
+    // them. Orphans beyond the capacity of the live shards stay orphaned.
+    fn rehome(&mut self) {
+        let mut rehomed = vec![false; self.shards.len()];
+        while !self.orphans.is_empty() {
+            let Some((index, shard)) = self
+                .shards
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, shard)| !shard.is_terminated && shard.subscriptions.len() < self.policy.subscriptions_per_connection_max)
+                .min_by_key(|(_, shard)| shard.subscriptions.len())
+            else {
+                break;
+            };
+
+            shard.subscriptions.extend(self.orphans.pop_front());
+            rehomed[index] = true;
+        }
+
+        for (index, shard) in self.shards.iter_mut().enumerate().filter(|(index, _)| rehomed[*index]) {
+            info!(shard = %ShardId(index), subscriptions = shard.subscriptions.len(), "Reconnecting shard to subscribe orphaned subscriptions.");
+            if let Some(rehome) = shard.rehome.take() {
+                let _ = rehome.send(());
+            }
+        }
+
+        if !self.orphans.is_empty() {
+            error!(stranded = self.orphans.len(), "Live shards are at capacity, subscriptions remain orphaned.");
+        }
+    }
+}
+
+// Shards a subscription set across as many connections as the exchange's per connection limit
+// requires, and merges every shard into a single `Stream`.
+#[derive(Debug)]
+pub struct ConnectionPool<Sub> {
+    table: Arc<Mutex<ShardTable<Sub>>>,
+}
+
+impl<Sub> ConnectionPool<Sub>
+where
+    Sub: Clone + Debug,
+{
+    pub fn new<Subs>(subscriptions: Subs, policy: ShardingPolicy) -> Result<Self, ShardingError>
+    where
+        Subs: IntoIterator<Item = Sub>,
+    {
+        if policy.subscriptions_per_connection_max == 0 {
+            return Err(ShardingError::ZeroCapacity);
+        }
+
+        let subscriptions = subscriptions.into_iter().collect::<Vec<_>>();
+        let connections = subscriptions.len().div_ceil(policy.subscriptions_per_connection_max).max(1);
+        if let Some(connections_max) = policy.connections_max.filter(|connections_max| connections > *connections_max) {
+            return Err(ShardingError::CapacityExceeded {
+                subscriptions: subscriptions.len(),
+                connections,
+                connections_max,
+            });
+        }
+
+        // Contiguous, evenly sized shards keep related subscriptions together
+        let (base, remainder) = (subscriptions.len() / connections, subscriptions.len() % connections);
+        let mut subscriptions = subscriptions.into_iter();
+        let shards = (0..connections)
+            .map(|index| Shard {
+                subscriptions: subscriptions.by_ref().take(base + usize::from(index < remainder)).collect(),
+                is_terminated: false,
+                rehome: None,
+            })
+            .collect();
+
+        Ok(Self {
+            table: Arc::new(Mutex::new(ShardTable {
+                policy,
+                shards,
+                orphans: VecDeque::new(),
+            })),
+        })
+    }
+
+    pub fn shards(&self) -> Vec<Vec<Sub>> {
+        self.table.lock().shards.iter().map(|shard| shard.subscriptions.clone()).collect()
+    }
+
+    // Subscriptions of terminated shards that no live shard had capacity for.
+    pub fn orphans(&self) -> Vec<Sub> {
+        self.table.lock().orphans.iter().cloned().collect()
+    }
+
+    // `connect` is invoked for every (re)connection of a shard with the subscriptions it currently
+    // owns, and is expected to subscribe them before returning the shard's `Stream`. A shard giving
+    // up reconnecting yields its `ShardTerminated` error, the merged `Stream` ends once all have.
+    pub fn connect<FnConnect, InitFut, InnerSt, InitErr>(
+        &self,
+        policy: ReconnectionPolicy,
+        connect: FnConnect,
+    ) -> impl Stream<Item = Result<StreamEvent<ShardId, InnerSt::Item>, ShardTerminated<InitErr>>> + use<Sub, FnConnect, InitFut, InnerSt, InitErr>
+    where
+        FnConnect: Clone + FnMut(ShardId, Vec<Sub>) -> InitFut,
+        InitFut: Future<Output = Result<InnerSt, InitErr>>,
+        InnerSt: Stream,
+        InitErr: Debug,
+    {
+        let shards = (0..self.table.lock().shards.len()).map(ShardId).map(|shard| {
+            let (table_assign, table_terminate) = (Arc::clone(&self.table), Arc::clone(&self.table));
+            let mut connect = connect.clone();
+
+            let connections = futures::stream::repeat(()).then(move |_| {
+                let (subscriptions, rehome) = table_assign.lock().assign(shard);
+                let connection = connect(shard, subscriptions);
+                async move { connection.await.map(|stream| stream.take_until(rehome)) }
+            });
+
+            let stream = connections
+                .with_reconnect_policy(policy.clone(), shard.0 as u64, move |attempt| {
+                    if let ReconnectionAttempt::Terminated { .. } = attempt {
+                        table_terminate.lock().terminate(shard);
+                    }
+                })
+                .map(move |result| match result {
+                    Ok(stream) => stream
+                        .map(StreamEvent::Item)
+                        .chain(futures::stream::once(std::future::ready(StreamEvent::Reconnecting(shard))))
+                        .map(Ok)
+                        .left_stream(),
+                    Err(error) => futures::stream::once(std::future::ready(Err(ShardTerminated { shard, error }))).right_stream(),
+                })
+                .flatten();
+
+            Box::pin(stream)
+        });
+
+        futures::stream::select_all(shards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::stream::BoxStream;
+
+    use super::*;
+    use crate::protocol::stream::{BackoffJitter, ReconnectionLimit};
+
+    type Connections = Arc<Mutex<Vec<(ShardId, Vec<u32>)>>>;
+
+    fn sharding(subscriptions_per_connection_max: usize) -> ShardingPolicy {
+        ShardingPolicy::new(subscriptions_per_connection_max, None)
+    }
+
+    fn give_up_at_once() -> ReconnectionPolicy {
+        ReconnectionPolicy {
+            backoff_ms_initial: 100,
+            backoff_multiplier: 2.0,
+            backoff_ms_max: 1000,
+            jitter: BackoffJitter::None,
+            attempts_max: Some(1),
+            backoff_ms_budget: None,
+        }
+    }
+
+    // Connections yield their subscriptions then idle, shards in `down` fail after a second
+    fn connector(
+        down: &'static [usize],
+        connections: Connections,
+    ) -> impl Clone + FnMut(ShardId, Vec<u32>) -> futures::future::BoxFuture<'static, Result<BoxStream<'static, u32>, &'static str>> {
+        move |shard, subscriptions| {
+            connections.lock().push((shard, subscriptions.clone()));
+            Box::pin(async move {
+                if down.contains(&shard.0) {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    return Err("down");
+                }
+
+                Ok(futures::stream::iter(subscriptions).chain(futures::stream::pending()).boxed())
+            })
+        }
+    }
+
+    #[test]
+    fn test_new_shards_contiguously() {
+        let pool = ConnectionPool::new(1..=5, sharding(2)).unwrap();
+        assert_eq!(pool.shards(), vec![vec![1, 2], vec![3, 4], vec![5]]);
+
+        assert_eq!(ConnectionPool::new(1..=5, sharding(0)).unwrap_err(), ShardingError::ZeroCapacity);
+        assert_eq!(
+            ConnectionPool::new(1..=5, ShardingPolicy::new(2, Some(2))).unwrap_err(),
+            ShardingError::CapacityExceeded {
+                subscriptions: 5,
+                connections: 3,
+                connections_max: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_terminate_rehomes_onto_least_loaded_shards() {
+        let pool = ConnectionPool::new(1..=11, sharding(5)).unwrap();
+        assert_eq!(pool.shards(), vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8], vec![9, 10, 11]]);
+
+        pool.table.lock().terminate(ShardId(0));
+        assert_eq!(pool.shards(), vec![vec![], vec![5, 6, 7, 8, 2], vec![9, 10, 11, 1, 3]]);
+        assert_eq!(pool.orphans(), vec![4]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_terminated_shard_rehomed_onto_live_connection() {
+        let pool = ConnectionPool::new(1..=4, sharding(3)).unwrap();
+        let connections = Connections::default();
+        let mut events = pool.connect(give_up_at_once(), connector(&[0], Arc::clone(&connections)));
+
+        let (mut items, mut errors) = (Vec::new(), Vec::new());
+        while items.len() < 6 {
+            match events.next().await.unwrap() {
+                Ok(StreamEvent::Item(item)) => items.push(Some(item)),
+                Ok(StreamEvent::Reconnecting(shard)) => {
+                    assert_eq!(shard, ShardId(1));
+                    items.push(None);
+                },
+                Err(error) => errors.push(error),
+            }
+        }
+
+        // The healthy shard reconnects at once to pick up what fits, the rest stays orphaned
+        assert_eq!(items, vec![Some(3), Some(4), None, Some(3), Some(4), Some(1)]);
+        let [ShardTerminated { shard, error }] = errors.as_slice() else {
+            panic!("expected one terminated shard, got {errors:?}");
+        };
+        assert_eq!(
+            (*shard, error.attempt, error.limit, error.error),
+            (ShardId(0), 1, ReconnectionLimit::AttemptsExhausted, "down")
+        );
+        assert_eq!(pool.orphans(), vec![2]);
+
+        let mut connections = connections.lock().clone();
+        connections.sort();
+        assert_eq!(
+            connections,
+            vec![(ShardId(0), vec![1, 2]), (ShardId(1), vec![3, 4]), (ShardId(1), vec![3, 4, 1])]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stream_ends_once_every_shard_terminated() {
+        let pool = ConnectionPool::new(1..=4, sharding(2)).unwrap();
+        let events = pool
+            .connect(give_up_at_once(), connector(&[0, 1], Connections::default()))
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut terminated = events.into_iter().map(|event| event.map(|_| ()).unwrap_err().shard).collect::<Vec<_>>();
+        terminated.sort();
+        assert_eq!(terminated, vec![ShardId(0), ShardId(1)]);
+        assert_eq!(pool.orphans().len(), 4);
+    }
+}
@@ -0,0 +1,495 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use futures::{Sink, Stream};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use smallvec::SmallVec;
+use thiserror::Error;
+use tokio::time::{Instant, Sleep};
+use tracing::{debug, warn};
+
+use crate::protocol::websocket::{WsError, WsMessage};
+
+const LIMITERS_INLINE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimit {
+    // Weight resets in full every `window`, measured from the first request of the window.
+    FixedWindow { weight_max: u32, window: Duration },
+    // Weight of every request counts against the limit for exactly `window` after it was sent.
+    SlidingWindow { weight_max: u32, window: Duration },
+    // Bursts up to `capacity`, replenished at `refill` tokens every `interval`.
+    TokenBucket { capacity: u32, refill: u32, interval: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    // Wait until the limit has capacity again.
+    #[default]
+    Queue,
+    // Fail immediately, leaving the caller to decide whether to retry.
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum InvalidRateLimit {
+    #[error("token bucket never refills, `refill` is zero")]
+    ZeroRefill,
+
+    #[error("token bucket never refills, `interval` is zero")]
+    ZeroInterval,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("rate limit exceeded for weight {weight}, retry after {retry_after:?}")]
+pub struct RateLimitError {
+    pub weight: u32,
+    pub retry_after: Duration,
+}
+
+// Cloning shares the underlying budget, so one limiter can be attached to every connection, endpoint
+// or account the exchange counts together, eg/ an account weight shared by REST and websocket orders.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    mode: RateLimitMode,
+    state: Arc<Mutex<RateLimitState>>,
+}
+
+#[derive(Debug)]
+enum RateLimitState {
+    FixedWindow { start: Instant, used: u32 },
+    SlidingWindow { sent: VecDeque<(Instant, u32)>, used: u32 },
+    TokenBucket { tokens: f64, updated: Instant },
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit, mode: RateLimitMode) -> Result<Self, InvalidRateLimit> {
+        match limit {
+            RateLimit::TokenBucket { refill: 0, .. } => return Err(InvalidRateLimit::ZeroRefill),
+            RateLimit::TokenBucket { interval, .. } if interval.is_zero() => return Err(InvalidRateLimit::ZeroInterval),
+            _ => {},
+        }
+
+        let now = Instant::now();
+        let state = match limit {
+            RateLimit::FixedWindow { .. } => RateLimitState::FixedWindow { start: now, used: 0 },
+            RateLimit::SlidingWindow { .. } => RateLimitState::SlidingWindow {
+                sent: VecDeque::new(),
+                used: 0,
+            },
+            RateLimit::TokenBucket { capacity, .. } => RateLimitState::TokenBucket {
+                tokens: f64::from(capacity),
+                updated: now,
+            },
+        };
+
+        Ok(Self {
+            limit,
+            mode,
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    // Consumes `weight` if available at `now`, otherwise returns the earliest `Instant` it would be.
+    // A request heavier than the whole limit is let through once the limit is fully unused.
+    pub fn try_acquire(&self, weight: u32, now: Instant) -> Result<(), Instant> {
+        try_acquire_all([self], weight, now).map_err(|(_, available_at)| available_at)
+    }
+
+    pub async fn acquire(&self, weight: u32) -> Result<(), RateLimitError> {
+        acquire_all([self], weight).await
+    }
+}
+
+// Waits for, or in `Reject` mode fails on, the limiters without capacity. Every limiter is charged
+// at once, so a request rejected by one limiter does not use up the budget of the others.
+pub async fn acquire_all<'a, Limiters>(limiters: Limiters, weight: u32) -> Result<(), RateLimitError>
+where
+    Limiters: IntoIterator<Item = &'a RateLimiter>,
+    Limiters::IntoIter: Clone,
+{
+    let limiters = limiters.into_iter();
+
+    loop {
+        let now = Instant::now();
+
+        match try_acquire_all(limiters.clone(), weight, now) {
+            Ok(()) => return Ok(()),
+            Err((RateLimitMode::Reject, available_at)) => {
+                return Err(RateLimitError {
+                    weight,
+                    retry_after: available_at.saturating_duration_since(now),
+                });
+            },
+            Err((RateLimitMode::Queue, wait_until)) => {
+                debug!(weight, ?wait_until, "Rate limit reached, delaying request.");
+                tokio::time::sleep_until(wait_until).await;
+            },
+        }
+    }
+}
+
+// Charges `weight` against every limiter if all have capacity at `now`, otherwise charges none and
+// returns the latest `Instant` at which they all would, rejecting limiters taking precedence.
+fn try_acquire_all<'a>(limiters: impl IntoIterator<Item = &'a RateLimiter>, weight: u32, now: Instant) -> Result<(), (RateLimitMode, Instant)> {
+    // Locked in address order so concurrent callers sharing limiters cannot deadlock, and a limiter
+    // attached more than once is only charged once. Requests rarely count against more than a few.
+    let mut limiters = limiters.into_iter().collect::<SmallVec<[_; LIMITERS_INLINE]>>();
+    limiters.sort_unstable_by_key(|limiter| Arc::as_ptr(&limiter.state));
+    limiters.dedup_by_key(|limiter| Arc::as_ptr(&limiter.state));
+
+    let mut states = limiters.iter().map(|limiter| limiter.state.lock()).collect::<SmallVec<[_; LIMITERS_INLINE]>>();
+
+    let blocked = limiters
+        .iter()
+        .zip(states.iter_mut())
+        .filter_map(|(limiter, state)| Some((limiter.mode, state.available_at(limiter.limit, weight, now)?)))
+        .max_by_key(|(mode, available_at)| (*mode == RateLimitMode::Reject, *available_at));
+
+    match blocked {
+        Some(blocked) => Err(blocked),
+        None => {
+            states.iter_mut().for_each(|state| state.consume(weight, now));
+            Ok(())
+        },
+    }
+}
+
+impl RateLimitState {
+    fn available_at(&mut self, limit: RateLimit, weight: u32, now: Instant) -> Option<Instant> {
+        match (self, limit) {
+            (Self::FixedWindow { start, used }, RateLimit::FixedWindow { weight_max, window }) => {
+                if now >= *start + window {
+                    *start = now;
+                    *used = 0;
+                }
+
+                (*used != 0 && *used + weight > weight_max).then_some(*start + window)
+            },
+            (Self::SlidingWindow { sent, used }, RateLimit::SlidingWindow { weight_max, window }) => {
+                while let Some((_, expired)) = sent.front().filter(|(time, _)| *time + window <= now) {
+                    *used -= expired;
+                    sent.pop_front();
+                }
+
+                if *used == 0 || *used + weight <= weight_max {
+                    return None;
+                }
+
+                // Earliest point at which enough of the window has expired to fit `weight`
+                let mut remaining = *used;
+                sent.iter().find_map(|(time, sent_weight)| {
+                    remaining -= sent_weight;
+                    (remaining == 0 || remaining + weight <= weight_max).then_some(*time + window)
+                })
+            },
+            (Self::TokenBucket { tokens, updated }, RateLimit::TokenBucket { capacity, refill, interval }) => {
+                let rate = f64::from(refill) / interval.as_secs_f64();
+                let capacity = f64::from(capacity);
+                *tokens = (*tokens + now.saturating_duration_since(*updated).as_secs_f64() * rate).min(capacity);
+                *updated = now;
+
+                let required = f64::from(weight).min(capacity);
+                (*tokens < required).then(|| now + Duration::from_secs_f64((required - *tokens) / rate))
+            },
+            _ => unreachable!("RateLimitState is constructed from its RateLimit"),
+        }
+    }
+
+    fn consume(&mut self, weight: u32, now: Instant) {
+        match self {
+            Self::FixedWindow { used, .. } => *used += weight,
+            Self::SlidingWindow { sent, used } => {
+                sent.push_back((now, weight));
+                *used += weight;
+            },
+            Self::TokenBucket { tokens, .. } => *tokens = (*tokens - f64::from(weight)).max(0.0),
+        }
+    }
+}
+
+pub fn ws_message_weight(message: &WsMessage) -> u32 {
+    match message {
+        WsMessage::Ping(_) | WsMessage::Pong(_) | WsMessage::Close(_) | WsMessage::Frame(_) => 0,
+        WsMessage::Text(_) | WsMessage::Binary(_) => 1,
+    }
+}
+
+// Throttles messages sent through `Socket`, eg/ subscription or order requests, with every attached
+// limiter. In `Queue` mode a throttled message is held until the limiters allow it, in `Reject` mode
+// `start_send` fails with an `io::ErrorKind::WouldBlock` error and the message is dropped. The
+// connection is still usable, `is_websocket_disconnected` does not count `WouldBlock` as a disconnect.
+#[derive(Debug)]
+#[pin_project]
+pub struct RateLimitedSink<Socket> {
+    #[pin]
+    socket: Socket,
+    limiters: Vec<RateLimiter>,
+    weigh: fn(&WsMessage) -> u32,
+    pending: Option<PendingMessage>,
+    #[pin]
+    sleep: Option<Sleep>,
+}
+
+#[derive(Debug)]
+struct PendingMessage {
+    message: WsMessage,
+    weight: u32,
+}
+
+impl<Socket> RateLimitedSink<Socket> {
+    pub fn new(socket: Socket, limiters: Vec<RateLimiter>, weigh: fn(&WsMessage) -> u32) -> Self {
+        Self {
+            socket,
+            limiters,
+            weigh,
+            pending: None,
+            sleep: None,
+        }
+    }
+}
+
+impl<Socket> RateLimitedSink<Socket>
+where
+    Socket: Sink<WsMessage, Error = WsError>,
+{
+    fn poll_pending(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), WsError>> {
+        let mut this = self.project();
+
+        loop {
+            let Some(PendingMessage { weight, .. }) = this.pending.as_ref() else {
+                return Poll::Ready(Ok(()));
+            };
+
+            if let Some(sleep) = this.sleep.as_mut().as_pin_mut() {
+                ready!(sleep.poll(cx));
+                this.sleep.set(None);
+            }
+
+            ready!(this.socket.as_mut().poll_ready(cx))?;
+            if let Err((_, wait_until)) = try_acquire_all(this.limiters.iter(), *weight, Instant::now()) {
+                debug!(weight, ?wait_until, "WebSocket rate limit reached, delaying message.");
+                this.sleep.set(Some(tokio::time::sleep_until(wait_until)));
+                continue;
+            }
+
+            let PendingMessage { message, .. } = this.pending.take().expect("pending checked to be Some");
+            this.socket.as_mut().start_send(message)?;
+        }
+    }
+}
+
+impl<Socket> Stream for RateLimitedSink<Socket>
+where
+    Socket: Stream<Item = Result<WsMessage, WsError>>,
+{
+    type Item = Result<WsMessage, WsError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().socket.poll_next(cx)
+    }
+}
+
+impl<Socket> Sink<WsMessage> for RateLimitedSink<Socket>
+where
+    Socket: Sink<WsMessage, Error = WsError>,
+{
+    type Error = WsError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_pending(cx))?;
+        self.project().socket.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: WsMessage) -> Result<(), Self::Error> {
+        let this = self.project();
+        let weight = (this.weigh)(&item);
+        let now = Instant::now();
+
+        // `poll_ready` has drained any pending message and readied `socket`, so an unthrottled
+        // message is passed straight through
+        match try_acquire_all(this.limiters.iter(), weight, now) {
+            Ok(()) => this.socket.start_send(item),
+            Err((RateLimitMode::Reject, available_at)) => {
+                let error = RateLimitError {
+                    weight,
+                    retry_after: available_at.saturating_duration_since(now),
+                };
+                warn!(%error, "WebSocket message rejected by rate limiter.");
+
+                Err(WsError::Io(std::io::Error::new(std::io::ErrorKind::WouldBlock, error)))
+            },
+            Err((RateLimitMode::Queue, _)) => {
+                *this.pending = Some(PendingMessage { message: item, weight });
+                Ok(())
+            },
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_pending(cx))?;
+        self.project().socket.poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_pending(cx))?;
+        self.project().socket.poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt, channel::mpsc};
+
+    use super::*;
+    use crate::protocol::websocket::is_websocket_disconnected;
+
+    fn fixed(weight_max: u32, mode: RateLimitMode) -> RateLimiter {
+        RateLimiter::new(
+            RateLimit::FixedWindow {
+                weight_max,
+                window: Duration::from_secs(1),
+            },
+            mode,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_token_bucket_never_refilling_rejected() {
+        let bucket = |refill, interval| {
+            RateLimiter::new(
+                RateLimit::TokenBucket {
+                    capacity: 10,
+                    refill,
+                    interval,
+                },
+                RateLimitMode::Queue,
+            )
+        };
+
+        assert_eq!(bucket(0, Duration::from_secs(1)).unwrap_err(), InvalidRateLimit::ZeroRefill);
+        assert_eq!(bucket(1, Duration::ZERO).unwrap_err(), InvalidRateLimit::ZeroInterval);
+        assert!(bucket(1, Duration::from_secs(1)).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_all_rejected_charges_none() {
+        let roomy = fixed(10, RateLimitMode::Queue);
+        let tight = fixed(2, RateLimitMode::Reject);
+        tight.try_acquire(2, Instant::now()).unwrap();
+
+        let error = acquire_all([&roomy, &tight], 1).await.unwrap_err();
+        assert_eq!(error.retry_after, Duration::from_secs(1));
+
+        // `roomy` kept its whole budget despite coming first
+        roomy.try_acquire(10, Instant::now()).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_all_queues_then_charges_all() {
+        let account = RateLimiter::new(
+            RateLimit::SlidingWindow {
+                weight_max: 10,
+                window: Duration::from_secs(10),
+            },
+            RateLimitMode::Queue,
+        )
+        .unwrap();
+        let endpoint = fixed(1, RateLimitMode::Queue);
+        let start = Instant::now();
+
+        acquire_all([&account, &endpoint], 1).await.unwrap();
+        acquire_all([&account, &endpoint], 1).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        // Both requests counted against the account once, waiting on `endpoint` charged nothing
+        assert!(account.try_acquire(9, Instant::now()).is_err());
+        account.try_acquire(8, Instant::now()).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_all_shared_limiter_charged_once() {
+        let shared = fixed(2, RateLimitMode::Reject);
+
+        acquire_all([&shared, &shared.clone()], 1).await.unwrap();
+        acquire_all([&shared], 1).await.unwrap();
+        assert!(acquire_all([&shared], 1).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket_refill() {
+        let bucket = RateLimiter::new(
+            RateLimit::TokenBucket {
+                capacity: 2,
+                refill: 1,
+                interval: Duration::from_millis(500),
+            },
+            RateLimitMode::Queue,
+        )
+        .unwrap();
+        let start = Instant::now();
+
+        bucket.try_acquire(2, start).unwrap();
+        assert_eq!(bucket.try_acquire(1, start), Err(start + Duration::from_millis(500)));
+
+        bucket.acquire(2).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    fn rate_limited(limiter: RateLimiter) -> (RateLimitedSink<impl Sink<WsMessage, Error = WsError>>, mpsc::UnboundedReceiver<WsMessage>) {
+        let (tx, rx) = mpsc::unbounded();
+        let socket = tx.sink_map_err(|_| WsError::ConnectionClosed);
+        (RateLimitedSink::new(socket, vec![limiter], ws_message_weight), rx)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sink_queue_delays_message() {
+        let (sink, mut rx) = rate_limited(fixed(1, RateLimitMode::Queue));
+        let mut sink = std::pin::pin!(sink);
+        let start = Instant::now();
+
+        sink.send(WsMessage::text("first")).await.unwrap();
+        sink.send(WsMessage::Ping(Default::default())).await.unwrap();
+        sink.send(WsMessage::text("second")).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        let sent = rx.by_ref().take(3).collect::<Vec<_>>().await;
+        assert_eq!(
+            sent,
+            vec![WsMessage::text("first"), WsMessage::Ping(Default::default()), WsMessage::text("second")]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sink_reject_keeps_connection() {
+        let (sink, mut rx) = rate_limited(fixed(1, RateLimitMode::Reject));
+        let mut sink = std::pin::pin!(sink);
+
+        sink.send(WsMessage::text("first")).await.unwrap();
+        let error = sink.send(WsMessage::text("second")).await.unwrap_err();
+        assert!(matches!(&error, WsError::Io(error) if error.kind() == std::io::ErrorKind::WouldBlock));
+        assert!(!is_websocket_disconnected(&error));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        sink.send(WsMessage::text("third")).await.unwrap();
+
+        let sent = rx.by_ref().take(2).collect::<Vec<_>>().await;
+        assert_eq!(sent, vec![WsMessage::text("first"), WsMessage::text("third")]);
+    }
+}
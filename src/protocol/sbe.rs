@@ -0,0 +1,641 @@
+use rust_decimal::Decimal;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::protocol::{
+    error::SocketError,
+    parser::{BinaryParser, ProtocolParser},
+    websocket::{WsError, WsMessage, process_close_frame, process_frame, process_ping, process_pong},
+};
+
+// blockLength, templateId, schemaId, version
+const MESSAGE_HEADER_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthEncoding {
+    U8,
+    U16,
+    U32,
+}
+
+// Layout shared by every message of a venue's schema.
+pub trait SbeSchema {
+    const SCHEMA_ID: u16;
+    const BYTE_ORDER: ByteOrder = ByteOrder::LittleEndian;
+    // Width of `numInGroup` in a repeating group's dimension header, its `blockLength` is always a u16.
+    const GROUP_COUNT: LengthEncoding = LengthEncoding::U16;
+    const VAR_DATA_LENGTH: LengthEncoding = LengthEncoding::U16;
+}
+
+// Decoded straight from the frame, usually an enum dispatching on `SbeHeader::template_id`. Unknown
+// templates should return `SbeError::UnknownTemplate`, which parsers skip rather than fail on.
+pub trait SbeDecode
+where
+    Self: Sized,
+{
+    type Schema: SbeSchema;
+
+    fn decode<'a>(root: SbeBlock<'a>, message: &mut SbeMessage<'a>) -> Result<Self, SbeError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SbeError {
+    #[error("truncated SBE message: {len} bytes needed at offset {offset}, {available} available")]
+    Truncated { offset: usize, len: usize, available: usize },
+
+    #[error("SBE schema id {received} does not match expected {expected}")]
+    SchemaMismatch { expected: u16, received: u16 },
+
+    #[error("unknown SBE template id {0}")]
+    UnknownTemplate(u16),
+
+    #[error("invalid UTF-8 in SBE field at offset {offset}")]
+    Utf8 { offset: usize },
+
+    #[error("invalid value {value} for SBE field {field}")]
+    InvalidValue { field: &'static str, value: i64 },
+}
+
+pub trait SbePrimitive
+where
+    Self: Sized + Copy,
+{
+    const SIZE: usize;
+
+    fn read(bytes: &[u8], order: ByteOrder) -> Self;
+
+    // SBE encodes an absent optional field as a type specific null sentinel.
+    fn is_null(self) -> bool;
+}
+
+macro_rules! sbe_primitive {
+    ($($primitive:ty => $null:expr),* $(,)?) => {$(
+        impl SbePrimitive for $primitive {
+            const SIZE: usize = size_of::<$primitive>();
+
+            fn read(bytes: &[u8], order: ByteOrder) -> Self {
+                let bytes = bytes.try_into().expect("slice sized to the primitive");
+                match order {
+                    ByteOrder::LittleEndian => <$primitive>::from_le_bytes(bytes),
+                    ByteOrder::BigEndian => <$primitive>::from_be_bytes(bytes),
+                }
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn is_null(self) -> bool {
+                ($null)(self)
+            }
+        }
+    )*};
+}
+
+sbe_primitive!(
+    u8 => |value| value == u8::MAX,
+    u16 => |value| value == u16::MAX,
+    u32 => |value| value == u32::MAX,
+    u64 => |value| value == u64::MAX,
+    i8 => |value| value == i8::MIN,
+    i16 => |value| value == i16::MIN,
+    i32 => |value| value == i32::MIN,
+    i64 => |value| value == i64::MIN,
+    f32 => |value: f32| value.is_nan(),
+    f64 => |value: f64| value.is_nan(),
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SbeHeader {
+    pub block_length: u16,
+    pub template_id: u16,
+    pub schema_id: u16,
+    pub version: u16,
+}
+
+// Fixed-layout block, fields are read at their schema offset relative to the start of the block.
+#[derive(Debug, Clone, Copy)]
+pub struct SbeBlock<'a> {
+    bytes: &'a [u8],
+    // Offset of the block within the message, for error reporting
+    base: usize,
+    order: ByteOrder,
+}
+
+impl<'a> SbeBlock<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn get<T>(&self, offset: usize) -> Result<T, SbeError>
+    where
+        T: SbePrimitive,
+    {
+        self.bytes(offset, T::SIZE).map(|bytes| T::read(bytes, self.order))
+    }
+
+    pub fn get_opt<T>(&self, offset: usize) -> Result<Option<T>, SbeError>
+    where
+        T: SbePrimitive,
+    {
+        self.get::<T>(offset).map(|value| (!value.is_null()).then_some(value))
+    }
+
+    pub fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], SbeError> {
+        self.bytes.get(offset..offset + len).ok_or(SbeError::Truncated {
+            offset: self.base + offset,
+            len,
+            available: self.bytes.len().saturating_sub(offset),
+        })
+    }
+
+    // Fixed length char array, padded with trailing NULs.
+    pub fn str(&self, offset: usize, len: usize) -> Result<&'a str, SbeError> {
+        let bytes = self.bytes(offset, len)?;
+        let end = bytes.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+
+        std::str::from_utf8(&bytes[..end]).map_err(|_| SbeError::Utf8 { offset: self.base + offset })
+    }
+
+    // i64 mantissa with the exponent fixed by the schema, eg/ price with exponent -8. A null mantissa
+    // is an `InvalidValue` error, optional fields are read with `decimal_opt`.
+    pub fn decimal(&self, offset: usize, exponent: i8) -> Result<Decimal, SbeError> {
+        self.decimal_opt(offset, exponent)?.ok_or(NULL_MANTISSA)
+    }
+
+    pub fn decimal_opt(&self, offset: usize, exponent: i8) -> Result<Option<Decimal>, SbeError> {
+        decimal(self.get::<i64>(offset)?, exponent)
+    }
+
+    // i64 mantissa followed by an i8 exponent.
+    pub fn decimal_composite(&self, offset: usize) -> Result<Decimal, SbeError> {
+        self.decimal_composite_opt(offset)?.ok_or(NULL_MANTISSA)
+    }
+
+    pub fn decimal_composite_opt(&self, offset: usize) -> Result<Option<Decimal>, SbeError> {
+        let mantissa = self.get::<i64>(offset)?;
+        let exponent = self.get::<i8>(offset + i64::SIZE)?;
+        decimal(mantissa, exponent)
+    }
+}
+
+const NULL_MANTISSA: SbeError = SbeError::InvalidValue {
+    field: "decimal mantissa",
+    value: i64::MIN,
+};
+
+// Exponents outside what a `Decimal` can represent, ie/ a scale above 28 or a value above ~7.9e28,
+// are an `InvalidValue` error rather than a panic.
+fn decimal(mantissa: i64, exponent: i8) -> Result<Option<Decimal>, SbeError> {
+    if mantissa.is_null() {
+        return Ok(None);
+    }
+
+    let exponent_abs = u32::from(exponent.unsigned_abs());
+    let value = if exponent <= 0 {
+        Decimal::try_new(mantissa, exponent_abs).ok()
+    } else {
+        10_i64
+            .checked_pow(exponent_abs)
+            .and_then(|multiplier| Decimal::from(mantissa).checked_mul(Decimal::from(multiplier)))
+    };
+
+    value.map(Some).ok_or(SbeError::InvalidValue {
+        field: "decimal exponent",
+        value: i64::from(exponent),
+    })
+}
+
+// Cursor over the variable part of a message following its root block: repeating groups and var
+// data, which must be read in schema order.
+#[derive(Debug)]
+pub struct SbeMessage<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    header: SbeHeader,
+    order: ByteOrder,
+    group_count: LengthEncoding,
+    var_data_length: LengthEncoding,
+}
+
+impl<'a> SbeMessage<'a> {
+    pub fn header(&self) -> &SbeHeader {
+        &self.header
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    // Decodes every entry of the next repeating group, `decode` may read nested groups and var data
+    // of an entry from the cursor it is handed.
+    pub fn group<T, FnDecode>(&mut self, mut decode: FnDecode) -> Result<Vec<T>, SbeError>
+    where
+        FnDecode: FnMut(&mut Self, SbeBlock<'a>) -> Result<T, SbeError>,
+    {
+        let block_length = self.read_length(LengthEncoding::U16)?;
+        let count = self.read_length(self.group_count)?;
+
+        // Entries are at least `block_length` long, so a corrupt count can't trigger a huge allocation
+        let mut entries = Vec::with_capacity(count.min(self.remaining() / block_length.max(1)));
+        for _ in 0..count {
+            let block = self.block(block_length)?;
+            entries.push(decode(self, block)?);
+        }
+
+        Ok(entries)
+    }
+
+    pub fn var_data(&mut self) -> Result<&'a [u8], SbeError> {
+        let len = self.read_length(self.var_data_length)?;
+        self.take(len)
+    }
+
+    pub fn var_str(&mut self) -> Result<&'a str, SbeError> {
+        let offset = self.position;
+        std::str::from_utf8(self.var_data()?).map_err(|_| SbeError::Utf8 { offset })
+    }
+
+    fn block(&mut self, len: usize) -> Result<SbeBlock<'a>, SbeError> {
+        let base = self.position;
+        let bytes = self.take(len)?;
+
+        Ok(SbeBlock {
+            bytes,
+            base,
+            order: self.order,
+        })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SbeError> {
+        let bytes = self.bytes.get(self.position..self.position + len).ok_or(SbeError::Truncated {
+            offset: self.position,
+            len,
+            available: self.remaining(),
+        })?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn read_length(&mut self, encoding: LengthEncoding) -> Result<usize, SbeError> {
+        let order = self.order;
+        let len = match encoding {
+            LengthEncoding::U8 => usize::from(u8::read(self.take(u8::SIZE)?, order)),
+            LengthEncoding::U16 => usize::from(u16::read(self.take(u16::SIZE)?, order)),
+            LengthEncoding::U32 => u32::read(self.take(u32::SIZE)?, order) as usize,
+        };
+
+        Ok(len)
+    }
+}
+
+pub fn decode_header<Schema>(bytes: &[u8]) -> Result<SbeHeader, SbeError>
+where
+    Schema: SbeSchema,
+{
+    let block = SbeBlock {
+        bytes,
+        base: 0,
+        order: Schema::BYTE_ORDER,
+    };
+
+    let header = SbeHeader {
+        block_length: block.get(0)?,
+        template_id: block.get(2)?,
+        schema_id: block.get(4)?,
+        version: block.get(6)?,
+    };
+
+    if header.schema_id != Schema::SCHEMA_ID {
+        return Err(SbeError::SchemaMismatch {
+            expected: Schema::SCHEMA_ID,
+            received: header.schema_id,
+        });
+    }
+
+    Ok(header)
+}
+
+pub fn decode<Output>(bytes: &[u8]) -> Result<Output, SbeError>
+where
+    Output: SbeDecode,
+{
+    let header = decode_header::<Output::Schema>(bytes)?;
+    let mut message = SbeMessage {
+        bytes,
+        position: MESSAGE_HEADER_LEN,
+        header,
+        order: <Output::Schema as SbeSchema>::BYTE_ORDER,
+        group_count: <Output::Schema as SbeSchema>::GROUP_COUNT,
+        var_data_length: <Output::Schema as SbeSchema>::VAR_DATA_LENGTH,
+    };
+
+    // Fields a newer schema version appends to the root block are skipped by honouring `block_length`
+    let root = message.block(usize::from(header.block_length))?;
+
+    Output::decode(root, &mut message)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WebsocketSbeParser;
+
+impl ProtocolParser for WebsocketSbeParser {}
+
+impl BinaryParser for WebsocketSbeParser {
+    type Message = WsMessage;
+    type Error = WsError;
+
+    fn parse<Output>(input: Result<Self::Message, Self::Error>) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: SbeDecode,
+    {
+        let output = match input {
+            Ok(WsMessage::Binary(payload)) => match decode::<Output>(&payload) {
+                Ok(output) => Some(Ok(output)),
+                Err(SbeError::UnknownTemplate(template_id)) => {
+                    debug!(template_id, "Skipping SBE message with unknown template.");
+                    None
+                },
+                Err(error) => return Some(Err(error.into())),
+            },
+            // Venues pushing binary market data still answer subscriptions with JSON text
+            Ok(WsMessage::Text(text)) => {
+                debug!(payload = %text, "Skipping text frame on binary stream.");
+                None
+            },
+            Ok(WsMessage::Ping(ping)) => process_ping(ping),
+            Ok(WsMessage::Pong(pong)) => process_pong(pong),
+            Ok(WsMessage::Close(close_frame)) => process_close_frame(close_frame),
+            Ok(WsMessage::Frame(frame)) => process_frame(frame),
+            Err(error) => Some(Err(SocketError::WebSocket(Box::new(error)))),
+        };
+
+        output.map(|result| result.map_err(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, marker::PhantomData};
+
+    use futures::StreamExt;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::protocol::{stream::BinaryExchangeStream, transformer::Transformer};
+
+    const TEMPLATE_TRADES: u16 = 1;
+    const ROOT_LEN: u16 = 16;
+    const TRADE_LEN: u16 = 17;
+
+    #[derive(Debug, PartialEq)]
+    struct LittleEndianSchema;
+
+    impl SbeSchema for LittleEndianSchema {
+        const SCHEMA_ID: u16 = 1;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct BigEndianSchema;
+
+    impl SbeSchema for BigEndianSchema {
+        const SCHEMA_ID: u16 = 2;
+        const BYTE_ORDER: ByteOrder = ByteOrder::BigEndian;
+        const GROUP_COUNT: LengthEncoding = LengthEncoding::U8;
+        const VAR_DATA_LENGTH: LengthEncoding = LengthEncoding::U32;
+    }
+
+    // Root: transactTime u64, bestBid i64 mantissa (exponent -2, optional)
+    // Group trades: price (i64 mantissa, i8 exponent), quantity i64 mantissa (exponent -8)
+    // Var data: symbol
+    #[derive(Debug, PartialEq)]
+    struct Trades<Schema> {
+        time: u64,
+        best_bid: Option<Decimal>,
+        trades: Vec<(Decimal, Decimal)>,
+        symbol: String,
+        schema: PhantomData<Schema>,
+    }
+
+    impl<Schema> SbeDecode for Trades<Schema>
+    where
+        Schema: SbeSchema,
+    {
+        type Schema = Schema;
+
+        fn decode<'a>(root: SbeBlock<'a>, message: &mut SbeMessage<'a>) -> Result<Self, SbeError> {
+            if message.header().template_id != TEMPLATE_TRADES {
+                return Err(SbeError::UnknownTemplate(message.header().template_id));
+            }
+
+            Ok(Self {
+                time: root.get(0)?,
+                best_bid: root.decimal_opt(8, -2)?,
+                trades: message.group(|_, trade| Ok((trade.decimal_composite(0)?, trade.decimal(9, -8)?)))?,
+                symbol: message.var_str()?.to_owned(),
+                schema: PhantomData,
+            })
+        }
+    }
+
+    // Hand encoder, integers are given as little endian bytes and flipped for big endian schemas.
+    struct Encoder<Schema> {
+        bytes: Vec<u8>,
+        schema: PhantomData<Schema>,
+    }
+
+    impl<Schema> Encoder<Schema>
+    where
+        Schema: SbeSchema,
+    {
+        fn new(block_length: u16, template_id: u16) -> Self {
+            Self {
+                bytes: Vec::new(),
+                schema: PhantomData,
+            }
+            .int(&block_length.to_le_bytes())
+            .int(&template_id.to_le_bytes())
+            .int(&Schema::SCHEMA_ID.to_le_bytes())
+            .int(&0_u16.to_le_bytes())
+        }
+
+        fn int(mut self, le_bytes: &[u8]) -> Self {
+            match Schema::BYTE_ORDER {
+                ByteOrder::LittleEndian => self.bytes.extend(le_bytes),
+                ByteOrder::BigEndian => self.bytes.extend(le_bytes.iter().rev()),
+            }
+            self
+        }
+
+        fn length(self, encoding: LengthEncoding, len: usize) -> Self {
+            match encoding {
+                LengthEncoding::U8 => self.int(&(len as u8).to_le_bytes()),
+                LengthEncoding::U16 => self.int(&(len as u16).to_le_bytes()),
+                LengthEncoding::U32 => self.int(&(len as u32).to_le_bytes()),
+            }
+        }
+
+        fn raw(mut self, bytes: &[u8]) -> Self {
+            self.bytes.extend(bytes);
+            self
+        }
+    }
+
+    fn encode<Schema>(best_bid: i64, trades: &[(i64, i8, i64)], symbol: &str) -> Vec<u8>
+    where
+        Schema: SbeSchema,
+    {
+        let mut encoder = Encoder::<Schema>::new(ROOT_LEN, TEMPLATE_TRADES)
+            .int(&1_700_000_000_000_u64.to_le_bytes())
+            .int(&best_bid.to_le_bytes())
+            .int(&TRADE_LEN.to_le_bytes())
+            .length(Schema::GROUP_COUNT, trades.len());
+
+        for (price, exponent, quantity) in trades {
+            encoder = encoder.int(&price.to_le_bytes()).int(&exponent.to_le_bytes()).int(&quantity.to_le_bytes());
+        }
+
+        encoder.length(Schema::VAR_DATA_LENGTH, symbol.len()).raw(symbol.as_bytes()).bytes
+    }
+
+    fn expected<Schema>() -> Trades<Schema> {
+        Trades {
+            time: 1_700_000_000_000,
+            best_bid: Some(dec!(64123.10)),
+            trades: vec![(dec!(64123.5), dec!(0.015)), (dec!(64130), dec!(2))],
+            symbol: "BTCUSDT".to_owned(),
+            schema: PhantomData,
+        }
+    }
+
+    const TRADES: [(i64, i8, i64); 2] = [(641235, -1, 1_500_000), (6413, 1, 200_000_000)];
+
+    #[test]
+    fn test_decode_little_endian() {
+        let bytes = encode::<LittleEndianSchema>(6412310, &TRADES, "BTCUSDT");
+        assert_eq!(decode::<Trades<LittleEndianSchema>>(&bytes).unwrap(), expected());
+    }
+
+    #[test]
+    fn test_decode_big_endian() {
+        let bytes = encode::<BigEndianSchema>(6412310, &TRADES, "BTCUSDT");
+        assert_eq!(&bytes[..2], &[0, ROOT_LEN as u8]);
+        assert_eq!(decode::<Trades<BigEndianSchema>>(&bytes).unwrap(), expected());
+    }
+
+    #[test]
+    fn test_decode_null_and_empty_group() {
+        let bytes = encode::<LittleEndianSchema>(i64::MIN, &[], "");
+        let trades = decode::<Trades<LittleEndianSchema>>(&bytes).unwrap();
+
+        assert_eq!(trades.best_bid, None);
+        assert!(trades.trades.is_empty());
+        assert_eq!(trades.symbol, "");
+    }
+
+    #[test]
+    fn test_decode_null_required_decimal() {
+        let bytes = encode::<LittleEndianSchema>(0, &[(i64::MIN, -1, 1)], "BTCUSDT");
+        assert_eq!(decode::<Trades<LittleEndianSchema>>(&bytes).unwrap_err(), NULL_MANTISSA);
+    }
+
+    #[test]
+    fn test_decode_skips_appended_root_fields() {
+        // A newer schema version appends a field to the root block, older decoders skip it
+        let mut bytes = encode::<LittleEndianSchema>(6412310, &TRADES, "BTCUSDT");
+        bytes[..2].copy_from_slice(&(ROOT_LEN + 4).to_le_bytes());
+        bytes.splice(8 + usize::from(ROOT_LEN)..8 + usize::from(ROOT_LEN), [0xAA; 4]);
+
+        assert_eq!(decode::<Trades<LittleEndianSchema>>(&bytes).unwrap(), expected());
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        let bytes = encode::<LittleEndianSchema>(6412310, &TRADES, "BTCUSDT");
+
+        assert!(matches!(
+            decode::<Trades<LittleEndianSchema>>(&bytes[..bytes.len() - 1]),
+            Err(SbeError::Truncated { len: 7, available: 6, .. })
+        ));
+        assert_eq!(
+            decode::<Trades<BigEndianSchema>>(&bytes).unwrap_err(),
+            SbeError::SchemaMismatch { expected: 2, received: 256 }
+        );
+
+        // A group count claiming far more entries than the message holds fails without allocating them
+        let mut corrupt = bytes.clone();
+        corrupt[8 + usize::from(ROOT_LEN) + 2..][..2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(decode::<Trades<LittleEndianSchema>>(&corrupt), Err(SbeError::Truncated { .. })));
+
+        let mut invalid_utf8 = bytes;
+        *invalid_utf8.last_mut().unwrap() = 0xFF;
+        assert!(matches!(decode::<Trades<LittleEndianSchema>>(&invalid_utf8), Err(SbeError::Utf8 { .. })));
+    }
+
+    #[test]
+    fn test_decimal_exponent_range() {
+        assert_eq!(decimal(1, 18).unwrap(), Some(dec!(1_000_000_000_000_000_000)));
+        assert_eq!(decimal(-7, -28).unwrap(), Some(Decimal::new(-7, 28)));
+        assert_eq!(decimal(i64::MIN, 0).unwrap(), None);
+
+        let invalid = |exponent: i8| SbeError::InvalidValue {
+            field: "decimal exponent",
+            value: i64::from(exponent),
+        };
+        assert_eq!(decimal(1, 19).unwrap_err(), invalid(19));
+        assert_eq!(decimal(1, i8::MAX).unwrap_err(), invalid(i8::MAX));
+        assert_eq!(decimal(i64::MAX, 18).unwrap_err(), invalid(18));
+        assert_eq!(decimal(1, -29).unwrap_err(), invalid(-29));
+        assert_eq!(decimal(1, i8::MIN).unwrap_err(), invalid(i8::MIN));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct TradeError(String);
+
+    impl From<Box<dyn std::error::Error>> for TradeError {
+        fn from(error: Box<dyn std::error::Error>) -> Self {
+            Self(error.to_string())
+        }
+    }
+
+    struct TradePrices;
+
+    impl Transformer for TradePrices {
+        type Error = TradeError;
+        type Input = Trades<LittleEndianSchema>;
+        type Output = Decimal;
+        type OutputIter = Vec<Result<Decimal, TradeError>>;
+
+        fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+            input.trades.into_iter().map(|(price, _)| Ok(price)).collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_binary_exchange_stream() {
+        let trades = encode::<LittleEndianSchema>(6412310, &TRADES, "BTCUSDT");
+        let unknown = Encoder::<LittleEndianSchema>::new(0, 99).bytes;
+        let frames = VecDeque::from([
+            WsMessage::text(r#"{"result":null,"id":1}"#),
+            WsMessage::binary(unknown),
+            WsMessage::binary(trades.clone()),
+            WsMessage::binary(trades[..10].to_vec()),
+        ]);
+
+        let stream = BinaryExchangeStream::<WebsocketSbeParser, _, _>::new(futures::stream::iter(frames.into_iter().map(Ok)), TradePrices);
+        let outputs = stream.collect::<Vec<Result<Decimal, TradeError>>>().await;
+
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[..2], [Ok(dec!(64123.5)), Ok(dec!(64130))]);
+        assert!(outputs[2].as_ref().unwrap_err().0.starts_with("truncated SBE message"));
+    }
+}
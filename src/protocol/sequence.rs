@@ -0,0 +1,346 @@
+use std::{fmt::Debug, hash::Hash};
+
+use fnv::FnvHashMap;
+use tracing::{debug, warn};
+
+use crate::protocol::transformer::Transformer;
+
+pub trait Sequenced {
+    type Key: Debug + Clone + Eq + Hash;
+
+    fn sequence_key(&self) -> Self::Key;
+
+    // Last sequence id contained in this message.
+    fn sequence(&self) -> u64;
+
+    // First sequence id contained in this message, for venues that batch a range of updates.
+    fn sequence_first(&self) -> u64 {
+        self.sequence()
+    }
+
+    fn is_snapshot(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceEvent<Key, T> {
+    Item(T),
+    GapDetected { key: Key, expected: u64, received: u64 },
+    // Too many deltas arrived before the resync, the buffered ones were discarded and a resync requested again.
+    BufferOverflow { key: Key, discarded: usize },
+    Resynced { key: Key, sequence: u64 },
+}
+
+// Deltas buffered per key while waiting on a resync snapshot.
+pub const RESYNC_BUFFER_MAX_DEFAULT: usize = 10_000;
+
+#[derive(Debug)]
+enum SequenceState<Input> {
+    Synced { sequence: u64 },
+    Resyncing { buffer: Vec<Input> },
+}
+
+#[derive(Debug)]
+pub struct SequencedTransformer<Inner, FnResync>
+where
+    Inner: Transformer,
+    Inner::Input: Sequenced,
+{
+    inner: Inner,
+    states: FnvHashMap<<Inner::Input as Sequenced>::Key, SequenceState<Inner::Input>>,
+    on_gap: FnResync,
+    buffer_max: usize,
+}
+
+impl<Inner, FnResync> Transformer for SequencedTransformer<Inner, FnResync>
+where
+    Inner: Transformer,
+    Inner::Input: Sequenced,
+    FnResync: FnMut(&<Inner::Input as Sequenced>::Key),
+{
+    type Error = Inner::Error;
+    type Input = Inner::Input;
+    type Output = SequenceEvent<<Inner::Input as Sequenced>::Key, Inner::Output>;
+    type OutputIter = Vec<Result<Self::Output, Self::Error>>;
+
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        let mut outputs = Vec::new();
+        let key = input.sequence_key();
+
+        if input.is_snapshot() {
+            let sequence = input.sequence();
+            let buffered = match self.states.insert(key.clone(), SequenceState::Synced { sequence }) {
+                Some(SequenceState::Resyncing { buffer }) => {
+                    debug!(?key, sequence, buffered = buffer.len(), "Resynced sequence from snapshot.");
+                    outputs.push(Ok(SequenceEvent::Resynced { key: key.clone(), sequence }));
+
+                    buffer
+                },
+                _ => Vec::new(),
+            };
+
+            self.apply(input, &mut outputs);
+            buffered.into_iter().for_each(|delta| self.process_delta(delta, &mut outputs));
+
+            return outputs;
+        }
+
+        self.process_delta(input, &mut outputs);
+
+        outputs
+    }
+}
+
+impl<Inner, FnResync> SequencedTransformer<Inner, FnResync>
+where
+    Inner: Transformer,
+    Inner::Input: Sequenced,
+    FnResync: FnMut(&<Inner::Input as Sequenced>::Key),
+{
+    pub fn new(inner: Inner, on_gap: FnResync) -> Self {
+        Self {
+            inner,
+            states: FnvHashMap::default(),
+            on_gap,
+            buffer_max: RESYNC_BUFFER_MAX_DEFAULT,
+        }
+    }
+
+    pub fn with_buffer_max(mut self, buffer_max: usize) -> Self {
+        self.buffer_max = buffer_max;
+        self
+    }
+
+    fn process_delta(&mut self, delta: Inner::Input, outputs: &mut Vec<Result<<Self as Transformer>::Output, Inner::Error>>) {
+        let key = delta.sequence_key();
+
+        let state = match self.states.get_mut(&key) {
+            Some(state) => state,
+            None => {
+                // The first delta seen for a key establishes the baseline
+                self.states.insert(key, SequenceState::Synced { sequence: delta.sequence() });
+                self.apply(delta, outputs);
+
+                return;
+            },
+        };
+
+        match state {
+            // Deltas a snapshot would cover are lost too, so a later gap triggers another resync
+            SequenceState::Resyncing { buffer } if buffer.len() >= self.buffer_max => {
+                let discarded = buffer.len();
+                warn!(?key, discarded, "Sequence resync buffer overflowed, discarding buffered deltas.");
+
+                buffer.clear();
+                buffer.push(delta);
+                outputs.push(Ok(SequenceEvent::BufferOverflow { key: key.clone(), discarded }));
+                (self.on_gap)(&key);
+            },
+            SequenceState::Resyncing { buffer } => buffer.push(delta),
+            SequenceState::Synced { sequence } if delta.sequence() <= *sequence => {
+                debug!(?key, sequence = delta.sequence(), "Dropping already applied sequence.");
+            },
+            // Saturating, as nothing follows a sequence of `u64::MAX` which the arm above drops
+            SequenceState::Synced { sequence } if delta.sequence_first() <= sequence.saturating_add(1) => {
+                *sequence = delta.sequence();
+                self.apply(delta, outputs);
+            },
+            SequenceState::Synced { sequence } => {
+                let expected = sequence.saturating_add(1);
+                let received = delta.sequence_first();
+                warn!(?key, expected, received, "Detected sequence gap, buffering until resynced.");
+
+                *state = SequenceState::Resyncing { buffer: vec![delta] };
+                outputs.push(Ok(SequenceEvent::GapDetected {
+                    key: key.clone(),
+                    expected,
+                    received,
+                }));
+                (self.on_gap)(&key);
+            },
+        }
+    }
+
+    fn apply(&mut self, input: Inner::Input, outputs: &mut Vec<Result<<Self as Transformer>::Output, Inner::Error>>) {
+        outputs.extend(self.inner.transform(input).into_iter().map(|result| result.map(SequenceEvent::Item)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, convert::Infallible};
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Update {
+        key: &'static str,
+        first: u64,
+        last: u64,
+        is_snapshot: bool,
+    }
+
+    impl Sequenced for Update {
+        type Key = &'static str;
+
+        fn sequence_key(&self) -> Self::Key {
+            self.key
+        }
+
+        fn sequence(&self) -> u64 {
+            self.last
+        }
+
+        fn sequence_first(&self) -> u64 {
+            self.first
+        }
+
+        fn is_snapshot(&self) -> bool {
+            self.is_snapshot
+        }
+    }
+
+    #[derive(Debug)]
+    struct Apply;
+
+    impl Transformer for Apply {
+        type Error = Infallible;
+        type Input = Update;
+        type Output = Update;
+        type OutputIter = Option<Result<Update, Infallible>>;
+
+        fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+            Some(Ok(input))
+        }
+    }
+
+    fn delta(key: &'static str, first: u64, last: u64) -> Update {
+        Update {
+            key,
+            first,
+            last,
+            is_snapshot: false,
+        }
+    }
+
+    fn snapshot(key: &'static str, sequence: u64) -> Update {
+        Update {
+            key,
+            first: sequence,
+            last: sequence,
+            is_snapshot: true,
+        }
+    }
+
+    fn transform<FnResync>(transformer: &mut SequencedTransformer<Apply, FnResync>, input: Update) -> Vec<SequenceEvent<&'static str, Update>>
+    where
+        FnResync: FnMut(&&'static str),
+    {
+        transformer.transform(input).into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_in_sequence_deltas_are_applied() {
+        let mut transformer = SequencedTransformer::new(Apply, |_: &&str| panic!("no gap expected"));
+
+        assert_eq!(transform(&mut transformer, delta("btc", 5, 5)), vec![SequenceEvent::Item(delta("btc", 5, 5))]);
+        assert_eq!(transform(&mut transformer, delta("btc", 6, 8)), vec![SequenceEvent::Item(delta("btc", 6, 8))]);
+
+        // Already applied, and a batch overlapping the last applied sequence
+        assert!(transform(&mut transformer, delta("btc", 7, 8)).is_empty());
+        assert_eq!(transform(&mut transformer, delta("btc", 8, 9)), vec![SequenceEvent::Item(delta("btc", 8, 9))]);
+    }
+
+    #[test]
+    fn test_gap_buffers_until_resynced() {
+        let gaps = RefCell::new(Vec::new());
+        let mut transformer = SequencedTransformer::new(Apply, |key: &&str| gaps.borrow_mut().push(*key));
+
+        transform(&mut transformer, delta("btc", 1, 1));
+        assert_eq!(
+            transform(&mut transformer, delta("btc", 4, 4)),
+            vec![SequenceEvent::GapDetected {
+                key: "btc",
+                expected: 2,
+                received: 4,
+            }]
+        );
+        assert_eq!(*gaps.borrow(), vec!["btc"]);
+
+        // Buffered while resyncing, other keys are unaffected
+        assert!(transform(&mut transformer, delta("btc", 5, 6)).is_empty());
+        assert_eq!(transform(&mut transformer, delta("eth", 1, 1)), vec![SequenceEvent::Item(delta("eth", 1, 1))]);
+
+        // Buffered deltas the snapshot already covers are dropped
+        assert_eq!(
+            transform(&mut transformer, snapshot("btc", 4)),
+            vec![
+                SequenceEvent::Resynced { key: "btc", sequence: 4 },
+                SequenceEvent::Item(snapshot("btc", 4)),
+                SequenceEvent::Item(delta("btc", 5, 6)),
+            ]
+        );
+        assert_eq!(transform(&mut transformer, delta("btc", 7, 7)), vec![SequenceEvent::Item(delta("btc", 7, 7))]);
+        assert_eq!(gaps.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_resync_replay_detects_further_gap() {
+        let gaps = RefCell::new(Vec::new());
+        let mut transformer = SequencedTransformer::new(Apply, |key: &&str| gaps.borrow_mut().push(*key));
+
+        transform(&mut transformer, delta("btc", 1, 1));
+        transform(&mut transformer, delta("btc", 3, 3));
+        transform(&mut transformer, delta("btc", 6, 6));
+
+        // The snapshot at 4 leaves 5 missing from the buffered deltas
+        assert_eq!(
+            transform(&mut transformer, snapshot("btc", 4)),
+            vec![
+                SequenceEvent::Resynced { key: "btc", sequence: 4 },
+                SequenceEvent::Item(snapshot("btc", 4)),
+                SequenceEvent::GapDetected {
+                    key: "btc",
+                    expected: 5,
+                    received: 6,
+                },
+            ]
+        );
+        assert_eq!(gaps.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_buffer_overflow_discards_and_resyncs() {
+        let gaps = RefCell::new(Vec::new());
+        let mut transformer = SequencedTransformer::new(Apply, |key: &&str| gaps.borrow_mut().push(*key)).with_buffer_max(2);
+
+        transform(&mut transformer, delta("btc", 1, 1));
+        transform(&mut transformer, delta("btc", 3, 3));
+        assert!(transform(&mut transformer, delta("btc", 4, 4)).is_empty());
+        assert_eq!(
+            transform(&mut transformer, delta("btc", 5, 5)),
+            vec![SequenceEvent::BufferOverflow { key: "btc", discarded: 2 }]
+        );
+        assert_eq!(*gaps.borrow(), vec!["btc", "btc"]);
+
+        // Only the delta past the overflow is replayed
+        assert_eq!(
+            transform(&mut transformer, snapshot("btc", 4)),
+            vec![
+                SequenceEvent::Resynced { key: "btc", sequence: 4 },
+                SequenceEvent::Item(snapshot("btc", 4)),
+                SequenceEvent::Item(delta("btc", 5, 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_sequence_does_not_overflow() {
+        let mut transformer = SequencedTransformer::new(Apply, |_: &&str| panic!("no gap expected"));
+
+        transform(&mut transformer, delta("btc", u64::MAX, u64::MAX));
+        assert!(transform(&mut transformer, delta("btc", u64::MAX, u64::MAX)).is_empty());
+        assert!(transform(&mut transformer, delta("btc", 1, 1)).is_empty());
+    }
+}
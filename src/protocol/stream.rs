@@ -2,16 +2,26 @@ use std::{
     collections::VecDeque,
     marker::PhantomData,
     pin::Pin,
-    task::{Context, Poll},
+    task::{Context, Poll, ready},
 };
 
+use chrono::{DateTime, Days, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeDelta, TimeZone, Utc};
+use derive_more::Constructor;
 use futures::{Stream, StreamExt};
 use pin_project::pin_project;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
 use tracing::{error, info, warn};
 
-use crate::protocol::{
-    parser::{ProtocolParser, WebsocketParser},
-    transformer::Transformer,
+use crate::{
+    protocol::{
+        latency::{LatencyStamps, Stamped},
+        parser::{BinaryParser, ProtocolParser, WebsocketBorrowedParser, WebsocketParser},
+        sbe::SbeDecode,
+        transformer::{BorrowedTransformer, Transformer},
+    },
+    transport::channel::{AsyncTx, NonBlockingTx},
 };
 
 pub type BarterExchangeStream<Protocol, InnerStream, StreamTransformer> = barter_integration::stream::ExchangeStream<Protocol, InnerStream, StreamTransformer>;
@@ -27,30 +37,66 @@ where
         InnerSt: Stream,
         InitErr: std::fmt::Debug,
     {
-        self.enumerate()
-            .scan(ReconnectionState::from(policy), move |state, (attempt, result)| match result {
-                Ok(stream) => {
-                    info!(attempt, ?stream_key, "Successfully initialized Stream.");
-                    state.reset_backoff();
-
-                    futures::future::Either::Left(std::future::ready(Some(Ok(stream))))
-                },
-                Err(error) => {
-                    warn!(attempt, ?stream_key, ?error, "Failed to re-initialize Stream.");
-                    let sleep_fut = state.generate_sleep_future();
-                    state.multiply_backoff();
-
-                    // TODO:
-                    // Would it still work without pin?
-                    futures::future::Either::Right(Box::pin(async move {
-                        sleep_fut.await;
-                        Some(Err(error))
-                    }))
-                },
-            })
+        // Without limits reconnection never gives up, so no terminal error is filtered out
+        self.with_reconnect_policy(policy.into(), stream_key, |_| {})
             .filter_map(|result| std::future::ready(result.ok()))
     }
 
+    // Ends with a `ReconnectionError` as the last item once the attempts or backoff budget of the
+    // policy run out, `on_attempt` is called for every (re)initialization attempt.
+    fn with_reconnect_policy<InnerSt, InitErr, FnOnAttempt>(
+        self,
+        policy: ReconnectionPolicy,
+        stream_key: u64,
+        on_attempt: FnOnAttempt,
+    ) -> impl Stream<Item = Result<InnerSt, ReconnectionError<InitErr>>>
+    where
+        Self: Stream<Item = Result<InnerSt, InitErr>>,
+        InnerSt: Stream,
+        InitErr: std::fmt::Debug,
+        FnOnAttempt: FnMut(ReconnectionAttempt<InitErr>),
+    {
+        // Unfolded rather than scanned, so giving up does not poll for yet another initialization
+        let init = (Box::pin(self), ReconnectionState::from(policy), on_attempt);
+
+        futures::stream::unfold(Some(init), move |init| async move {
+            let (mut streams, mut state, mut on_attempt) = init?;
+
+            loop {
+                let attempt = state.attempts_failed + 1;
+                match streams.next().await? {
+                    Ok(stream) => {
+                        info!(attempt, ?stream_key, "Successfully initialized Stream.");
+                        state.reset_backoff();
+                        on_attempt(ReconnectionAttempt::Connected { attempt });
+
+                        return Some((Ok(stream), Some((streams, state, on_attempt))));
+                    },
+                    Err(error) => match state.next_backoff() {
+                        Ok(backoff) => {
+                            warn!(attempt, ?stream_key, ?error, ?backoff, "Failed to re-initialize Stream.");
+                            on_attempt(ReconnectionAttempt::Failed { attempt, error, backoff });
+
+                            tokio::time::sleep(backoff).await;
+                        },
+                        Err(limit) => {
+                            error!(
+                                attempt,
+                                ?stream_key,
+                                ?error,
+                                ?limit,
+                                "Failed to re-initialize Stream, giving up on reconnection."
+                            );
+                            on_attempt(ReconnectionAttempt::Terminated { attempt, limit });
+
+                            return Some((Err(ReconnectionError { attempt, limit, error }), None));
+                        },
+                    },
+                }
+            }
+        })
+    }
+
     fn with_termination_on_error<InnerSt, T, E, FnIsTerminal>(
         self,
         is_terminal: FnIsTerminal,
@@ -76,6 +122,37 @@ where
         })
     }
 
+    fn with_stale_timeout<InnerSt, T, E, FnOnStale>(
+        self,
+        threshold: std::time::Duration,
+        on_stale: FnOnStale,
+        stream_key: u64,
+    ) -> impl Stream<Item = impl Stream<Item = Result<T, E>>>
+    where
+        Self: Stream<Item = InnerSt>,
+        InnerSt: Stream<Item = Result<T, E>>,
+        FnOnStale: Copy + Fn(StaleFeed<()>) -> E,
+    {
+        self.with_stale_timeout_by_key(threshold, |_: &T| None::<()>, on_stale, stream_key)
+    }
+
+    fn with_stale_timeout_by_key<InnerSt, T, E, Key, FnKey, FnOnStale>(
+        self,
+        threshold: std::time::Duration,
+        key: FnKey,
+        on_stale: FnOnStale,
+        stream_key: u64,
+    ) -> impl Stream<Item = impl Stream<Item = Result<T, E>>>
+    where
+        Self: Stream<Item = InnerSt>,
+        InnerSt: Stream<Item = Result<T, E>>,
+        Key: std::fmt::Debug + Clone + Eq + std::hash::Hash,
+        FnKey: Copy + Fn(&T) -> Option<Key>,
+        FnOnStale: Copy + Fn(StaleFeed<Key>) -> E,
+    {
+        self.map(move |stream| StaleWatchdog::new(stream, threshold, key, on_stale, stream_key))
+    }
+
     fn with_reconnection_events<InnerSt, Origin>(self, origin: Origin) -> impl Stream<Item = StreamEvent<Origin, InnerSt::Item>>
     where
         Self: Stream<Item = InnerSt>,
@@ -108,19 +185,82 @@ where
         })
     }
 
-    // TOOD:
-    // Optional cron based reconnection, used for example to reconnect upon daily exchange maintenance windows.
+    fn with_scheduled_reconnect<InnerSt, Tz>(self, schedule: ReconnectionSchedule<Tz>, stream_key: u64) -> impl Stream<Item = impl Stream<Item = InnerSt::Item>>
+    where
+        Self: Stream<Item = InnerSt>,
+        InnerSt: Stream,
+        Tz: TimeZone,
+    {
+        self.with_scheduled_reconnect_clock(schedule, Utc::now, stream_key)
+    }
 
-    // fn forward_to<Transmitter>(self, tx: Transmitter) -> impl Future<Output = ()> + Send
-    // where
-    //     Self: Stream + Sized + Send,
-    //     Self::Item: Into<Transmitter::Item>,
-    //     Transmitter: Tx + Send + 'static,
-    // {
-    //     tokio_stream::StreamExt::map_while(self, move |event| tx.send(event.into()).ok()).collect()
-    // }
+    // `clock` is read once per (re)connection to find the next scheduled time.
+    fn with_scheduled_reconnect_clock<InnerSt, Tz, FnNow>(
+        self,
+        schedule: ReconnectionSchedule<Tz>,
+        clock: FnNow,
+        stream_key: u64,
+    ) -> impl Stream<Item = impl Stream<Item = InnerSt::Item>>
+    where
+        Self: Stream<Item = InnerSt>,
+        InnerSt: Stream,
+        Tz: TimeZone,
+        FnNow: Fn() -> DateTime<Utc>,
+    {
+        self.map(move |stream| {
+            let now = clock();
+            let reconnect_at = schedule.next_after(now);
+            info!(?stream_key, ?reconnect_at, "Scheduled Stream reconnection.");
+
+            let teardown_fut = async move {
+                match reconnect_at {
+                    Some(reconnect_at) => {
+                        let sleep_duration = (reconnect_at - now).to_std().unwrap_or_default();
+                        tokio::time::sleep(sleep_duration).await;
+                        info!(?stream_key, %reconnect_at, "Tearing down Stream for scheduled reconnection.");
+                    },
+                    None => std::future::pending().await,
+                }
+            };
+
+            stream.take_until(teardown_fut)
+        })
+    }
+
+    // Only for transmitters that never block, as the send happens on the async task driving the stream.
+    fn forward_to<Transmitter, T>(self, tx: Transmitter) -> impl Future<Output = ForwardTermination<Transmitter::SendError>>
+    where
+        Self: Stream,
+        Self::Item: Into<T>,
+        Transmitter: NonBlockingTx<T>,
+    {
+        self.forward_to_async(ReadyTx { tx, item: PhantomData })
+    }
+
+    fn forward_to_async<Transmitter, T>(self, tx: Transmitter) -> impl Future<Output = ForwardTermination<Transmitter::SendError>>
+    where
+        Self: Stream,
+        Self::Item: Into<T>,
+        Transmitter: AsyncTx<T>,
+    {
+        async move {
+            let mut stream = std::pin::pin!(self);
+            while let Some(event) = stream.next().await {
+                if let Err(error) = tx.send(event.into()).await {
+                    warn!(?error, "Failed to forward Stream event, receiver is gone.");
+
+                    return ForwardTermination::SendFailed(error);
+                }
+            }
+
+            info!("Forwarded Stream ended.");
+            ForwardTermination::StreamEnded
+        }
+    }
 }
 
+impl<St> RecoverableStream for St where St: Stream {}
+
 pub async fn init_recoverable_stream<FnInit, InnerSt, InitErr, InitFut>(
     init_inner_stream: FnInit,
 ) -> Result<impl Stream<Item = Result<InnerSt, InitErr>>, InitErr>
@@ -142,22 +282,278 @@ pub enum StreamEvent<Origin, T> {
 }
 
 #[derive(Debug)]
+pub enum ForwardTermination<SendError> {
+    StreamEnded,
+    SendFailed(SendError),
+}
+
+// Adapts a `NonBlockingTx` to `AsyncTx`, its send completes immediately.
+struct ReadyTx<Tx, T> {
+    tx: Tx,
+    item: PhantomData<fn(T)>,
+}
+
+impl<Tx, T> AsyncTx<T> for ReadyTx<Tx, T>
+where
+    Tx: NonBlockingTx<T>,
+{
+    type SendError = Tx::SendError;
+    type SendFuture<'a>
+        = std::future::Ready<Result<(), Self::SendError>>
+    where
+        Self: 'a,
+        T: 'a;
+
+    fn send(&self, item: T) -> Self::SendFuture<'_> {
+        std::future::ready(self.tx.send(item))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StaleFeed<Key> {
+    Stream { silent_for: std::time::Duration },
+    Instrument { key: Key, silent_for: std::time::Duration },
+}
+
+// Fires once per silence, the stream and each key are only watched again after their next item.
+#[pin_project]
+struct StaleWatchdog<InnerSt, E, Key, FnKey, FnOnStale> {
+    #[pin]
+    stream: InnerSt,
+    #[pin]
+    sleep: tokio::time::Sleep,
+    // Disarmed while nothing is watched, until the next item
+    is_armed: bool,
+    threshold: std::time::Duration,
+    // `None` once reported stale
+    time_last_item: Option<tokio::time::Instant>,
+    // Stale keys are evicted
+    time_last_item_by_key: fnv::FnvHashMap<Key, tokio::time::Instant>,
+    pending: VecDeque<E>,
+    key: FnKey,
+    on_stale: FnOnStale,
+    stream_key: u64,
+}
+
+impl<InnerSt, E, Key, FnKey, FnOnStale> StaleWatchdog<InnerSt, E, Key, FnKey, FnOnStale> {
+    fn new(stream: InnerSt, threshold: std::time::Duration, key: FnKey, on_stale: FnOnStale, stream_key: u64) -> Self {
+        let now = tokio::time::Instant::now();
+
+        Self {
+            stream,
+            sleep: tokio::time::sleep_until(now + threshold),
+            is_armed: true,
+            threshold,
+            time_last_item: Some(now),
+            time_last_item_by_key: fnv::FnvHashMap::default(),
+            pending: VecDeque::new(),
+            key,
+            on_stale,
+            stream_key,
+        }
+    }
+}
+
+impl<InnerSt, T, E, Key, FnKey, FnOnStale> Stream for StaleWatchdog<InnerSt, E, Key, FnKey, FnOnStale>
+where
+    InnerSt: Stream<Item = Result<T, E>>,
+    Key: std::fmt::Debug + Clone + Eq + std::hash::Hash,
+    FnKey: Fn(&T) -> Option<Key>,
+    FnOnStale: Fn(StaleFeed<Key>) -> E,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(error) = this.pending.pop_front() {
+                return Poll::Ready(Some(Err(error)));
+            }
+
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(result)) => {
+                    let now = tokio::time::Instant::now();
+                    *this.time_last_item = Some(now);
+
+                    if let Some(key) = result.as_ref().ok().and_then(|item| (this.key)(item)) {
+                        this.time_last_item_by_key.insert(key, now);
+                    }
+
+                    if !*this.is_armed {
+                        this.sleep.as_mut().reset(now + *this.threshold);
+                        *this.is_armed = true;
+                    }
+
+                    return Poll::Ready(Some(result));
+                },
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => {},
+            }
+
+            // Items only move deadlines forward, so the timer is re-armed lazily once it fires
+            if !*this.is_armed || this.sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            let now = tokio::time::Instant::now();
+            if let Some(time_last_item) = *this.time_last_item {
+                let silent_for = now - time_last_item;
+                if silent_for >= *this.threshold {
+                    warn!(stream_key = *this.stream_key, ?silent_for, "Stream has gone stale.");
+                    this.pending.push_back((this.on_stale)(StaleFeed::Stream { silent_for }));
+                    *this.time_last_item = None;
+                }
+            }
+
+            this.time_last_item_by_key.retain(|key, time_last_item| {
+                let silent_for = now - *time_last_item;
+                if silent_for < *this.threshold {
+                    return true;
+                }
+
+                warn!(stream_key = *this.stream_key, ?key, ?silent_for, "Stream has gone stale for instrument.");
+                this.pending.push_back((this.on_stale)(StaleFeed::Instrument { key: key.clone(), silent_for }));
+                false
+            });
+
+            let time_oldest = this.time_last_item_by_key.values().copied().chain(*this.time_last_item).min();
+            match time_oldest {
+                Some(time_oldest) => this.sleep.as_mut().reset(time_oldest + *this.threshold),
+                None => *this.is_armed = false,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ReconnectionBackoffPolicy {
     pub backoff_ms_initial: u64,
     pub backoff_multiplier: u8,
     pub backoff_ms_max: u64,
 }
 
+#[derive(Debug, Clone)]
+pub struct ReconnectionPolicy {
+    pub backoff_ms_initial: u64,
+    pub backoff_multiplier: f64,
+    pub backoff_ms_max: u64,
+    pub jitter: BackoffJitter,
+    // Limits apply to consecutive failures, both reset once the Stream is re-initialized.
+    pub attempts_max: Option<u32>,
+    pub backoff_ms_budget: Option<u64>,
+}
+
+impl From<ReconnectionBackoffPolicy> for ReconnectionPolicy {
+    fn from(policy: ReconnectionBackoffPolicy) -> Self {
+        Self {
+            backoff_ms_initial: policy.backoff_ms_initial,
+            backoff_multiplier: f64::from(policy.backoff_multiplier),
+            backoff_ms_max: policy.backoff_ms_max,
+            jitter: BackoffJitter::None,
+            attempts_max: None,
+            backoff_ms_budget: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackoffJitter {
+    #[default]
+    None,
+    // Sleep uniformly in `[0, backoff]`.
+    Full,
+    // Sleep uniformly in `[initial, previous sleep * multiplier]`, capped at the max.
+    Decorrelated,
+}
+
+#[derive(Debug)]
+pub enum ReconnectionAttempt<InitErr> {
+    Connected {
+        attempt: u32,
+    },
+    Failed {
+        attempt: u32,
+        error: InitErr,
+        backoff: std::time::Duration,
+    },
+    // The `ReconnectionError` itself is the last item of the Stream.
+    Terminated {
+        attempt: u32,
+        limit: ReconnectionLimit,
+    },
+}
+
+#[derive(Debug, Error)]
+#[error("Stream reconnection gave up after attempt {attempt} ({limit:?}): {error:?}")]
+pub struct ReconnectionError<InitErr> {
+    pub attempt: u32,
+    pub limit: ReconnectionLimit,
+    pub error: InitErr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconnectionLimit {
+    AttemptsExhausted,
+    BudgetExhausted,
+}
+
+#[derive(Debug, Clone, Constructor)]
+pub struct ReconnectionSchedule<Tz>
+where
+    Tz: TimeZone,
+{
+    pub timezone: Tz,
+    pub times: Vec<NaiveTime>,
+}
+
+impl<Tz> ReconnectionSchedule<Tz>
+where
+    Tz: TimeZone,
+{
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.timezone).date_naive();
+
+        // Today's times may all have passed already
+        (0..=1)
+            .filter_map(|days| today.checked_add_days(Days::new(days)))
+            .flat_map(|date| self.times.iter().map(move |time| date.and_time(*time)))
+            .filter_map(|local| self.resolve(local))
+            .filter(|time| *time > now)
+            .min()
+    }
+
+    fn resolve(&self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self.timezone.from_local_datetime(&local) {
+            LocalResult::Single(time) => Some(time.with_timezone(&Utc)),
+            // Repeated by a DST fall back, only the first occurrence counts
+            LocalResult::Ambiguous(earliest, _) => Some(earliest.with_timezone(&Utc)),
+            // Skipped by a DST spring forward, rolled forward by the length of the gap
+            LocalResult::None => {
+                let before_gap = self.timezone.from_local_datetime(&(local - TimeDelta::days(1))).earliest()?;
+                let time = before_gap.offset().fix().from_local_datetime(&local).single()?;
+                Some(time.with_timezone(&Utc))
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 struct ReconnectionState {
-    policy: ReconnectionBackoffPolicy,
+    policy: ReconnectionPolicy,
     backoff_ms_current: u64,
+    backoff_ms_previous: u64,
+    backoff_ms_spent: u64,
+    attempts_failed: u32,
 }
 
-impl From<ReconnectionBackoffPolicy> for ReconnectionState {
-    fn from(policy: ReconnectionBackoffPolicy) -> Self {
+impl From<ReconnectionPolicy> for ReconnectionState {
+    fn from(policy: ReconnectionPolicy) -> Self {
         Self {
             backoff_ms_current: policy.backoff_ms_initial,
+            backoff_ms_previous: policy.backoff_ms_initial,
+            backoff_ms_spent: 0,
+            attempts_failed: 0,
             policy,
         }
     }
@@ -166,19 +562,44 @@ impl From<ReconnectionBackoffPolicy> for ReconnectionState {
 impl ReconnectionState {
     fn reset_backoff(&mut self) {
         self.backoff_ms_current = self.policy.backoff_ms_initial;
+        self.backoff_ms_previous = self.policy.backoff_ms_initial;
+        self.backoff_ms_spent = 0;
+        self.attempts_failed = 0;
     }
 
     fn multiply_backoff(&mut self) {
-        let next = self.backoff_ms_current * self.policy.backoff_multiplier as u64;
+        let next = (self.backoff_ms_current as f64 * self.policy.backoff_multiplier) as u64;
         let next_capped = std::cmp::min(next, self.policy.backoff_ms_max);
 
         self.backoff_ms_current = next_capped;
     }
 
-    fn generate_sleep_future(&self) -> tokio::time::Sleep {
-        let sleep_duration = std::time::Duration::from_millis(self.backoff_ms_current);
+    fn next_backoff(&mut self) -> Result<std::time::Duration, ReconnectionLimit> {
+        self.attempts_failed += 1;
+        if self.policy.attempts_max.is_some_and(|attempts_max| self.attempts_failed >= attempts_max) {
+            return Err(ReconnectionLimit::AttemptsExhausted);
+        }
+
+        let backoff_ms = match self.policy.jitter {
+            BackoffJitter::None => self.backoff_ms_current,
+            BackoffJitter::Full => rand::rng().random_range(0..=self.backoff_ms_current),
+            BackoffJitter::Decorrelated => {
+                let upper = (self.backoff_ms_previous as f64 * self.policy.backoff_multiplier) as u64;
+                let upper = upper.max(self.policy.backoff_ms_initial);
+
+                std::cmp::min(rand::rng().random_range(self.policy.backoff_ms_initial..=upper), self.policy.backoff_ms_max)
+            },
+        };
+
+        if self.policy.backoff_ms_budget.is_some_and(|budget| self.backoff_ms_spent + backoff_ms > budget) {
+            return Err(ReconnectionLimit::BudgetExhausted);
+        }
+
+        self.backoff_ms_spent += backoff_ms;
+        self.backoff_ms_previous = backoff_ms;
+        self.multiply_backoff();
 
-        tokio::time::sleep(sleep_duration)
+        Ok(std::time::Duration::from_millis(backoff_ms))
     }
 }
 
@@ -202,46 +623,821 @@ where
     Protocol: WebsocketParser,
     StreamRaw: Stream<Item = Result<Protocol::Message, Protocol::Error>> + Unpin,
     StreamTransformer: Transformer,
+    StreamTransformer::Input: DeserializeOwned,
     StreamTransformer::Error: From<Box<dyn std::error::Error>>,
 {
     type Item = Result<StreamTransformer::Output, StreamTransformer::Error>;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        loop {
-            // Flush Self::Item buffer if it is not currently empty
-            if let Some(output) = self.buffer.pop_front() {
-                return Poll::Ready(Some(output));
-            }
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let transformer = this.transformer;
 
-            // Poll inner `Stream` for next the next input protocol message
-            let input = match self.as_mut().project().stream.poll_next(cx) {
-                Poll::Ready(Some(input)) => input,
-                Poll::Ready(None) => return Poll::Ready(None),
-                Poll::Pending => return Poll::Pending,
-            };
+        poll_exchange_stream(this.stream, this.buffer, cx, |input, buffer| {
+            let parsed = Protocol::parse::<StreamTransformer::Input>(input);
+            buffer_transformed(parsed, |exchange_message| transformer.transform(exchange_message), buffer)
+        })
+    }
+}
+
+// Poll loop shared by every exchange stream. Buffered outputs are yielded first, otherwise the next
+// raw message is handed to `process`, which parses & transforms it into `buffer`.
+fn poll_exchange_stream<StreamRaw, Item>(
+    mut stream: Pin<&mut StreamRaw>,
+    buffer: &mut VecDeque<Item>,
+    cx: &mut Context<'_>,
+    mut process: impl FnMut(StreamRaw::Item, &mut VecDeque<Item>),
+) -> Poll<Option<Item>>
+where
+    StreamRaw: Stream,
+{
+    loop {
+        if let Some(output) = buffer.pop_front() {
+            return Poll::Ready(Some(output));
+        }
+
+        match ready!(stream.as_mut().poll_next(cx)) {
+            Some(input) => process(input, buffer),
+            None => return Poll::Ready(None),
+        }
+    }
+}
+
+// Buffers the outputs of transforming a parsed message, or the parse error. Messages the parser
+// deems safe-to-skip (`None`) buffer nothing.
+fn buffer_transformed<Message, Output, Error, Outputs>(
+    parsed: Option<Result<Message, Box<dyn std::error::Error>>>,
+    transform: impl FnOnce(Message) -> Outputs,
+    buffer: &mut VecDeque<Result<Output, Error>>,
+) where
+    Outputs: IntoIterator<Item = Result<Output, Error>>,
+    Error: From<Box<dyn std::error::Error>>,
+{
+    match parsed {
+        Some(Ok(exchange_message)) => buffer.extend(transform(exchange_message)),
+        Some(Err(error)) => buffer.push_back(Err(error.into())),
+        None => {},
+    }
+}
 
-            // Parse input protocol message into `ExchangeMessage`
-            let exchange_message = match Protocol::parse::<StreamTransformer::Input>(input) {
-                // `StreamParser` successfully deserialised `ExchangeMessage`
-                Some(Ok(exchange_message)) => exchange_message,
+impl<Protocol, StreamRaw, StTransformer> ExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: ProtocolParser,
+    StreamRaw: Stream,
+    StTransformer: Transformer,
+{
+    // Outputs already buffered were read before stamping began, so they are stamped as of now.
+    pub fn with_latency_stamps(
+        self,
+        time_exchange: fn(&StTransformer::Output) -> Option<DateTime<Utc>>,
+    ) -> StampedExchangeStream<Protocol, StreamRaw, StTransformer> {
+        let now = Utc::now();
+        let buffer = self
+            .buffer
+            .into_iter()
+            .map(|output| output.map(|value| stamp(value, time_exchange, now, now, now)))
+            .collect();
 
-                // If `StreamParser` returns an Err pass it downstream
-                Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+        StampedExchangeStream {
+            stream: self.stream,
+            transformer: self.transformer,
+            buffer,
+            time_exchange,
+            protocol_marker: PhantomData,
+        }
+    }
+}
+
+// `ExchangeStream` that stamps every output with when its message was read, parsed and transformed,
+// alongside the exchange's own event time extracted by `time_exchange`.
+#[derive(Debug)]
+#[pin_project]
+pub struct StampedExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: ProtocolParser,
+    StreamRaw: Stream,
+    StTransformer: Transformer,
+{
+    #[pin]
+    pub stream: StreamRaw,
+    pub transformer: StTransformer,
+    pub buffer: VecDeque<Result<Stamped<StTransformer::Output>, StTransformer::Error>>,
+    pub time_exchange: fn(&StTransformer::Output) -> Option<DateTime<Utc>>,
+    pub protocol_marker: PhantomData<Protocol>,
+}
 
-                // If `StreamParser` returns None it's a safe-to-skip message
-                None => continue,
+impl<Protocol, StreamRaw, StTransformer> Stream for StampedExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: WebsocketParser,
+    StreamRaw: Stream<Item = Result<Protocol::Message, Protocol::Error>>,
+    StTransformer: Transformer,
+    StTransformer::Input: DeserializeOwned,
+    StTransformer::Error: From<Box<dyn std::error::Error>>,
+{
+    type Item = Result<Stamped<StTransformer::Output>, StTransformer::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let (transformer, time_exchange) = (this.transformer, *this.time_exchange);
+
+        poll_exchange_stream(this.stream, this.buffer, cx, |input, buffer| {
+            let time_received = Utc::now();
+            let parsed = Protocol::parse::<StTransformer::Input>(input);
+            let time_parsed = Utc::now();
+
+            buffer_transformed(
+                parsed,
+                |exchange_message| {
+                    let outputs = transformer.transform(exchange_message);
+                    let time_transformed = Utc::now();
+
+                    outputs
+                        .into_iter()
+                        .map(move |output| output.map(|value| stamp(value, time_exchange, time_received, time_parsed, time_transformed)))
+                },
+                buffer,
+            )
+        })
+    }
+}
+
+fn stamp<Output>(
+    value: Output,
+    time_exchange: fn(&Output) -> Option<DateTime<Utc>>,
+    time_received: DateTime<Utc>,
+    time_parsed: DateTime<Utc>,
+    time_transformed: DateTime<Utc>,
+) -> Stamped<Output> {
+    Stamped {
+        stamps: LatencyStamps {
+            time_exchange: time_exchange(&value),
+            time_received,
+            time_parsed,
+            time_transformed,
+        },
+        value,
+    }
+}
+
+#[derive(Debug)]
+#[pin_project]
+pub struct BorrowedExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: WebsocketBorrowedParser,
+    StreamRaw: Stream,
+    StTransformer: BorrowedTransformer,
+{
+    #[pin]
+    pub stream: StreamRaw,
+    pub transformer: StTransformer,
+    pub buffer: VecDeque<Result<StTransformer::Output, StTransformer::Error>>,
+    pub parse_buffer: Protocol::Buffer,
+    pub protocol_marker: PhantomData<Protocol>,
+}
+
+impl<Protocol, StreamRaw, StTransformer> BorrowedExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: WebsocketBorrowedParser,
+    StreamRaw: Stream,
+    StTransformer: BorrowedTransformer,
+{
+    pub fn new(stream: StreamRaw, transformer: StTransformer) -> Self {
+        Self {
+            stream,
+            transformer,
+            buffer: VecDeque::new(),
+            parse_buffer: Protocol::Buffer::default(),
+            protocol_marker: PhantomData,
+        }
+    }
+}
+
+impl<Protocol, StreamRaw, StTransformer> Stream for BorrowedExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: WebsocketBorrowedParser,
+    StreamRaw: Stream<Item = Result<Protocol::Message, Protocol::Error>>,
+    StTransformer: BorrowedTransformer,
+    StTransformer::Error: From<Box<dyn std::error::Error>>,
+{
+    type Item = Result<StTransformer::Output, StTransformer::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let (transformer, parse_buffer) = (this.transformer, this.parse_buffer);
+
+        // The message borrows from `parse_buffer`, so it is transformed before the next message is parsed
+        poll_exchange_stream(this.stream, this.buffer, cx, |input, buffer| {
+            let parsed = Protocol::parse_borrowed::<StTransformer::Input<'_>>(input, parse_buffer);
+            buffer_transformed(parsed, |exchange_message| transformer.transform(exchange_message), buffer)
+        })
+    }
+}
+
+#[derive(Debug)]
+#[pin_project]
+pub struct BinaryExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: BinaryParser,
+    StreamRaw: Stream,
+    StTransformer: Transformer,
+{
+    #[pin]
+    pub stream: StreamRaw,
+    pub transformer: StTransformer,
+    pub buffer: VecDeque<Result<StTransformer::Output, StTransformer::Error>>,
+    pub protocol_marker: PhantomData<Protocol>,
+}
+
+impl<Protocol, StreamRaw, StTransformer> BinaryExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: BinaryParser,
+    StreamRaw: Stream,
+    StTransformer: Transformer,
+{
+    pub fn new(stream: StreamRaw, transformer: StTransformer) -> Self {
+        Self {
+            stream,
+            transformer,
+            buffer: VecDeque::new(),
+            protocol_marker: PhantomData,
+        }
+    }
+}
+
+impl<Protocol, StreamRaw, StTransformer> Stream for BinaryExchangeStream<Protocol, StreamRaw, StTransformer>
+where
+    Protocol: BinaryParser,
+    StreamRaw: Stream<Item = Result<Protocol::Message, Protocol::Error>>,
+    StTransformer: Transformer,
+    StTransformer::Input: SbeDecode,
+    StTransformer::Error: From<Box<dyn std::error::Error>>,
+{
+    type Item = Result<StTransformer::Output, StTransformer::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let transformer = this.transformer;
+
+        poll_exchange_stream(this.stream, this.buffer, cx, |input, buffer| {
+            let parsed = Protocol::parse::<StTransformer::Input>(input);
+            buffer_transformed(parsed, |exchange_message| transformer.transform(exchange_message), buffer)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use chrono::{FixedOffset, NaiveDate};
+    use parking_lot::Mutex;
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::{
+        protocol::websocket::{WebsocketJsonParser, WsError, WsMessage},
+        transport::channel::AsyncRx,
+    };
+
+    type Inner = futures::stream::Iter<std::vec::IntoIter<u32>>;
+
+    fn policy(multiplier: f64) -> ReconnectionPolicy {
+        ReconnectionPolicy {
+            backoff_ms_initial: 100,
+            backoff_multiplier: multiplier,
+            backoff_ms_max: 1000,
+            jitter: BackoffJitter::None,
+            attempts_max: None,
+            backoff_ms_budget: None,
+        }
+    }
+
+    // Initializations following `outcomes`, then failing forever, counting every attempt.
+    fn inits(outcomes: Vec<Result<u32, &'static str>>, attempts: Arc<AtomicUsize>) -> impl Stream<Item = Result<Inner, &'static str>> {
+        let mut outcomes = outcomes.into_iter();
+        futures::stream::repeat_with(move || {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            outcomes.next().unwrap_or(Err("refused")).map(|item| futures::stream::iter(vec![item]))
+        })
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Attempt {
+        Connected(u32),
+        Failed(u32, u64),
+        Terminated(u32, ReconnectionLimit),
+    }
+
+    fn record(attempts: &Arc<Mutex<Vec<Attempt>>>) -> impl FnMut(ReconnectionAttempt<&'static str>) + use<> {
+        let attempts = Arc::clone(attempts);
+        move |attempt| {
+            attempts.lock().push(match attempt {
+                ReconnectionAttempt::Connected { attempt } => Attempt::Connected(attempt),
+                ReconnectionAttempt::Failed { attempt, backoff, .. } => Attempt::Failed(attempt, backoff.as_millis() as u64),
+                ReconnectionAttempt::Terminated { attempt, limit } => Attempt::Terminated(attempt, limit),
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_policy_fractional_backoff_capped() {
+        let events = Arc::default();
+        let outcomes = vec![
+            Err("refused"),
+            Err("refused"),
+            Err("refused"),
+            Err("refused"),
+            Err("refused"),
+            Err("refused"),
+            Ok(1),
+        ];
+        let start = Instant::now();
+
+        let mut streams = std::pin::pin!(inits(outcomes, Arc::default()).with_reconnect_policy(policy(2.5), 0, record(&events)));
+        let stream = streams.next().await.unwrap().unwrap();
+
+        assert_eq!(stream.collect::<Vec<_>>().await, vec![1]);
+        assert_eq!(
+            *events.lock(),
+            vec![
+                Attempt::Failed(1, 100),
+                Attempt::Failed(2, 250),
+                Attempt::Failed(3, 625),
+                Attempt::Failed(4, 1000),
+                Attempt::Failed(5, 1000),
+                Attempt::Failed(6, 1000),
+                Attempt::Connected(7),
+            ]
+        );
+        assert_eq!(start.elapsed(), Duration::from_millis(3975));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_policy_resets_after_connecting() {
+        let events = Arc::default();
+        let outcomes = vec![Err("refused"), Err("refused"), Ok(1), Err("refused"), Ok(2)];
+
+        let streams = inits(outcomes, Arc::default()).with_reconnect_policy(policy(2.0), 0, record(&events));
+        let items = streams.take(2).map(Result::unwrap).flatten().collect::<Vec<_>>().await;
+
+        assert_eq!(items, vec![1, 2]);
+        assert_eq!(
+            *events.lock(),
+            vec![
+                Attempt::Failed(1, 100),
+                Attempt::Failed(2, 200),
+                Attempt::Connected(3),
+                Attempt::Failed(1, 100),
+                Attempt::Connected(2),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_policy_attempts_exhausted() {
+        let (events, attempts) = (Arc::default(), Arc::new(AtomicUsize::new(0)));
+        let policy = ReconnectionPolicy {
+            attempts_max: Some(3),
+            ..policy(2.0)
+        };
+        let start = Instant::now();
+
+        let items = inits(Vec::new(), Arc::clone(&attempts))
+            .with_reconnect_policy(policy, 0, record(&events))
+            .collect::<Vec<_>>()
+            .await;
+
+        // The terminal error is the last item, without initializing again afterwards
+        let [Err(ReconnectionError { attempt, limit, error })] = items.as_slice() else {
+            panic!("expected a single terminal error, got {items:?}");
+        };
+        assert_eq!((*attempt, *limit, *error), (3, ReconnectionLimit::AttemptsExhausted, "refused"));
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        assert_eq!(
+            *events.lock(),
+            vec![
+                Attempt::Failed(1, 100),
+                Attempt::Failed(2, 200),
+                Attempt::Terminated(3, ReconnectionLimit::AttemptsExhausted),
+            ]
+        );
+        assert_eq!(start.elapsed(), Duration::from_millis(300));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_policy_budget_exhausted() {
+        let events = Arc::default();
+        let policy = ReconnectionPolicy {
+            backoff_ms_budget: Some(650),
+            ..policy(2.0)
+        };
+
+        let items = inits(vec![Ok(1)], Arc::default())
+            .with_reconnect_policy(policy, 0, record(&events))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_ok());
+        assert!(matches!(
+            items[1],
+            Err(ReconnectionError {
+                attempt: 3,
+                limit: ReconnectionLimit::BudgetExhausted,
+                ..
+            })
+        ));
+        assert_eq!(
+            events.lock()[1..],
+            [
+                Attempt::Failed(1, 100),
+                Attempt::Failed(2, 200),
+                Attempt::Terminated(3, ReconnectionLimit::BudgetExhausted),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_policy_jitter_bounds() {
+        for jitter in [BackoffJitter::Full, BackoffJitter::Decorrelated] {
+            let events = Arc::default();
+            let policy = ReconnectionPolicy {
+                jitter,
+                attempts_max: Some(50),
+                ..policy(2.0)
             };
 
-            // Transform `ExchangeMessage` into `Transformer::OutputIter`
-            // ie/ IntoIterator<Item = Result<Output, SocketError>>
-            self.transformer
-                .transform(exchange_message)
+            inits(Vec::new(), Arc::default())
+                .with_reconnect_policy(policy, 0, record(&events))
+                .collect::<Vec<_>>()
+                .await;
+
+            let backoffs = events
+                .lock()
+                .iter()
+                .filter_map(|event| match event {
+                    Attempt::Failed(_, backoff) => Some(*backoff),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(backoffs.len(), 49);
+
+            match jitter {
+                BackoffJitter::Full => {
+                    let uncapped = (0..backoffs.len() as u32).map(|attempt| (100 * 2_u64.pow(attempt.min(4))).min(1000));
+                    assert!(backoffs.iter().zip(uncapped).all(|(backoff, max)| *backoff <= max));
+                },
+                _ => {
+                    assert!(backoffs.iter().all(|backoff| (100..=1000).contains(backoff)));
+                    assert!(backoffs.windows(2).all(|pair| pair[1] <= (pair[0] * 2).max(100)));
+                },
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reconnect_backoff_retries_forever() {
+        let policy = ReconnectionBackoffPolicy {
+            backoff_ms_initial: 10,
+            backoff_multiplier: 3,
+            backoff_ms_max: 1000,
+        };
+        let outcomes = vec![Err("refused"), Err("refused"), Err("refused"), Ok(7)];
+        let start = Instant::now();
+
+        let mut streams = std::pin::pin!(inits(outcomes, Arc::default()).with_reconnect_backoff(policy, 0));
+        let stream = streams.next().await.unwrap();
+
+        assert_eq!(stream.collect::<Vec<_>>().await, vec![7]);
+        assert_eq!(start.elapsed(), Duration::from_millis(10 + 30 + 90));
+    }
+
+    #[tokio::test]
+    async fn test_forward_to_non_blocking_tx() {
+        let (tx, rx) = crate::transport::channel::mpsc_unbounded::<crate::transport::channel::KanalSyncChannel, u32>();
+
+        let termination = futures::stream::iter([1_u32, 2, 3]).forward_to(tx).await;
+
+        assert!(matches!(termination, ForwardTermination::StreamEnded));
+        assert_eq!(rx.rx.drain_into(&mut Vec::new()).unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_forward_to_dropped_receiver_send_failed() {
+        let (tx, rx) = crate::transport::channel::mpsc_unbounded::<crate::transport::channel::KanalSyncChannel, u32>();
+        drop(rx);
+
+        // Forwarding stops at the first failed send
+        let polled = AtomicUsize::new(0);
+        let stream = futures::stream::iter([1_u32, 2, 3]).inspect(|_| {
+            polled.fetch_add(1, Ordering::Relaxed);
+        });
+        let termination = stream.forward_to(tx).await;
+
+        assert!(matches!(termination, ForwardTermination::SendFailed(_)));
+        assert_eq!(polled.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_forward_to_async() {
+        let (tx, mut rx) = crate::transport::channel::mpsc_unbounded::<crate::transport::channel::KanalAsyncChannel, u32>();
+
+        let termination = futures::stream::iter([1_u32, 2, 3]).forward_to_async(tx.clone()).await;
+        assert!(matches!(termination, ForwardTermination::StreamEnded));
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(AsyncRx::recv(&mut rx).await.unwrap());
+        }
+        assert_eq!(received, vec![1, 2, 3]);
+
+        drop(rx);
+        let termination = futures::stream::iter([4_u32]).forward_to_async(tx).await;
+        assert!(matches!(termination, ForwardTermination::SendFailed(_)));
+    }
+
+    // America/New_York for 2024 only, DST from 2024-03-10 07:00 UTC until 2024-11-03 06:00 UTC
+    #[derive(Debug, Clone, Copy)]
+    struct NewYork2024;
+
+    impl NewYork2024 {
+        fn est() -> FixedOffset {
+            FixedOffset::west_opt(5 * 3600).unwrap()
+        }
+
+        fn edt() -> FixedOffset {
+            FixedOffset::west_opt(4 * 3600).unwrap()
+        }
+    }
+
+    impl TimeZone for NewYork2024 {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Self
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        // Earlier instants first, so `earliest` picks daylight time when ambiguous
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let valid: Vec<_> = [Self::edt(), Self::est()]
+                .into_iter()
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - TimeDelta::seconds(offset.local_minus_utc().into()))) == *offset)
+                .collect();
+
+            match valid[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => unreachable!(),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let dst = utc_time("2024-03-10T07:00:00Z").naive_utc()..utc_time("2024-11-03T06:00:00Z").naive_utc();
+            if dst.contains(utc) { Self::edt() } else { Self::est() }
+        }
+    }
+
+    fn utc_time(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn at(time: &str) -> NaiveTime {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn test_schedule_next_after_across_timezones() {
+        let shanghai = ReconnectionSchedule::new(FixedOffset::east_opt(8 * 3600).unwrap(), vec![at("20:45:00"), at("08:45:00")]);
+        assert_eq!(shanghai.next_after(utc_time("2024-01-01T00:00:00Z")), Some(utc_time("2024-01-01T00:45:00Z")));
+        assert_eq!(shanghai.next_after(utc_time("2024-01-01T00:45:00Z")), Some(utc_time("2024-01-01T12:45:00Z")));
+        // 21:00 local, the next time falls on the following local day
+        assert_eq!(shanghai.next_after(utc_time("2024-01-01T13:00:00Z")), Some(utc_time("2024-01-02T00:45:00Z")));
+
+        let utc = ReconnectionSchedule::new(Utc, vec![at("00:00:00")]);
+        assert_eq!(utc.next_after(utc_time("2024-01-01T00:00:00Z")), Some(utc_time("2024-01-02T00:00:00Z")));
+
+        let never = ReconnectionSchedule::new(Utc, Vec::new());
+        assert_eq!(never.next_after(utc_time("2024-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_schedule_next_after_across_dst() {
+        // 02:30 is skipped on 2024-03-10, rolled forward to 03:30 daylight time
+        let gap = ReconnectionSchedule::new(NewYork2024, vec![at("02:30:00")]);
+        assert_eq!(gap.next_after(utc_time("2024-03-09T12:00:00Z")), Some(utc_time("2024-03-10T07:30:00Z")));
+        assert_eq!(gap.next_after(utc_time("2024-03-10T07:30:00Z")), Some(utc_time("2024-03-11T06:30:00Z")));
+
+        // 01:30 happens twice on 2024-11-03, only the first counts
+        let repeat = ReconnectionSchedule::new(NewYork2024, vec![at("01:30:00")]);
+        assert_eq!(repeat.next_after(utc_time("2024-11-02T12:00:00Z")), Some(utc_time("2024-11-03T05:30:00Z")));
+        assert_eq!(repeat.next_after(utc_time("2024-11-03T05:30:00Z")), Some(utc_time("2024-11-04T06:30:00Z")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scheduled_reconnect_tears_down_stream() {
+        let start = Instant::now();
+        let clock = move || utc_time("2024-01-01T00:00:00Z") + TimeDelta::from_std(start.elapsed()).unwrap();
+        let schedule = ReconnectionSchedule::new(Utc, vec![at("00:01:00")]);
+
+        let connections = futures::stream::repeat_with(|| futures::stream::iter([Instant::now()]).chain(futures::stream::pending()));
+        let connected = connections
+            .with_scheduled_reconnect_clock(schedule, clock, 0)
+            .flatten()
+            .take(2)
+            .collect::<Vec<_>>()
+            .await;
+
+        // Torn down at the scheduled time, the next is only due a day later
+        assert_eq!(connected, vec![start, start + Duration::from_secs(60)]);
+    }
+
+    const STALE_AFTER: Duration = Duration::from_secs(10);
+
+    type Quote = Result<(&'static str, u32), StaleFeed<&'static str>>;
+
+    fn quote(key: &'static str) -> Quote {
+        Ok((key, 1))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_timeout_fires_once_per_silence() {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<u32, StaleFeed<()>>>();
+        let stream = futures::stream::iter([rx]).with_stale_timeout(STALE_AFTER, std::convert::identity, 0).flatten();
+        let mut stream = std::pin::pin!(stream);
+        let start = Instant::now();
+
+        assert_eq!(stream.next().await, Some(Err(StaleFeed::Stream { silent_for: STALE_AFTER })));
+        assert_eq!(start.elapsed(), STALE_AFTER);
+        assert!(tokio::time::timeout(10 * STALE_AFTER, stream.next()).await.is_err());
+
+        // The next item re-arms the watchdog
+        tx.unbounded_send(Ok(1)).unwrap();
+        assert_eq!(stream.next().await, Some(Ok(1)));
+        let item = Instant::now();
+        assert_eq!(stream.next().await, Some(Err(StaleFeed::Stream { silent_for: STALE_AFTER })));
+        assert_eq!(item.elapsed(), STALE_AFTER);
+
+        drop(tx);
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_timeout_by_key_evicts_stale_keys() {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Quote>();
+        let stream = futures::stream::iter([rx])
+            .with_stale_timeout_by_key(STALE_AFTER, |(key, _): &(&'static str, u32)| Some(*key), std::convert::identity, 0)
+            .flatten();
+        let mut stream = std::pin::pin!(stream);
+        let start = Instant::now();
+
+        tx.unbounded_send(quote("btc")).unwrap();
+        tx.unbounded_send(quote("eth")).unwrap();
+        assert_eq!(stream.next().await, Some(quote("btc")));
+        assert_eq!(stream.next().await, Some(quote("eth")));
+
+        tokio::time::sleep(STALE_AFTER / 2).await;
+        tx.unbounded_send(quote("btc")).unwrap();
+        assert_eq!(stream.next().await, Some(quote("btc")));
+
+        assert_eq!(
+            stream.next().await,
+            Some(Err(StaleFeed::Instrument {
+                key: "eth",
+                silent_for: STALE_AFTER
+            }))
+        );
+        assert_eq!(start.elapsed(), STALE_AFTER);
+
+        assert_eq!(stream.next().await, Some(Err(StaleFeed::Stream { silent_for: STALE_AFTER })));
+        assert_eq!(
+            stream.next().await,
+            Some(Err(StaleFeed::Instrument {
+                key: "btc",
+                silent_for: STALE_AFTER
+            }))
+        );
+        assert_eq!(start.elapsed(), STALE_AFTER * 3 / 2);
+
+        // Evicted keys stay quiet until they yield again
+        assert!(tokio::time::timeout(10 * STALE_AFTER, stream.next()).await.is_err());
+        tx.unbounded_send(quote("eth")).unwrap();
+        assert_eq!(stream.next().await, Some(quote("eth")));
+        assert_eq!(stream.next().await, Some(Err(StaleFeed::Stream { silent_for: STALE_AFTER })));
+        assert_eq!(
+            stream.next().await,
+            Some(Err(StaleFeed::Instrument {
+                key: "eth",
+                silent_for: STALE_AFTER
+            }))
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct NumbersError(String);
+
+    impl From<Box<dyn std::error::Error>> for NumbersError {
+        fn from(error: Box<dyn std::error::Error>) -> Self {
+            Self(error.to_string())
+        }
+    }
+
+    // Yields every number of a JSON array, or of a JSON array of numeric strings when borrowed.
+    #[derive(Debug, Default)]
+    struct Numbers;
+
+    impl Transformer for Numbers {
+        type Error = NumbersError;
+        type Input = Vec<i64>;
+        type Output = i64;
+        type OutputIter = Vec<Result<i64, NumbersError>>;
+
+        fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+            input.into_iter().map(Ok).collect()
+        }
+    }
+
+    impl BorrowedTransformer for Numbers {
+        type Error = NumbersError;
+        type Input<'de> = Vec<&'de str>;
+        type Output = i64;
+        type OutputIter = Vec<Result<i64, NumbersError>>;
+
+        fn transform(&mut self, input: Self::Input<'_>) -> Self::OutputIter {
+            input
                 .into_iter()
-                .for_each(|output_result: Result<StreamTransformer::Output, StreamTransformer::Error>| self.buffer.push_back(output_result));
+                .map(|number| number.parse().map_err(|_| NumbersError(number.to_owned())))
+                .collect()
+        }
+    }
 
-            // if let Some(output) = self.buffer.pop_front() {
-            //     return Poll::Ready(Some(output));
-            // }
+    fn frames(frames: &[&str]) -> futures::stream::Iter<std::vec::IntoIter<Result<WsMessage, WsError>>> {
+        let mut frames = frames.iter().map(|frame| WsMessage::text(*frame)).map(Ok).collect::<Vec<_>>();
+        frames.insert(1, Ok(WsMessage::Ping(Default::default())));
+        futures::stream::iter(frames)
+    }
+
+    fn exchange_stream(frames: &[&str]) -> ExchangeStream<WebsocketJsonParser, futures::stream::Iter<std::vec::IntoIter<Result<WsMessage, WsError>>>, Numbers> {
+        ExchangeStream {
+            stream: self::frames(frames),
+            transformer: Numbers,
+            buffer: VecDeque::new(),
+            protocol_marker: PhantomData,
         }
     }
+
+    #[tokio::test]
+    async fn test_exchange_stream_skips_and_yields_parse_errors() {
+        let outputs = exchange_stream(&["[1,2]", "oops", "[]", "[3]"]).collect::<Vec<_>>().await;
+
+        assert_eq!(outputs.len(), 4);
+        assert_eq!(outputs[..2], [Ok(1), Ok(2)]);
+        assert!(outputs[2].is_err());
+        assert_eq!(outputs[3], Ok(3));
+    }
+
+    #[tokio::test]
+    async fn test_stamped_stream_carries_buffered_outputs() {
+        let mut stream = exchange_stream(&["[1,2]", "[3]"]);
+        assert_eq!(stream.next().await, Some(Ok(1)));
+
+        let stamped = stream.with_latency_stamps(|_| None).collect::<Vec<_>>().await;
+        let values = stamped.iter().map(|output| output.as_ref().unwrap().value).collect::<Vec<_>>();
+        assert_eq!(values, vec![2, 3]);
+
+        for output in stamped {
+            let LatencyStamps {
+                time_exchange,
+                time_received,
+                time_parsed,
+                time_transformed,
+            } = output.unwrap().stamps;
+            assert_eq!(time_exchange, None);
+            assert!(time_received <= time_parsed && time_parsed <= time_transformed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_borrowed_stream_matches_owned() {
+        let stream = BorrowedExchangeStream::<WebsocketJsonParser, _, Numbers>::new(frames(&[r#"["1","2"]"#, "oops", r#"["x","3"]"#]), Numbers);
+        let outputs = stream.collect::<Vec<_>>().await;
+
+        assert_eq!(outputs.len(), 5);
+        assert_eq!(outputs[..2], [Ok(1), Ok(2)]);
+        assert!(outputs[2].is_err());
+        assert_eq!(outputs[3..], [Err(NumbersError("x".to_owned())), Ok(3)]);
+    }
 }
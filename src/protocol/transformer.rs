@@ -1,10 +1,388 @@
+use std::{hash::Hash, marker::PhantomData};
+
+use fnv::FnvHashMap;
 use serde::Deserialize;
 
 pub trait Transformer {
     type Error;
-    type Input: for<'de> Deserialize<'de>;
+    type Input;
     type Output;
     type OutputIter: IntoIterator<Item = Result<Self::Output, Self::Error>>;
 
     fn transform(&mut self, input: Self::Input) -> Self::OutputIter;
 }
+
+pub trait BorrowedTransformer {
+    type Error;
+    type Input<'de>: Deserialize<'de>;
+    type Output;
+    type OutputIter: IntoIterator<Item = Result<Self::Output, Self::Error>>;
+
+    fn transform(&mut self, input: Self::Input<'_>) -> Self::OutputIter;
+}
+
+// Closures passed to `map_output` and `filter` are cloned into the iterator of every input, so they
+// should not carry state, a `StatefulTransformer` is the place for that.
+pub trait TransformerExt
+where
+    Self: Transformer + Sized,
+{
+    fn map_output<FnMap, Output>(self, op: FnMap) -> MapOutput<Self, FnMap>
+    where
+        FnMap: Fn(Self::Output) -> Output + Clone,
+    {
+        MapOutput { inner: self, op }
+    }
+
+    fn filter<FnFilter>(self, predicate: FnFilter) -> Filter<Self, FnFilter>
+    where
+        FnFilter: Fn(&Self::Output) -> bool + Clone,
+    {
+        Filter { inner: self, predicate }
+    }
+
+    fn and_then<Next>(self, next: Next) -> AndThen<Self, Next>
+    where
+        Next: Transformer<Input = Self::Output>,
+        Next::Error: From<Self::Error>,
+    {
+        AndThen { first: self, next }
+    }
+}
+
+impl<T> TransformerExt for T where T: Transformer {}
+
+#[derive(Debug)]
+pub struct MapOutput<Inner, FnMap> {
+    inner: Inner,
+    op: FnMap,
+}
+
+impl<Inner, FnMap, Output> Transformer for MapOutput<Inner, FnMap>
+where
+    Inner: Transformer,
+    FnMap: Fn(Inner::Output) -> Output + Clone,
+{
+    type Error = Inner::Error;
+    type Input = Inner::Input;
+    type Output = Output;
+    type OutputIter = MapOutputIter<<Inner::OutputIter as IntoIterator>::IntoIter, FnMap>;
+
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        MapOutputIter {
+            outputs: self.inner.transform(input).into_iter(),
+            op: self.op.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MapOutputIter<Outputs, FnMap> {
+    outputs: Outputs,
+    op: FnMap,
+}
+
+impl<Outputs, FnMap, Output, Error, Mapped> Iterator for MapOutputIter<Outputs, FnMap>
+where
+    Outputs: Iterator<Item = Result<Output, Error>>,
+    FnMap: Fn(Output) -> Mapped,
+{
+    type Item = Result<Mapped, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.outputs.next().map(|result| result.map(&self.op))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.outputs.size_hint()
+    }
+}
+
+// Only successful outputs are subject to the predicate, errors always pass through.
+#[derive(Debug)]
+pub struct Filter<Inner, FnFilter> {
+    inner: Inner,
+    predicate: FnFilter,
+}
+
+impl<Inner, FnFilter> Transformer for Filter<Inner, FnFilter>
+where
+    Inner: Transformer,
+    FnFilter: Fn(&Inner::Output) -> bool + Clone,
+{
+    type Error = Inner::Error;
+    type Input = Inner::Input;
+    type Output = Inner::Output;
+    type OutputIter = FilterIter<<Inner::OutputIter as IntoIterator>::IntoIter, FnFilter>;
+
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        FilterIter {
+            outputs: self.inner.transform(input).into_iter(),
+            predicate: self.predicate.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FilterIter<Outputs, FnFilter> {
+    outputs: Outputs,
+    predicate: FnFilter,
+}
+
+impl<Outputs, FnFilter, Output, Error> Iterator for FilterIter<Outputs, FnFilter>
+where
+    Outputs: Iterator<Item = Result<Output, Error>>,
+    FnFilter: Fn(&Output) -> bool,
+{
+    type Item = Result<Output, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.outputs.by_ref().find(|result| result.as_ref().map_or(true, &self.predicate))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.outputs.size_hint().1)
+    }
+}
+
+#[derive(Debug)]
+pub struct AndThen<First, Next> {
+    first: First,
+    next: Next,
+}
+
+impl<First, Next> Transformer for AndThen<First, Next>
+where
+    First: Transformer,
+    Next: Transformer<Input = First::Output>,
+    Next::Error: From<First::Error>,
+{
+    type Error = Next::Error;
+    type Input = First::Input;
+    type Output = Next::Output;
+    type OutputIter = AndThenIter<<Next::OutputIter as IntoIterator>::IntoIter, Next::Output, Next::Error>;
+
+    // `next` has to run before returning, but only the outputs following the first one are buffered,
+    // so the common single output case does not allocate.
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        let mut intermediates = self.first.transform(input).into_iter();
+        let head = intermediates.next().map(|result| self.next_step(result));
+        let tail = intermediates.flat_map(|result| self.next_step(result)).collect::<Vec<_>>();
+
+        AndThenIter { head, tail: tail.into_iter() }
+    }
+}
+
+impl<First, Next> AndThen<First, Next>
+where
+    First: Transformer,
+    Next: Transformer<Input = First::Output>,
+    Next::Error: From<First::Error>,
+{
+    fn next_step(&mut self, intermediate: Result<First::Output, First::Error>) -> AndThenStep<<Next::OutputIter as IntoIterator>::IntoIter, Next::Error> {
+        match intermediate {
+            Ok(intermediate) => AndThenStep::Outputs(self.next.transform(intermediate).into_iter()),
+            Err(error) => AndThenStep::Error(Some(Next::Error::from(error))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AndThenIter<Outputs, Output, Error> {
+    head: Option<AndThenStep<Outputs, Error>>,
+    tail: std::vec::IntoIter<Result<Output, Error>>,
+}
+
+impl<Outputs, Output, Error> Iterator for AndThenIter<Outputs, Output, Error>
+where
+    Outputs: Iterator<Item = Result<Output, Error>>,
+{
+    type Item = Result<Output, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.head.as_mut().and_then(Iterator::next).or_else(|| self.tail.next())
+    }
+}
+
+#[derive(Debug)]
+enum AndThenStep<Outputs, Error> {
+    Outputs(Outputs),
+    Error(Option<Error>),
+}
+
+impl<Outputs, Output, Error> Iterator for AndThenStep<Outputs, Error>
+where
+    Outputs: Iterator<Item = Result<Output, Error>>,
+{
+    type Item = Result<Output, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Outputs(outputs) => outputs.next(),
+            Self::Error(error) => error.take().map(Err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StatefulTransformer<Key, State, Input, OutputIter, FnKey, FnTransform> {
+    states: FnvHashMap<Key, State>,
+    key: FnKey,
+    op: FnTransform,
+    marker: PhantomData<fn(Input) -> OutputIter>,
+}
+
+impl<Key, State, Input, OutputIter, FnKey, FnTransform> StatefulTransformer<Key, State, Input, OutputIter, FnKey, FnTransform>
+where
+    Key: Eq + Hash,
+    State: Default,
+    FnKey: Fn(&Input) -> Key,
+    FnTransform: FnMut(&mut State, Input) -> OutputIter,
+{
+    pub fn new(key: FnKey, op: FnTransform) -> Self {
+        Self {
+            states: FnvHashMap::default(),
+            key,
+            op,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn state(&self, key: &Key) -> Option<&State> {
+        self.states.get(key)
+    }
+}
+
+impl<Key, State, Input, OutputIter, Output, Error, FnKey, FnTransform> Transformer for StatefulTransformer<Key, State, Input, OutputIter, FnKey, FnTransform>
+where
+    Key: Eq + Hash,
+    State: Default,
+    OutputIter: IntoIterator<Item = Result<Output, Error>>,
+    FnKey: Fn(&Input) -> Key,
+    FnTransform: FnMut(&mut State, Input) -> OutputIter,
+{
+    type Error = Error;
+    type Input = Input;
+    type Output = Output;
+    type OutputIter = OutputIter;
+
+    fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+        let key = (self.key)(&input);
+        let state = self.states.entry(key).or_default();
+
+        (self.op)(state, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    // Parses every comma separated number, yielding the unparsable ones as errors
+    #[derive(Debug)]
+    struct Numbers;
+
+    impl Transformer for Numbers {
+        type Error = String;
+        type Input = &'static str;
+        type Output = u32;
+        type OutputIter = Vec<Result<u32, String>>;
+
+        fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+            input.split(',').map(|number| number.parse().map_err(|_| number.to_owned())).collect()
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum RepeatError {
+        Parse(String),
+        TooMany(u32),
+    }
+
+    impl From<String> for RepeatError {
+        fn from(error: String) -> Self {
+            Self::Parse(error)
+        }
+    }
+
+    // Repeats every number as many times as its value, up to 3
+    #[derive(Debug, Default)]
+    struct Repeat {
+        inputs: usize,
+    }
+
+    impl Transformer for Repeat {
+        type Error = RepeatError;
+        type Input = u32;
+        type Output = u32;
+        type OutputIter = Vec<Result<u32, RepeatError>>;
+
+        fn transform(&mut self, input: Self::Input) -> Self::OutputIter {
+            self.inputs += 1;
+            match input {
+                0..=3 => vec![Ok(input); input as usize],
+                _ => vec![Err(RepeatError::TooMany(input))],
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_output_keeps_errors() {
+        let mut transformer = Numbers.map_output(|number| number * 10);
+
+        let outputs = transformer.transform("1,x,3").collect::<Vec<_>>();
+        assert_eq!(outputs, vec![Ok(10), Err("x".to_owned()), Ok(30)]);
+    }
+
+    #[test]
+    fn test_filter_passes_errors_through() {
+        let mut transformer = Numbers.filter(|number| number % 2 == 0);
+
+        let outputs = transformer.transform("1,2,x,4,5").collect::<Vec<_>>();
+        assert_eq!(outputs, vec![Ok(2), Err("x".to_owned()), Ok(4)]);
+        assert_eq!(transformer.transform("1,3").count(), 0);
+    }
+
+    #[test]
+    fn test_and_then_chains_in_order() {
+        let mut transformer = Numbers.and_then(Repeat::default());
+
+        let outputs = transformer.transform("2,x,0,9,1").collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            vec![Ok(2), Ok(2), Err(RepeatError::Parse("x".to_owned())), Err(RepeatError::TooMany(9)), Ok(1)]
+        );
+        assert_eq!(transformer.next.inputs, 4);
+
+        let outputs = transformer.transform("3").collect::<Vec<_>>();
+        assert_eq!(outputs, vec![Ok(3), Ok(3), Ok(3)]);
+    }
+
+    #[test]
+    fn test_combinators_compose() {
+        let mut transformer = Numbers.filter(|number| *number > 1).and_then(Repeat::default()).map_output(|number| number + 1);
+
+        let outputs = transformer.transform("1,2").collect::<Vec<_>>();
+        assert_eq!(outputs, vec![Ok(3), Ok(3)]);
+    }
+
+    #[test]
+    fn test_stateful_transformer_keeps_state_per_key() {
+        let mut transformer = StatefulTransformer::new(
+            |(instrument, _): &(&'static str, u32)| *instrument,
+            |volume: &mut u32, (instrument, quantity): (&'static str, u32)| {
+                *volume += quantity;
+                std::iter::once(Ok::<_, Infallible>((instrument, *volume)))
+            },
+        );
+
+        assert_eq!(transformer.transform(("btc", 2)).collect::<Vec<_>>(), vec![Ok(("btc", 2))]);
+        assert_eq!(transformer.transform(("eth", 5)).collect::<Vec<_>>(), vec![Ok(("eth", 5))]);
+        assert_eq!(transformer.transform(("btc", 3)).collect::<Vec<_>>(), vec![Ok(("btc", 5))]);
+
+        assert_eq!(transformer.state(&"btc"), Some(&5));
+        assert_eq!(transformer.state(&"sol"), None);
+    }
+}
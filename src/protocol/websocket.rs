@@ -1,17 +1,363 @@
+use std::{cell::RefCell, fmt::Debug, io::ErrorKind};
+
+use serde::{Deserialize, de::DeserializeOwned};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
     MaybeTlsStream,
     connect_async,
     tungstenite::{
+        Bytes,
         Utf8Bytes,
         client::IntoClientRequest,
         error::ProtocolError,
         protocol::{CloseFrame, frame::Frame},
     },
 };
+use tracing::debug;
+
+use crate::protocol::{
+    error::SocketError,
+    parser::{ProtocolParser, WebsocketBorrowedParser, WebsocketParser},
+};
+
+pub type WebSocket = tokio_tungstenite::WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub type WsSink = futures::stream::SplitSink<WebSocket, WsMessage>;
+pub type WsStream = futures::stream::SplitStream<WebSocket>;
+pub type WsMessage = tokio_tungstenite::tungstenite::Message;
+pub type WsError = tokio_tungstenite::tungstenite::Error;
 
 pub type BarterWebSocket = barter_integration::protocol::websocket::WebSocket;
 pub type BarterWsSink = barter_integration::protocol::websocket::WsSink;
 pub type BarterWsStream = barter_integration::protocol::websocket::WsStream;
 pub type BarterWsMessage = barter_integration::protocol::websocket::WsMessage;
 pub type BarterWsError = barter_integration::protocol::websocket::WsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WebsocketJsonParser;
+
+impl ProtocolParser for WebsocketJsonParser {}
+
+impl WebsocketParser for WebsocketJsonParser {
+    type Stream = WebSocket;
+    type Message = WsMessage;
+    type Error = WsError;
+
+    fn parse<Output>(input: Result<Self::Message, Self::Error>) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: DeserializeOwned,
+    {
+        let output = match input {
+            Ok(WsMessage::Text(text)) => process_text(text),
+            Ok(WsMessage::Binary(binary)) => process_binary(binary),
+            Ok(WsMessage::Ping(ping)) => process_ping(ping),
+            Ok(WsMessage::Pong(pong)) => process_pong(pong),
+            Ok(WsMessage::Close(close_frame)) => process_close_frame(close_frame),
+            Ok(WsMessage::Frame(frame)) => process_frame(frame),
+            Err(error) => Some(Err(SocketError::WebSocket(Box::new(error)))),
+        };
+
+        output.map(|result| result.map_err(Into::into))
+    }
+}
+
+impl WebsocketBorrowedParser for WebsocketJsonParser {
+    type Buffer = JsonParseBuffer;
+
+    fn parse_borrowed<'de, Output>(
+        input: Result<Self::Message, Self::Error>,
+        buffer: &'de mut Self::Buffer,
+    ) -> Option<Result<Output, Box<dyn std::error::Error>>>
+    where
+        Output: Deserialize<'de>,
+    {
+        let output = match input {
+            Ok(WsMessage::Text(text)) => Some(deserialize_json_in_place(text.as_bytes(), buffer)),
+            Ok(WsMessage::Binary(binary)) => Some(deserialize_json_in_place(&binary, buffer)),
+            Ok(WsMessage::Ping(ping)) => process_ping(ping),
+            Ok(WsMessage::Pong(pong)) => process_pong(pong),
+            Ok(WsMessage::Close(close_frame)) => process_close_frame(close_frame),
+            Ok(WsMessage::Frame(frame)) => process_frame(frame),
+            Err(error) => Some(Err(SocketError::WebSocket(Box::new(error)))),
+        };
+
+        output.map(|result| result.map_err(Into::into))
+    }
+}
+
+#[derive(Default)]
+pub struct JsonParseBuffer {
+    payload: Vec<u8>,
+    simd: simd_json::Buffers,
+}
+
+impl Debug for JsonParseBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonParseBuffer")
+            .field("capacity", &self.payload.capacity())
+            .finish_non_exhaustive()
+    }
+}
+
+pub fn process_text<ExchangeMessage>(payload: Utf8Bytes) -> Option<Result<ExchangeMessage, SocketError>>
+where
+    ExchangeMessage: DeserializeOwned,
+{
+    Some(deserialize_json(payload.as_bytes()))
+}
+
+pub fn process_binary<ExchangeMessage>(payload: Bytes) -> Option<Result<ExchangeMessage, SocketError>>
+where
+    ExchangeMessage: DeserializeOwned,
+{
+    Some(deserialize_json(&payload))
+}
+
+pub fn process_ping<ExchangeMessage>(ping: Bytes) -> Option<Result<ExchangeMessage, SocketError>> {
+    debug!(payload = ?ping, "Received Ping WebSocket message.");
+
+    None
+}
+
+pub fn process_pong<ExchangeMessage>(pong: Bytes) -> Option<Result<ExchangeMessage, SocketError>> {
+    debug!(payload = ?pong, "Received Pong WebSocket message.");
+
+    None
+}
+
+pub fn process_close_frame<ExchangeMessage>(close_frame: Option<CloseFrame>) -> Option<Result<ExchangeMessage, SocketError>> {
+    debug!(payload = ?close_frame, "Received CloseFrame WebSocket message.");
+
+    None
+}
+
+pub fn process_frame<ExchangeMessage>(frame: Frame) -> Option<Result<ExchangeMessage, SocketError>> {
+    debug!(payload = ?frame, "Received unexpected Frame WebSocket message.");
+
+    None
+}
+
+fn deserialize_json<ExchangeMessage>(payload: &[u8]) -> Result<ExchangeMessage, SocketError>
+where
+    ExchangeMessage: DeserializeOwned,
+{
+    thread_local! {
+        static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // simd-json rewrites its input, so it parses a reused per thread copy and leaves `payload`
+    // intact for the fallback, without allocating per message
+    let simd = SCRATCH.with_borrow_mut(|scratch| {
+        scratch.clear();
+        scratch.extend_from_slice(payload);
+        simd_json::serde::from_slice::<ExchangeMessage>(scratch)
+    });
+    if let Ok(message) = simd {
+        return Ok(message);
+    }
+
+    serde_json::from_slice::<ExchangeMessage>(payload).map_err(|error| {
+        let payload = String::from_utf8_lossy(payload).into_owned();
+        debug!(?error, %payload, "Failed to deserialize WebSocket message.");
+
+        SocketError::Deserialise { error, payload }
+    })
+}
+
+fn deserialize_json_in_place<'de, ExchangeMessage>(payload: &[u8], buffer: &'de mut JsonParseBuffer) -> Result<ExchangeMessage, SocketError>
+where
+    ExchangeMessage: Deserialize<'de>,
+{
+    // The frame payload is shared, so it is copied once into the reusable buffer which simd-json then rewrites
+    let JsonParseBuffer { payload: scratch, simd } = buffer;
+    scratch.clear();
+    scratch.extend_from_slice(payload);
+
+    simd_json::serde::from_slice_with_buffers::<ExchangeMessage>(scratch, simd).map_err(|error| {
+        let payload = String::from_utf8_lossy(payload).into_owned();
+        debug!(?error, %payload, "Failed to deserialize WebSocket message in place.");
+
+        SocketError::DeserialiseInPlace { error, payload }
+    })
+}
+
+pub async fn connect<Request>(request: Request) -> Result<WebSocket, SocketError>
+where
+    Request: IntoClientRequest + Unpin + Debug,
+{
+    debug!(?request, "Establishing WebSocket connection.");
+
+    connect_async(request)
+        .await
+        .map(|(websocket, _)| websocket)
+        .map_err(|error| SocketError::WebSocket(Box::new(error)))
+}
+
+// Only I/O errors signalling a lost connection count, eg/ a `WouldBlock` leaves the socket usable.
+pub fn is_websocket_disconnected(error: &WsError) -> bool {
+    match error {
+        WsError::ConnectionClosed
+        | WsError::AlreadyClosed
+        | WsError::Protocol(ProtocolError::SendAfterClosing | ProtocolError::ResetWithoutClosingHandshake) => true,
+        WsError::Io(error) => matches!(
+            error.kind(),
+            ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::NotConnected
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::TimedOut
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use rust_decimal_macros::dec;
+    use tokio_tungstenite::tungstenite::protocol::{CloseFrame, frame::coding::CloseCode};
+
+    use super::*;
+
+    // Recorded from Binance spot and OKX public streams.
+    const BINANCE_TRADE: &str = r#"{"e":"trade","E":1700000000001,"s":"BTCUSDT","t":12345,"p":"64123.10","q":"0.01500","T":1700000000000,"m":true,"M":true}"#;
+    const OKX_TRADE: &str = r#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[{"instId":"BTC-USDT","tradeId":"130639474","px":"42219.9","sz":"0.12060306","side":"buy","ts":"1630048897897"}]}"#;
+    const OKX_SUBSCRIBED: &str = r#"{"event":"subscribe","arg":{"channel":"trades","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct BinanceTrade {
+        #[serde(rename = "s")]
+        symbol: String,
+        #[serde(rename = "t")]
+        id: u64,
+        #[serde(rename = "p")]
+        price: rust_decimal::Decimal,
+        #[serde(rename = "m")]
+        buyer_is_maker: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct OkxTrades {
+        data: Vec<OkxTrade>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct OkxTrade {
+        #[serde(rename = "instId")]
+        instrument: String,
+        #[serde(rename = "px")]
+        price: rust_decimal::Decimal,
+    }
+
+    fn parse<Output>(message: Result<WsMessage, WsError>) -> Option<Result<Output, SocketError>>
+    where
+        Output: DeserializeOwned,
+    {
+        WebsocketJsonParser::parse::<Output>(message).map(|result| result.map_err(|error| *error.downcast::<SocketError>().unwrap()))
+    }
+
+    #[test]
+    fn test_parse_text_frame() {
+        let trade = parse::<BinanceTrade>(Ok(WsMessage::text(BINANCE_TRADE))).unwrap().unwrap();
+
+        assert_eq!(
+            trade,
+            BinanceTrade {
+                symbol: "BTCUSDT".to_owned(),
+                id: 12345,
+                price: dec!(64123.10),
+                buyer_is_maker: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_binary_frame() {
+        let trades = parse::<OkxTrades>(Ok(WsMessage::binary(OKX_TRADE.as_bytes().to_vec()))).unwrap().unwrap();
+
+        assert_eq!(
+            trades.data,
+            vec![OkxTrade {
+                instrument: "BTC-USDT".to_owned(),
+                price: dec!(42219.9),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_scratch_reused_across_frames() {
+        // A shorter frame after a longer one must not see the stale tail of the scratch copy
+        parse::<OkxTrades>(Ok(WsMessage::text(OKX_TRADE))).unwrap().unwrap();
+        let trade = parse::<BinanceTrade>(Ok(WsMessage::text(BINANCE_TRADE))).unwrap().unwrap();
+
+        assert_eq!(trade.id, 12345);
+    }
+
+    #[test]
+    fn test_parse_control_frames_skipped() {
+        let close = CloseFrame {
+            code: CloseCode::Normal,
+            reason: "bye".into(),
+        };
+
+        assert!(parse::<OkxTrades>(Ok(WsMessage::Ping(Bytes::from_static(b"ping")))).is_none());
+        assert!(parse::<OkxTrades>(Ok(WsMessage::Pong(Bytes::new()))).is_none());
+        assert!(parse::<OkxTrades>(Ok(WsMessage::Close(Some(close)))).is_none());
+        assert!(parse::<OkxTrades>(Ok(WsMessage::Close(None))).is_none());
+    }
+
+    #[test]
+    fn test_parse_unexpected_message_shape() {
+        let error = parse::<OkxTrades>(Ok(WsMessage::text(OKX_SUBSCRIBED))).unwrap().unwrap_err();
+
+        assert!(matches!(error, SocketError::Deserialise { payload, .. } if payload == OKX_SUBSCRIBED));
+    }
+
+    #[test]
+    fn test_parse_malformed_payload() {
+        let error = parse::<BinanceTrade>(Ok(WsMessage::text(r#"{"e":"trade","s":"BTC"#))).unwrap().unwrap_err();
+
+        assert!(matches!(error, SocketError::Deserialise { .. }));
+    }
+
+    #[test]
+    fn test_parse_websocket_error() {
+        let error = parse::<BinanceTrade>(Err(WsError::ConnectionClosed)).unwrap().unwrap_err();
+
+        assert!(matches!(error, SocketError::WebSocket(error) if matches!(*error, WsError::ConnectionClosed)));
+    }
+
+    #[test]
+    fn test_parse_borrowed_frame() {
+        #[derive(Deserialize)]
+        struct Symbol<'a> {
+            #[serde(rename = "s")]
+            symbol: &'a str,
+        }
+
+        let mut buffer = JsonParseBuffer::default();
+        let symbol = WebsocketJsonParser::parse_borrowed::<Symbol>(Ok(WsMessage::text(BINANCE_TRADE)), &mut buffer)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(symbol.symbol, "BTCUSDT");
+    }
+
+    #[test]
+    fn test_is_websocket_disconnected() {
+        let io = |kind| WsError::Io(io::Error::from(kind));
+
+        assert!(is_websocket_disconnected(&WsError::ConnectionClosed));
+        assert!(is_websocket_disconnected(&WsError::AlreadyClosed));
+        assert!(is_websocket_disconnected(&WsError::Protocol(ProtocolError::SendAfterClosing)));
+        assert!(is_websocket_disconnected(&WsError::Protocol(ProtocolError::ResetWithoutClosingHandshake)));
+        assert!(is_websocket_disconnected(&io(ErrorKind::ConnectionReset)));
+        assert!(is_websocket_disconnected(&io(ErrorKind::UnexpectedEof)));
+        assert!(is_websocket_disconnected(&io(ErrorKind::BrokenPipe)));
+
+        assert!(!is_websocket_disconnected(&io(ErrorKind::WouldBlock)));
+        assert!(!is_websocket_disconnected(&io(ErrorKind::Interrupted)));
+        assert!(!is_websocket_disconnected(&WsError::Utf8("invalid".to_owned())));
+    }
+}
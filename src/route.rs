@@ -1,4 +1,5 @@
 use derive_more::Constructor;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Constructor)]
 pub struct RoutingKey<K, V> {
@@ -14,13 +15,37 @@ pub struct RoutingTable<Front, Exchange, Asset, Instrument> {
     instruments: Vec<RoutingKey<InstrumentIndex, Instrument>>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Constructor)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Constructor)]
 pub struct FrontIndex(usize);
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Constructor)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Constructor)]
 pub struct ExchangeIndex(usize);
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Constructor)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Constructor, Serialize, Deserialize)]
 pub struct AssetIndex(usize);
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Constructor)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Constructor, Serialize, Deserialize)]
 pub struct InstrumentIndex(usize);
+
+impl FrontIndex {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl ExchangeIndex {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl AssetIndex {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl InstrumentIndex {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
@@ -67,6 +67,14 @@ pub trait SyncTx<T> {
     fn send(&self, item: T) -> Result<(), Self::SendError>;
 }
 
+// Marker for a `SyncTx` whose send never blocks, eg/ an unbounded channel, so it is safe to call
+// from within an async task.
+pub trait NonBlockingTx<T>
+where
+    Self: SyncTx<T>,
+{
+}
+
 pub trait AsyncTx<T> {
     type SendError: Debug;
     type SendFuture<'a>: Future<Output = Result<(), Self::SendError>> + 'a
@@ -98,6 +106,13 @@ where
     }
 }
 
+impl<K, T> NonBlockingTx<T> for UnboundedTx<SyncChannel<K>, T>
+where
+    K: SyncChannelKind,
+    K::Sender<T>: Debug + Clone + SendSyncLike<T>,
+{
+}
+
 impl<K, T> AsyncTx<T> for UnboundedTx<AsyncChannel<K>, T>
 where
     K: AsyncChannelKind,
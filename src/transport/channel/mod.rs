@@ -1,5 +1,5 @@
 mod base;
 mod kanal;
 
-pub use base::{AsyncRx, AsyncTx, SyncRx, SyncTx, UnboundedRx, UnboundedTx, mpsc_unbounded};
+pub use base::{AsyncRx, AsyncTx, NonBlockingTx, SyncRx, SyncTx, UnboundedRx, UnboundedTx, mpsc_unbounded};
 pub use kanal::{KanalAsyncChannel, KanalSyncChannel};